@@ -5,4 +5,17 @@ use bevy::asset::Asset;
 /// and inserting appropriate systems when registering with the app
 pub trait CodeAsset: Asset {
     fn bytes(&self) -> &[u8];
+
+    /// The file extensions of this asset type, used to route scripts in a [`crate::hosts::ScriptComponent`] to their host
+    fn extensions() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns true if the given asset path has one of the extensions of this asset type
+    fn matches_path(path: &str) -> bool {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| Self::extensions().contains(&ext))
+    }
 }
@@ -1,6 +1,14 @@
-use bevy::prelude::Event;
+use std::any::TypeId;
 
-use crate::{error::ScriptError, hosts::Recipients};
+use bevy::{
+    prelude::{Entity, Event},
+    reflect::Reflect,
+};
+
+use crate::{
+    error::ScriptError,
+    hosts::{Recipients, ScriptHost},
+};
 
 /// An error coming from a script
 #[derive(Debug, Event)]
@@ -15,6 +23,86 @@ pub struct ScriptLoaded {
     pub sid: u32,
 }
 
+/// An event emitted when a loaded script was enabled via [`crate::hosts::Script::set_enabled`],
+/// the script's `on_enable` hook is called right after.
+#[derive(Clone, Debug, Event)]
+pub struct ScriptEnabled {
+    pub sid: u32,
+    pub entity: Entity,
+}
+
+/// An event emitted when a loaded script was disabled via [`crate::hosts::Script::set_enabled`],
+/// the script's `on_disable` hook is called right after.
+#[derive(Clone, Debug, Event)]
+pub struct ScriptDisabled {
+    pub sid: u32,
+    pub entity: Entity,
+}
+
+/// A script event understood by every script host, whose arguments are reflected values.
+///
+/// Each host converts the arguments into script values via the proxies registered by its scripting API
+/// (e.g. `ReflectLuaProxyable` or `ReflectRhaiProxyable`), so the same event only needs to be sent once for all languages.
+/// Unlike host specific events, these are not consumed by the script event handlers: each handler calls the
+/// scripts with events whose priority lies in its range.
+#[derive(Debug, Event)]
+pub struct ReflectedScriptEvent {
+    pub hook_name: String,
+    pub args: Vec<Box<dyn Reflect>>,
+    pub recipients: Recipients,
+    pub priority: u32,
+    /// the type id of the only script host handling this event, all hosts handle it if None
+    pub host: Option<TypeId>,
+}
+
+impl ReflectedScriptEvent {
+    /// Creates an event calling the given hook on all scripts without any arguments
+    pub fn new(hook_name: impl Into<String>, priority: u32) -> Self {
+        Self {
+            hook_name: hook_name.into(),
+            args: Default::default(),
+            recipients: Recipients::All,
+            priority,
+            host: None,
+        }
+    }
+
+    /// Appends an argument passed to the hook
+    pub fn with_arg(mut self, arg: impl Reflect) -> Self {
+        self.args.push(Box::new(arg));
+        self
+    }
+
+    /// Sets the scripts this event is sent to
+    pub fn with_recipients(mut self, recipients: Recipients) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Restricts this event to scripts of the given host
+    pub fn for_host<H: ScriptHost>(mut self) -> Self {
+        self.host = Some(TypeId::of::<H>());
+        self
+    }
+
+    /// Copies this event, its arguments are copied via [`Reflect::clone_value`]
+    /// and so may be dynamic representations of their original types
+    pub fn clone_value(&self) -> Self {
+        Self {
+            hook_name: self.hook_name.clone(),
+            args: self.args.iter().map(|arg| arg.clone_value()).collect(),
+            recipients: self.recipients.clone(),
+            priority: self.priority,
+            host: self.host,
+        }
+    }
+
+    /// Returns true if scripts of the given host should handle this event
+    pub fn is_for_host<H: ScriptHost>(&self) -> bool {
+        self.host.is_none_or(|host| host == TypeId::of::<H>())
+    }
+}
+
 /// A trait for events to be handled by scripts
 pub trait ScriptEvent: Send + Sync + Clone + Event + 'static {
     /// Retrieves the recipient scripts for this event
@@ -0,0 +1,371 @@
+//! Fault isolation for misbehaving scripts
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::Instant};
+
+use crate::{error::ScriptError, event::ScriptErrorEvent, hosts::ScriptData};
+
+/// Decides how errors produced by a script are reported, and when the script gets disabled.
+#[derive(Debug, Clone, PartialEq, Default, Reflect)]
+pub enum FailurePolicy {
+    /// Every error is logged and sent as a [`ScriptErrorEvent`], the script is never disabled.
+    #[default]
+    ReportAll,
+    /// Only the first error is logged and sent as a [`ScriptErrorEvent`] until the script is reloaded
+    /// or re-enabled, the script is never disabled.
+    ReportOnce,
+    /// Every error is reported, once the script produces `max_errors` errors within `window` it is disabled
+    /// until reloaded or re-enabled via [`ScriptFaults::enable`].
+    DisableAfter { max_errors: u32, window: Duration },
+    /// Every error is reported and disables the script for a period of time, starting at `initial` and doubling with every
+    /// consecutive failure up to `max`. Failures are consecutive if they happen within `max` of each other.
+    RetryWithBackoff { initial: Duration, max: Duration },
+}
+
+/// Whether a script is allowed to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaultStatus {
+    /// the script runs normally
+    #[default]
+    Healthy,
+    /// the script was disabled by its failure policy and will not run until it's reloaded or re-enabled
+    Disabled,
+    /// the script was disabled by its failure policy and will run again after the given instant
+    Backoff { until: Instant },
+}
+
+/// The fault tracking state of a single script
+#[derive(Debug, Clone, Default)]
+pub struct ScriptFaultState {
+    /// the policy of this script, if None the default policy is used
+    pub policy: Option<FailurePolicy>,
+    /// whether the script is allowed to run
+    pub status: FaultStatus,
+    /// the number of errors produced since the script was (re)loaded or re-enabled
+    pub error_count: u32,
+    /// timestamps of errors within the current `DisableAfter` window
+    recent_errors: VecDeque<Instant>,
+    /// the number of consecutive failures under the `RetryWithBackoff` policy
+    consecutive_failures: u32,
+    last_error: Option<Instant>,
+}
+
+/// The outcome of recording a script error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorOutcome {
+    /// whether the error should be logged and sent as an event
+    pub report: bool,
+    /// the new status of the script if the error changed it
+    pub status_change: Option<FaultStatus>,
+}
+
+/// A resource tracking errors produced by each script, and applying their failure policies.
+///
+/// Scripts disabled by their policies are skipped by the script event handlers.
+#[derive(Resource, Default, Debug)]
+pub struct ScriptFaults {
+    /// the policy used for scripts which do not specify their own
+    pub default_policy: FailurePolicy,
+    scripts: HashMap<u32, ScriptFaultState>,
+}
+
+impl ScriptFaults {
+    /// Creates a fault tracker with the given default policy
+    pub fn with_default_policy(default_policy: FailurePolicy) -> Self {
+        Self {
+            default_policy,
+            scripts: Default::default(),
+        }
+    }
+
+    /// Retrieves the fault tracking state of the given script
+    pub fn get(&self, script_id: u32) -> Option<&ScriptFaultState> {
+        self.scripts.get(&script_id)
+    }
+
+    /// The status of the given script, scripts which never failed are healthy
+    pub fn status(&self, script_id: u32) -> FaultStatus {
+        self.scripts
+            .get(&script_id)
+            .map(|s| s.status)
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the given script was disabled by its failure policy
+    pub fn is_disabled(&self, script_id: u32) -> bool {
+        self.status(script_id) != FaultStatus::Healthy
+    }
+
+    /// Iterates over the ids of all currently disabled scripts
+    pub fn disabled_scripts(&self) -> impl Iterator<Item = u32> + '_ {
+        self.scripts
+            .iter()
+            .filter_map(|(sid, s)| (s.status != FaultStatus::Healthy).then_some(*sid))
+    }
+
+    /// Sets the failure policy of the given script, None means the default policy is used
+    pub fn set_policy(&mut self, script_id: u32, policy: Option<FailurePolicy>) {
+        self.scripts.entry(script_id).or_default().policy = policy;
+    }
+
+    /// The failure policy applied to the given script
+    pub fn policy(&self, script_id: u32) -> &FailurePolicy {
+        self.scripts
+            .get(&script_id)
+            .and_then(|s| s.policy.as_ref())
+            .unwrap_or(&self.default_policy)
+    }
+
+    /// Starts tracking the given script afresh with the given policy, used whenever a script is (re)loaded
+    pub fn reset(&mut self, script_id: u32, policy: Option<FailurePolicy>) {
+        self.scripts.insert(
+            script_id,
+            ScriptFaultState {
+                policy,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Re-enables the given script and forgets any errors it produced, keeping its policy
+    pub fn enable(&mut self, script_id: u32) {
+        if let Some(state) = self.scripts.get_mut(&script_id) {
+            *state = ScriptFaultState {
+                policy: state.policy.take(),
+                ..Default::default()
+            };
+        }
+    }
+
+    /// Stops tracking the given script
+    pub fn remove(&mut self, script_id: u32) {
+        self.scripts.remove(&script_id);
+    }
+
+    /// Re-enables scripts whose backoff period has elapsed, returns the ids of re-enabled scripts
+    pub fn refresh(&mut self, now: Instant) -> Vec<u32> {
+        let mut retried = Vec::default();
+        for (sid, state) in self.scripts.iter_mut() {
+            if matches!(state.status, FaultStatus::Backoff { until } if until <= now) {
+                state.status = FaultStatus::Healthy;
+                retried.push(*sid);
+            }
+        }
+        retried
+    }
+
+    /// Records an error produced by the given script at the given time and applies its failure policy
+    pub fn record_error(&mut self, script_id: u32, now: Instant) -> ErrorOutcome {
+        let default_policy = &self.default_policy;
+        let state = self.scripts.entry(script_id).or_default();
+        let policy = state.policy.as_ref().unwrap_or(default_policy);
+
+        state.error_count += 1;
+        let previous_error = state.last_error.replace(now);
+
+        let (report, status) = match policy {
+            FailurePolicy::ReportAll => (true, state.status),
+            FailurePolicy::ReportOnce => (state.error_count == 1, state.status),
+            FailurePolicy::DisableAfter { max_errors, window } => {
+                state.recent_errors.push_back(now);
+                while state
+                    .recent_errors
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) > *window)
+                {
+                    state.recent_errors.pop_front();
+                }
+
+                if state.recent_errors.len() as u32 >= *max_errors {
+                    (true, FaultStatus::Disabled)
+                } else {
+                    (true, state.status)
+                }
+            }
+            FailurePolicy::RetryWithBackoff { initial, max } => {
+                if previous_error.is_some_and(|t| now.duration_since(t) > *max) {
+                    state.consecutive_failures = 0;
+                }
+                let backoff = initial
+                    .saturating_mul(2u32.saturating_pow(state.consecutive_failures))
+                    .min(*max);
+                state.consecutive_failures += 1;
+                (
+                    true,
+                    FaultStatus::Backoff {
+                        until: now + backoff,
+                    },
+                )
+            }
+        };
+
+        let status_change = (status != state.status).then_some(status);
+        state.status = status;
+        ErrorOutcome {
+            report,
+            status_change,
+        }
+    }
+}
+
+/// Reports an error produced by the given script, applying its failure policy.
+///
+/// Unless the policy suppresses it, the error is logged and sent as a [`ScriptErrorEvent`].
+/// Scripts exceeding their error budget are disabled.
+pub fn report_script_error(world: &mut World, script_data: &ScriptData, error: ScriptError) {
+    let outcome = match world.get_resource_mut::<ScriptFaults>() {
+        Some(mut faults) => faults.record_error(script_data.sid, Instant::now()),
+        None => ErrorOutcome {
+            report: true,
+            status_change: None,
+        },
+    };
+
+    if outcome.report {
+        error!("{}", error);
+        world.send_event(ScriptErrorEvent { error });
+    }
+
+    match outcome.status_change {
+        Some(FaultStatus::Disabled) => warn!(
+            "Script `{}` ({}) was disabled after exceeding its error budget",
+            script_data.name, script_data.sid
+        ),
+        Some(FaultStatus::Backoff { until }) => warn!(
+            "Script `{}` ({}) was disabled for {:?} after failing",
+            script_data.name,
+            script_data.sid,
+            until.saturating_duration_since(Instant::now())
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SID: u32 = 0;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn report_all_never_disables() {
+        let mut faults = ScriptFaults::default();
+        let now = Instant::now();
+        for i in 0..10 {
+            let outcome = faults.record_error(SID, now + secs(i));
+            assert!(outcome.report);
+            assert_eq!(outcome.status_change, None);
+        }
+        assert_eq!(faults.get(SID).unwrap().error_count, 10);
+        assert!(!faults.is_disabled(SID));
+    }
+
+    #[test]
+    fn report_once_until_enabled() {
+        let mut faults = ScriptFaults::with_default_policy(FailurePolicy::ReportOnce);
+        let now = Instant::now();
+        assert!(faults.record_error(SID, now).report);
+        assert!(!faults.record_error(SID, now).report);
+        assert!(!faults.is_disabled(SID));
+
+        faults.enable(SID);
+        assert!(faults.record_error(SID, now).report);
+        assert!(!faults.record_error(SID, now).report);
+    }
+
+    #[test]
+    fn disable_after_threshold_within_window() {
+        let mut faults = ScriptFaults::default();
+        faults.set_policy(
+            SID,
+            Some(FailurePolicy::DisableAfter {
+                max_errors: 3,
+                window: secs(10),
+            }),
+        );
+        let now = Instant::now();
+
+        // errors which fall out of the window do not count
+        faults.record_error(SID, now);
+        faults.record_error(SID, now + secs(11));
+        let outcome = faults.record_error(SID, now + secs(12));
+        assert_eq!(outcome.status_change, None);
+        assert!(!faults.is_disabled(SID));
+
+        let outcome = faults.record_error(SID, now + secs(13));
+        assert!(outcome.report);
+        assert_eq!(outcome.status_change, Some(FaultStatus::Disabled));
+        assert_eq!(faults.disabled_scripts().collect::<Vec<_>>(), vec![SID]);
+
+        // stays disabled, refreshing only affects backoffs
+        assert!(faults.refresh(now + secs(1000)).is_empty());
+        assert!(faults.is_disabled(SID));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_and_refresh_retries() {
+        let mut faults = ScriptFaults::with_default_policy(FailurePolicy::RetryWithBackoff {
+            initial: secs(1),
+            max: secs(5),
+        });
+        let now = Instant::now();
+
+        let backoff = |faults: &ScriptFaults, from: Instant| match faults.status(SID) {
+            FaultStatus::Backoff { until } => until - from,
+            status => panic!("expected a backoff, got {status:?}"),
+        };
+
+        let mut t = now;
+        for expected in [1, 2, 4, 5, 5] {
+            faults.record_error(SID, t);
+            assert_eq!(backoff(&faults, t), secs(expected));
+
+            assert!(faults.refresh(t).is_empty());
+            t += secs(expected);
+            assert_eq!(faults.refresh(t), vec![SID]);
+            assert!(!faults.is_disabled(SID));
+        }
+
+        // failures further apart than `max` start over
+        t += secs(6);
+        faults.record_error(SID, t);
+        assert_eq!(backoff(&faults, t), secs(1));
+    }
+
+    #[test]
+    fn reload_and_enable_reset_state() {
+        let mut faults = ScriptFaults::default();
+        let policy = FailurePolicy::DisableAfter {
+            max_errors: 1,
+            window: secs(1),
+        };
+        faults.reset(SID, Some(policy.clone()));
+        let now = Instant::now();
+
+        faults.record_error(SID, now);
+        assert!(faults.is_disabled(SID));
+
+        faults.enable(SID);
+        assert!(!faults.is_disabled(SID));
+        assert_eq!(faults.get(SID).unwrap().error_count, 0);
+        assert_eq!(faults.policy(SID), &policy);
+
+        faults.record_error(SID, now);
+        assert!(faults.is_disabled(SID));
+
+        // reloading uses the policy of the reloaded script
+        faults.reset(SID, None);
+        assert!(!faults.is_disabled(SID));
+        assert_eq!(faults.policy(SID), &FailurePolicy::ReportAll);
+
+        faults.remove(SID);
+        assert!(faults.get(SID).is_none());
+    }
+}
@@ -1,7 +1,7 @@
 //! All script host related stuff
 use bevy::{asset::Asset, ecs::schedule::ScheduleLabel, prelude::*};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     iter::once,
     sync::atomic::{AtomicU32, Ordering},
 };
@@ -10,8 +10,12 @@ use crate::{
     asset::CodeAsset,
     docs::DocFragment,
     error::ScriptError,
-    event::{ScriptEvent, ScriptLoaded},
-    world::WorldPointer,
+    event::{ReflectedScriptEvent, ScriptErrorEvent, ScriptEvent, ScriptLoaded},
+    faults::FailurePolicy,
+    props::{ScriptProps, ScriptValue},
+    status::ScriptStatus,
+    systems::ScriptLifecycle,
+    world::{apply_script_commands, WorldPointer},
 };
 
 /// Describes the target set of scripts this event should
@@ -96,6 +100,19 @@ pub trait ScriptHost: Send + Sync + 'static + Default + Resource {
         providers: &mut APIProviders<Self>,
     );
 
+    /// Calls scripts with events shared by all hosts, converting their reflected arguments into script values.
+    ///
+    /// Called by the script event handlers right after `handle_events`, hosts which do not support reflected
+    /// arguments ignore these events.
+    fn handle_reflected_events<'a>(
+        &mut self,
+        _world: &mut World,
+        _events: &[ReflectedScriptEvent],
+        _ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        _providers: &mut APIProviders<Self>,
+    ) {
+    }
+
     /// Loads and runs script instantaneously without storing any script data into the world.
     /// The script id is set to `u32::MAX`.
     fn run_one_shot(
@@ -113,17 +130,68 @@ pub trait ScriptHost: Send + Sync + 'static + Default + Resource {
         };
 
         let mut providers: APIProviders<Self> = world.remove_resource().unwrap();
-        let mut ctx = self.load_script(script, &fd, &mut providers).unwrap();
-        self.setup_script(&fd, &mut ctx, &mut providers)?;
-        let events = [event; 1];
+        let result = self
+            .load_script(script, &fd, &mut providers)
+            .and_then(|mut ctx| {
+                self.setup_script(&fd, &mut ctx, &mut providers)?;
+                let events = [event; 1];
 
-        self.handle_events(world, &events, once((fd, &mut ctx)), &mut providers);
+                self.handle_events(world, &events, once((fd, &mut ctx)), &mut providers);
+                Ok(())
+            });
 
         world.insert_resource(providers);
 
+        result
+    }
+
+    /// Evaluates a snippet of code inside the context of an already loaded script, returning
+    /// the result (and anything printed, if the host supports capturing output) formatted as a string.
+    ///
+    /// Unlike `run_one_shot` the snippet can see and modify the state of the script, which makes this
+    /// the building block for REPLs and in-game consoles. See [`eval_in_script`] for the entry point.
+    ///
+    /// How much state a snippet can reach depends on the language, e.g. Rune scripts keep no state between calls
+    /// so snippets are compiled alongside the script's source instead, and do not have their printed output captured.
+    fn eval_in_context(
+        &mut self,
+        _code: &str,
+        script_data: &ScriptData,
+        _ctx: &mut Self::ScriptContext,
+        _world: &mut World,
+        _providers: &mut APIProviders<Self>,
+    ) -> Result<String, ScriptError> {
+        Err(ScriptError::Other(format!(
+            "Script host does not support evaluating code in the context of script `{}`",
+            script_data.name
+        )))
+    }
+
+    /// Exposes the properties of a script instance to its context, e.g. as a `props` table.
+    ///
+    /// Called right after `setup_script` whenever a script is (re)loaded, hosts which do not support properties ignore them.
+    fn apply_props(
+        &mut self,
+        _script_data: &ScriptData,
+        _ctx: &mut Self::ScriptContext,
+        _props: &ScriptProps,
+    ) -> Result<(), ScriptError> {
         Ok(())
     }
 
+    /// Calls the given hook without arguments on each of the given scripts which define it.
+    ///
+    /// Used for lifecycle hooks such as `on_enable` and `on_disable`, which are not tied to any particular script event type.
+    /// Hosts which do not support this simply ignore lifecycle hooks.
+    fn call_lifecycle_hook<'a>(
+        &mut self,
+        _world: &mut World,
+        _hook: &str,
+        _ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        _providers: &mut APIProviders<Self>,
+    ) {
+    }
+
     /// Registers the script host with the given app, and attaches handlers to deal with spawning/removing scripts in the given System Set.
     ///
     /// Ideally place after any game logic which can spawn/remove/modify scripts to avoid frame lag. (typically `PostUpdate`)
@@ -138,6 +206,51 @@ pub trait ScriptHost: Send + Sync + 'static + Default + Resource {
     fn register_with_app_in_set(app: &mut App, schedule: impl ScheduleLabel, set: impl SystemSet);
 }
 
+/// Evaluates a snippet of code inside the context of the loaded script with the given id
+/// using [`ScriptHost::eval_in_context`], returning the formatted result or the error produced.
+///
+/// Must not be called from within a script callback, i.e. while the host is handling events.
+pub fn eval_in_script<H: ScriptHost>(
+    world: &mut World,
+    script_id: u32,
+    code: &str,
+) -> Result<String, ScriptError> {
+    let (Some(mut contexts), Some(mut host), Some(mut providers)) = (
+        world.remove_resource::<ScriptContexts<H::ScriptContext>>(),
+        world.remove_resource::<H>(),
+        world.remove_resource::<APIProviders<H>>(),
+    ) else {
+        return Err(ScriptError::Other(
+            "Script host is not registered or is currently handling events".to_owned(),
+        ));
+    };
+
+    let result = match contexts.context_entities.get_mut(&script_id) {
+        Some((entity, Some(ctx), name)) => {
+            let script_data = ScriptData {
+                sid: script_id,
+                entity: *entity,
+                name,
+            };
+            let result = host.eval_in_context(code, &script_data, ctx, world, &mut providers);
+            apply_script_commands(world);
+            result
+        }
+        Some((_, None, name)) => Err(ScriptError::Other(format!(
+            "Script `{name}` has not been loaded yet"
+        ))),
+        None => Err(ScriptError::Other(format!(
+            "No script with id {script_id} exists"
+        ))),
+    };
+
+    world.insert_resource(contexts);
+    world.insert_resource(host);
+    world.insert_resource(providers);
+
+    result
+}
+
 /// Implementors can modify a script context in order to enable
 /// API access. ScriptHosts call `attach_api` when creating scripts
 pub trait APIProvider: 'static + Send + Sync {
@@ -183,6 +296,11 @@ pub trait APIProvider: 'static + Send + Sync {
     /// Some providers might provide additional types which need to be registered
     /// with the reflection API to work.
     fn register_with_app(&self, _app: &mut App) {}
+
+    /// The name of this provider as it appears in tracing spans, defaults to the type name
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 #[derive(Resource)]
@@ -210,6 +328,7 @@ impl<T: ScriptHost> Default for APIProviders<T> {
 impl<T: ScriptHost> APIProviders<T> {
     pub fn attach_all(&mut self, ctx: &mut T::APITarget) -> Result<(), ScriptError> {
         for p in self.providers.iter_mut() {
+            let _span = info_span!("attach_api", provider = p.name()).entered();
             p.attach_api(ctx)?;
         }
 
@@ -223,6 +342,13 @@ impl<T: ScriptHost> APIProviders<T> {
         ctx: &mut T::ScriptContext,
     ) -> Result<(), ScriptError> {
         for p in self.providers.iter_mut() {
+            let _span = info_span!(
+                "setup_script_runtime",
+                provider = p.name(),
+                script = script_data.name,
+                sid = script_data.sid
+            )
+            .entered();
             p.setup_script_runtime(world_ptr.clone(), script_data, ctx)?;
         }
 
@@ -235,6 +361,13 @@ impl<T: ScriptHost> APIProviders<T> {
         ctx: &mut T::ScriptContext,
     ) -> Result<(), ScriptError> {
         for p in self.providers.iter_mut() {
+            let _span = info_span!(
+                "setup_script",
+                provider = p.name(),
+                script = script_data.name,
+                sid = script_data.sid
+            )
+            .entered();
             p.setup_script(script_data, ctx)?;
         }
 
@@ -268,12 +401,15 @@ pub struct ScriptContexts<C> {
     /// holds script contexts for all scripts given their instance ids.
     /// This also stores contexts which are not fully loaded hence the Option
     pub context_entities: HashMap<u32, (Entity, Option<C>, String)>,
+    /// ids of scripts which are loaded but disabled, these do not receive events
+    disabled: HashSet<u32>,
 }
 
 impl<C> Default for ScriptContexts<C> {
     fn default() -> Self {
         Self {
             context_entities: Default::default(),
+            disabled: Default::default(),
         }
     }
 }
@@ -290,6 +426,26 @@ impl<C> ScriptContexts<C> {
 
     pub fn remove_context(&mut self, script_id: u32) {
         self.context_entities.remove(&script_id);
+        self.disabled.remove(&script_id);
+    }
+
+    /// Returns true unless the given script was disabled via [`Script::set_enabled`]
+    pub fn is_enabled(&self, script_id: u32) -> bool {
+        !self.disabled.contains(&script_id)
+    }
+
+    /// Iterates over the ids of all scripts which were disabled via [`Script::set_enabled`]
+    pub fn disabled_scripts(&self) -> impl Iterator<Item = u32> + '_ {
+        self.disabled.iter().copied()
+    }
+
+    /// Enables or disables event handling for the given script, returns true if this changed its state
+    pub fn set_enabled(&mut self, script_id: u32, enabled: bool) -> bool {
+        if enabled {
+            self.disabled.remove(&script_id)
+        } else {
+            self.disabled.insert(script_id)
+        }
     }
 
     pub fn has_context(&self, script_id: u32) -> bool {
@@ -305,32 +461,81 @@ impl<C> ScriptContexts<C> {
 
 /// A struct defining an instance of a script asset.
 /// Multiple instances of the same script can exist on the same entity
+///
+/// Scripts are serialized by their asset path, so they can be saved in scenes as long as their handle was loaded from a path.
+/// Scripts created via reflection (e.g. when spawning a scene) are given a fresh ID and their handle is
+/// re-loaded from the asset path before their context is created.
 #[derive(Debug, Reflect)]
 pub struct Script<T: Asset> {
     /// a strong handle to the script asset
+    #[reflect(ignore)]
     handle: Handle<T>,
 
+    /// the asset path of the script asset if it was loaded from one, used to restore the handle
+    path: Option<String>,
+
     /// the name of the script, usually its file name + relative asset path
     name: String,
 
     /// uniquely identifies the script instance (scripts which use the same asset don't necessarily have the same ID)
+    #[reflect(ignore, default = "next_script_id")]
     id: u32,
+
+    /// decides how errors produced by this script are handled, if None the default policy of [`crate::faults::ScriptFaults`] is used
+    failure_policy: Option<FailurePolicy>,
+
+    /// disabled scripts keep their context but do not receive any events
+    enabled: bool,
+
+    /// properties of this script instance, exposed to the script whenever it's (re)loaded
+    props: ScriptProps,
+
+    /// true if this script instance was created from a [`ScriptComponent`], in which case the router keeps it in sync
+    #[reflect(default)]
+    routed: bool,
 }
 
 static COUNTER: AtomicU32 = AtomicU32::new(0);
 
+fn next_script_id() -> u32 {
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
 impl<T: Asset> Script<T> {
     /// creates a new script instance with the given name and asset handle
     /// automatically gives this script instance a unique ID.
     /// No two scripts instances ever share the same ID
     pub fn new(name: String, handle: Handle<T>) -> Self {
         Self {
+            path: handle.path().map(ToString::to_string),
             handle,
             name,
-            id: COUNTER.fetch_add(1, Ordering::Relaxed),
+            id: next_script_id(),
+            failure_policy: None,
+            enabled: true,
+            props: Default::default(),
+            routed: false,
         }
     }
 
+    /// marks this script instance as created from a [`ScriptComponent`]
+    pub(crate) fn routed(mut self) -> Self {
+        self.routed = true;
+        self
+    }
+
+    /// sets the failure policy of this script instance, overriding the default policy
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = Some(policy);
+        self
+    }
+
+    /// sets a property of this script instance, see [`Script::props`]
+    pub fn with_prop(mut self, name: impl Into<String>, value: impl Into<ScriptValue>) -> Self {
+        self.props.insert(name.into(), value.into());
+        self
+    }
+
     #[inline(always)]
     /// returns the name of the script
     pub fn name(&self) -> &str {
@@ -343,12 +548,71 @@ impl<T: Asset> Script<T> {
         &self.handle
     }
 
+    #[inline(always)]
+    /// returns the asset path of the script asset, if its handle was loaded from a path
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
     #[inline(always)]
     /// returns the unique ID of this script instance
     pub fn id(&self) -> u32 {
         self.id
     }
 
+    #[inline(always)]
+    /// returns the failure policy of this script instance if it overrides the default policy
+    pub fn failure_policy(&self) -> Option<&FailurePolicy> {
+        self.failure_policy.as_ref()
+    }
+
+    #[inline(always)]
+    /// returns true if this script instance was created from a [`ScriptComponent`] rather than added to its collection directly
+    pub fn is_routed(&self) -> bool {
+        self.routed
+    }
+
+    #[inline(always)]
+    /// returns true if this script instance receives events
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// enables or disables this script instance, disabled scripts keep their context and state but do not receive events.
+    /// Scripts are notified of the change via their `on_enable`/`on_disable` hooks
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    #[inline(always)]
+    /// returns the properties of this script instance, which are exposed to the script
+    /// (e.g. as a `props` table) when it's loaded and every time it's hot-reloaded
+    pub fn props(&self) -> &ScriptProps {
+        &self.props
+    }
+
+    #[inline(always)]
+    /// returns the properties of this script instance mutably, changes take effect when the script is next (re)loaded
+    pub fn props_mut(&mut self) -> &mut ScriptProps {
+        &mut self.props
+    }
+
+    /// re-loads the asset handle of a script which was created via reflection (e.g. from a scene) from its asset path,
+    /// returns false if the handle could not be restored
+    pub(crate) fn restore_handle(&mut self, asset_server: &AssetServer) -> bool {
+        if self.handle != Handle::default() {
+            return true;
+        }
+
+        match &self.path {
+            Some(path) => {
+                self.handle = asset_server.load(path.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
     /// reloads the script by deleting the old context and inserting a new one
     /// if the script context never existed, it will after this call.
     pub(crate) fn reload_script<H: ScriptHost>(
@@ -357,7 +621,7 @@ impl<T: Asset> Script<T> {
         script_assets: &Assets<H::ScriptAsset>,
         providers: &mut APIProviders<H>,
         contexts: &mut ScriptContexts<H::ScriptContext>,
-        event_writer: &mut EventWriter<ScriptLoaded>,
+        lifecycle: &mut ScriptLifecycle,
     ) {
         debug!("reloading script {}", script.id);
 
@@ -373,17 +637,18 @@ impl<T: Asset> Script<T> {
                 script_assets,
                 providers,
                 contexts,
-                event_writer,
+                lifecycle,
             );
         } else {
             // remove old context
-            contexts.remove_context(script.id());
+            lifecycle.remove_script(contexts, script.id());
         }
     }
 
     /// checks if a script has loaded, and if so loads (`ScriptHost::load_script`),
     /// sets up (`ScriptHost::setup_script`) and inserts its new context into the contexts resource
-    /// otherwise inserts None. Sends ScriptLoaded event if the script was loaded
+    /// otherwise inserts None. Sends ScriptLoaded event if the script was loaded, or a ScriptErrorEvent if
+    /// loading or setup failed.
     pub(crate) fn insert_new_script_context<H: ScriptHost>(
         host: &mut H,
         new_script: &Script<H::ScriptAsset>,
@@ -391,7 +656,7 @@ impl<T: Asset> Script<T> {
         script_assets: &Assets<H::ScriptAsset>,
         providers: &mut APIProviders<H>,
         contexts: &mut ScriptContexts<H::ScriptContext>,
-        event_writer: &mut EventWriter<ScriptLoaded>,
+        lifecycle: &mut ScriptLifecycle,
     ) {
         let fd = ScriptData {
             sid: new_script.id(),
@@ -404,23 +669,54 @@ impl<T: Asset> Script<T> {
             None => {
                 // not loaded yet
                 debug!("Inserted script which hasn't loaded yet {:?}", fd);
+                lifecycle.statuses.set(fd.sid, ScriptStatus::Pending);
+                lifecycle
+                    .statuses
+                    .set_enabled(fd.sid, new_script.is_enabled());
+                contexts.set_enabled(fd.sid, new_script.is_enabled());
                 contexts.insert_context(fd, None);
                 return;
             }
         };
         debug!("Inserted script {:?}", fd);
-
-        match host.load_script(script.bytes(), &fd, providers) {
-            Ok(mut ctx) => {
-                host.setup_script(&fd, &mut ctx, providers)
-                    .expect("Failed to setup script");
+        lifecycle.statuses.set(fd.sid, ScriptStatus::Compiling);
+
+        let load_span = info_span!("load_script", script = fd.name, sid = fd.sid).entered();
+        let loaded = host.load_script(script.bytes(), &fd, providers);
+        load_span.exit();
+
+        let loaded = loaded.and_then(|mut ctx| {
+            let _setup_span = info_span!("setup_script", script = fd.name, sid = fd.sid).entered();
+            host.setup_script(&fd, &mut ctx, providers)?;
+            host.apply_props(&fd, &mut ctx, &new_script.props)?;
+            Ok(ctx)
+        });
+
+        // a fresh context starts with a clean slate
+        lifecycle
+            .faults
+            .reset(new_script.id(), new_script.failure_policy.clone());
+
+        contexts.set_enabled(new_script.id(), new_script.is_enabled());
+        lifecycle
+            .statuses
+            .set_enabled(new_script.id(), new_script.is_enabled());
+        match loaded {
+            Ok(ctx) => {
                 contexts.insert_context(fd, Some(ctx));
-                event_writer.send(ScriptLoaded {
+                lifecycle
+                    .statuses
+                    .set(new_script.id(), ScriptStatus::Loaded);
+                lifecycle.loaded.send(ScriptLoaded {
                     sid: new_script.id(),
                 });
             }
             Err(e) => {
                 warn! {"Error in loading script {}:\n{}", &new_script.name,e}
+                lifecycle
+                    .statuses
+                    .set(new_script.id(), ScriptStatus::LoadFailed(e.to_string()));
+                lifecycle.errors.send(ScriptErrorEvent { error: e });
                 // this script will now never execute, unless manually reloaded
                 // but contexts are left in a valid state
                 contexts.insert_context(fd, None);
@@ -446,3 +742,60 @@ impl<T: Asset> Default for ScriptCollection<T> {
         }
     }
 }
+
+impl<T: Asset> ScriptCollection<T> {
+    /// enables or disables all scripts on this entity, see [`Script::set_enabled`]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.scripts
+            .iter_mut()
+            .for_each(|script| script.set_enabled(enabled));
+    }
+
+    /// enables or disables the script with the given id, returns false if no such script is attached to this entity
+    pub fn set_script_enabled(&mut self, script_id: u32, enabled: bool) -> bool {
+        match self.scripts.iter_mut().find(|s| s.id() == script_id) {
+            Some(script) => {
+                script.set_enabled(enabled);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default, Clone, Reflect)]
+#[reflect(Component, Default)]
+/// A language agnostic component storing scripts given their asset paths.
+///
+/// Each script is routed to the registered script host handling its file extension, which creates and keeps in sync
+/// a [`ScriptCollection`] of its own asset type on the same entity. Only the scripts created from this component are
+/// managed that way (see [`Script::is_routed`]), scripts added to those collections directly are left untouched.
+pub struct ScriptComponent {
+    /// asset paths of the scripts, the same path can appear multiple times to create multiple instances
+    pub scripts: Vec<String>,
+}
+
+impl ScriptComponent {
+    /// creates a component with the given script asset paths
+    pub fn new<S: Into<String>>(scripts: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            scripts: scripts.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A resource storing the file extensions of all registered script hosts
+#[derive(Resource, Debug, Default)]
+pub struct ScriptExtensions {
+    pub extensions: HashSet<&'static str>,
+}
+
+impl ScriptExtensions {
+    /// Returns true if a registered script host handles the extension of the given asset path
+    pub fn is_routable(&self, path: &str) -> bool {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(ext))
+    }
+}
@@ -2,15 +2,30 @@ use crate::{
     event::ScriptErrorEvent,
     hosts::{APIProvider, APIProviders, ScriptHost},
 };
-use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
-use event::ScriptLoaded;
-use systems::script_event_handler;
+use asset::CodeAsset;
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, reflect::GetTypeRegistration};
+use event::{ReflectedScriptEvent, ScriptDisabled, ScriptEnabled, ScriptLoaded};
+use faults::{FailurePolicy, ScriptFaults};
+use hosts::Recipients;
+use hosts::{ScriptComponent, ScriptExtensions};
+use metrics::{script_metrics_diagnostics, ScriptMetrics};
+use props::{ScriptProps, ScriptValue};
+use status::{script_status_synchronizer, ScriptStatuses};
+use systems::{
+    script_component_validator, script_component_watcher, script_event_forwarder,
+    script_event_handler, ScriptSystemSet,
+};
+use world::{check_script_change_ticks, ScriptChangeTicks, ScriptCommands};
 
 pub mod asset;
 pub mod docs;
 pub mod error;
 pub mod event;
+pub mod faults;
 pub mod hosts;
+pub mod metrics;
+pub mod props;
+pub mod status;
 pub mod systems;
 pub mod world;
 pub mod prelude {
@@ -19,15 +34,22 @@ pub mod prelude {
         crate::asset::CodeAsset,
         crate::docs::DocFragment,
         crate::error::ScriptError,
-        crate::event::{ScriptErrorEvent, ScriptEvent},
+        crate::event::{
+            ReflectedScriptEvent, ScriptDisabled, ScriptEnabled, ScriptErrorEvent, ScriptEvent,
+        },
+        crate::faults::{report_script_error, FailurePolicy, FaultStatus, ScriptFaults},
         crate::hosts::{
-            APIProvider, APIProviders, Recipients, Script, ScriptCollection, ScriptContexts,
-            ScriptData, ScriptHost,
+            eval_in_script, APIProvider, APIProviders, Recipients, Script, ScriptCollection,
+            ScriptComponent, ScriptContexts, ScriptData, ScriptExtensions, ScriptHost,
         },
+        crate::metrics::{CallStats, ScriptMetric, ScriptMetrics},
+        crate::props::{ScriptProps, ScriptValue},
+        crate::status::{ScriptStatus, ScriptStatuses},
         crate::systems::script_event_handler,
+        crate::world::{run_script_callback, ScriptChangeTicks, ScriptCommands},
         crate::{
-            AddScriptApiProvider, AddScriptHost, AddScriptHostHandler, GenDocumentation,
-            ScriptingPlugin,
+            AddScriptApiProvider, AddScriptHost, AddScriptHostHandler, ForwardEventToScripts,
+            GenDocumentation, ScriptingPlugin, WatchComponentForScripts,
         },
         bevy_event_priority::{
             AddPriorityEvent, PriorityEvent, PriorityEventReader, PriorityEventWriter,
@@ -43,7 +65,31 @@ pub struct ScriptingPlugin;
 
 impl Plugin for ScriptingPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_event::<ScriptErrorEvent>();
+        app.add_event::<ScriptErrorEvent>()
+            .init_resource::<ScriptMetrics>()
+            .init_resource::<ScriptFaults>()
+            .init_resource::<ScriptStatuses>()
+            .init_resource::<ScriptExtensions>()
+            .init_resource::<ScriptCommands>()
+            .init_resource::<ScriptChangeTicks>()
+            .register_type::<ScriptComponent>()
+            .register_type::<ScriptMetrics>()
+            .register_type::<ScriptStatuses>()
+            // types stored in scripts, needed for scripts to round-trip through scenes
+            .register_type::<ScriptValue>()
+            .register_type::<ScriptProps>()
+            .register_type::<FailurePolicy>()
+            .register_type::<Option<FailurePolicy>>()
+            .register_type::<Option<String>>()
+            .add_systems(
+                Last,
+                (
+                    script_metrics_diagnostics,
+                    script_status_synchronizer,
+                    script_component_validator,
+                    check_script_change_ticks,
+                ),
+            );
     }
 }
 
@@ -97,7 +143,18 @@ impl AddScriptHost for App {
     {
         T::register_with_app_in_set(self, schedule, set);
         self.init_resource::<T>();
+        self.init_resource::<ScriptMetrics>();
+        self.init_resource::<ScriptFaults>();
+        self.init_resource::<ScriptStatuses>();
+        self.init_resource::<ScriptChangeTicks>();
+        self.world
+            .get_resource_or_insert_with(ScriptExtensions::default)
+            .extensions
+            .extend(T::ScriptAsset::extensions());
         self.add_event::<ScriptLoaded>();
+        self.add_event::<ScriptEnabled>();
+        self.add_event::<ScriptDisabled>();
+        self.add_event::<ReflectedScriptEvent>();
         self
     }
 
@@ -107,7 +164,18 @@ impl AddScriptHost for App {
     {
         T::register_with_app(self, schedule);
         self.init_resource::<T>();
+        self.init_resource::<ScriptMetrics>();
+        self.init_resource::<ScriptFaults>();
+        self.init_resource::<ScriptStatuses>();
+        self.init_resource::<ScriptChangeTicks>();
+        self.world
+            .get_resource_or_insert_with(ScriptExtensions::default)
+            .extensions
+            .extend(T::ScriptAsset::extensions());
         self.add_event::<ScriptLoaded>();
+        self.add_event::<ScriptEnabled>();
+        self.add_event::<ScriptDisabled>();
+        self.add_event::<ReflectedScriptEvent>();
         self
     }
 }
@@ -209,3 +277,101 @@ impl AddScriptHostHandler for App {
         self
     }
 }
+
+pub trait ForwardEventToScripts {
+    /// Forwards every event of type `T` to all scripts of the host `H` as a [`ReflectedScriptEvent`] with the given priority,
+    /// calling the given hook with the event as its only argument.
+    ///
+    /// Events are forwarded in [`ScriptSystemSet::EventForwarding`] within `PostUpdate`, script handlers
+    /// running after this set receive the events in the same frame, others in the next one.
+    fn forward_event_to_scripts<T: Event + Reflect + GetTypeRegistration, H: ScriptHost>(
+        &mut self,
+        hook_name: impl Into<String>,
+        priority: u32,
+    ) -> &mut Self;
+
+    /// The same as `forward_event_to_scripts` but only the scripts attached to the entity contained in the event,
+    /// as returned by `target`, receive it.
+    fn forward_event_to_entity_scripts<T: Event + Reflect + GetTypeRegistration, H: ScriptHost>(
+        &mut self,
+        hook_name: impl Into<String>,
+        priority: u32,
+        target: impl Fn(&T) -> Entity + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl ForwardEventToScripts for App {
+    fn forward_event_to_scripts<T: Event + Reflect + GetTypeRegistration, H: ScriptHost>(
+        &mut self,
+        hook_name: impl Into<String>,
+        priority: u32,
+    ) -> &mut Self {
+        self.add_event::<ReflectedScriptEvent>()
+            .register_type::<T>()
+            .add_systems(
+                PostUpdate,
+                script_event_forwarder::<T, H>(hook_name.into(), priority, |_| Recipients::All)
+                    .in_set(ScriptSystemSet::EventForwarding),
+            )
+    }
+
+    fn forward_event_to_entity_scripts<T: Event + Reflect + GetTypeRegistration, H: ScriptHost>(
+        &mut self,
+        hook_name: impl Into<String>,
+        priority: u32,
+        target: impl Fn(&T) -> Entity + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_event::<ReflectedScriptEvent>()
+            .register_type::<T>()
+            .add_systems(
+                PostUpdate,
+                script_event_forwarder::<T, H>(hook_name.into(), priority, move |event| {
+                    Recipients::Entity(target(event))
+                })
+                .in_set(ScriptSystemSet::EventForwarding),
+            )
+    }
+}
+
+pub trait WatchComponentForScripts {
+    /// Calls the `on_component_added(entity, type)` and `on_component_removed(entity, type)` hooks of all scripts,
+    /// whenever a component of type `C` is added to or removed from any entity, see [`systems::script_component_watcher`].
+    ///
+    /// Like forwarded events, these are sent as [`ReflectedScriptEvent`]s with the given priority in [`ScriptSystemSet::EventForwarding`]
+    /// and handled by every script host.
+    fn watch_component_for_scripts<C: Component + TypePath>(&mut self, priority: u32) -> &mut Self;
+
+    /// The same as `watch_component_for_scripts` but only the scripts attached to the entity
+    /// the component was added to or removed from are called.
+    fn watch_component_for_entity_scripts<C: Component + TypePath>(
+        &mut self,
+        priority: u32,
+    ) -> &mut Self;
+}
+
+impl WatchComponentForScripts for App {
+    fn watch_component_for_scripts<C: Component + TypePath>(&mut self, priority: u32) -> &mut Self {
+        self.add_event::<ReflectedScriptEvent>()
+            .register_type::<Entity>()
+            .register_type::<String>()
+            .add_systems(
+                PostUpdate,
+                script_component_watcher::<C>(priority, |_| Recipients::All)
+                    .in_set(ScriptSystemSet::EventForwarding),
+            )
+    }
+
+    fn watch_component_for_entity_scripts<C: Component + TypePath>(
+        &mut self,
+        priority: u32,
+    ) -> &mut Self {
+        self.add_event::<ReflectedScriptEvent>()
+            .register_type::<Entity>()
+            .register_type::<String>()
+            .add_systems(
+                PostUpdate,
+                script_component_watcher::<C>(priority, Recipients::Entity)
+                    .in_set(ScriptSystemSet::EventForwarding),
+            )
+    }
+}
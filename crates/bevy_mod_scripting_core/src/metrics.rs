@@ -0,0 +1,263 @@
+//! Per-script performance metrics and their integration with bevy diagnostics
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore},
+    prelude::*,
+    utils::Instant,
+};
+
+use crate::hosts::ScriptData;
+
+/// Call statistics accumulated over the lifetime of a script or a single hook
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct CallStats {
+    /// the number of times the script or hook was invoked
+    pub calls: u64,
+    /// the number of invocations which resulted in an error
+    pub errors: u64,
+    /// the total time spent executing
+    pub total_time: Duration,
+    /// the longest single invocation
+    pub max_time: Duration,
+}
+
+impl CallStats {
+    /// Records a single invocation which took `elapsed` time to complete
+    pub fn record(&mut self, elapsed: Duration, errored: bool) {
+        self.calls += 1;
+        if errored {
+            self.errors += 1;
+        }
+        self.total_time += elapsed;
+        self.max_time = self.max_time.max(elapsed);
+    }
+
+    /// The average time spent in a single invocation
+    pub fn mean_time(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total_time / self.calls as u32
+        }
+    }
+}
+
+/// Metrics collected for a single script instance
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct ScriptMetric {
+    /// the name of the script
+    pub name: String,
+    /// the entity the script is attached to
+    pub entity: Option<Entity>,
+    /// statistics accumulated over all hooks of this script
+    pub total: CallStats,
+    /// statistics accumulated for each hook of this script
+    pub hooks: HashMap<String, CallStats>,
+    /// time spent executing since the last diagnostics update, drained every frame
+    frame_time: Duration,
+    /// time spent executing each hook since the last diagnostics update, drained every frame
+    hook_frame_times: HashMap<String, Duration>,
+}
+
+/// A resource storing performance metrics for every script which handled at least one event.
+///
+/// Script hosts record every hook invocation here, the metrics are also published
+/// to the [`DiagnosticsStore`] each frame under `script/<script name>#<script id>` paths
+/// so they show up in existing diagnostic tooling such as the `LogDiagnosticsPlugin`.
+#[derive(Resource, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct ScriptMetrics {
+    /// metrics for each script given its instance id
+    pub scripts: HashMap<u32, ScriptMetric>,
+    /// diagnostics of removed scripts, retired on the next diagnostics update
+    #[reflect(ignore)]
+    retired: Vec<DiagnosticPath>,
+}
+
+impl ScriptMetrics {
+    /// Records a single invocation of `hook` on the given script.
+    pub fn record_call(
+        &mut self,
+        script_data: &ScriptData,
+        hook: &str,
+        elapsed: Duration,
+        errored: bool,
+    ) {
+        let metric = self.scripts.entry(script_data.sid).or_default();
+        if metric.name != script_data.name {
+            metric.name = script_data.name.to_owned();
+        }
+        metric.entity = Some(script_data.entity);
+        metric.total.record(elapsed, errored);
+        metric.frame_time += elapsed;
+
+        match metric.hooks.get_mut(hook) {
+            Some(stats) => stats.record(elapsed, errored),
+            None => {
+                let mut stats = CallStats::default();
+                stats.record(elapsed, errored);
+                metric.hooks.insert(hook.to_owned(), stats);
+            }
+        }
+        *metric.hook_frame_times.entry(hook.to_owned()).or_default() += elapsed;
+    }
+
+    /// Retrieves the metrics of the given script
+    pub fn get(&self, script_id: u32) -> Option<&ScriptMetric> {
+        self.scripts.get(&script_id)
+    }
+
+    /// Retrieves the statistics of the given hook on the given script
+    pub fn get_hook(&self, script_id: u32, hook: &str) -> Option<&CallStats> {
+        self.scripts.get(&script_id)?.hooks.get(hook)
+    }
+
+    /// Removes the metrics of the given script, its diagnostics stop being published
+    pub fn remove(&mut self, script_id: u32) -> Option<ScriptMetric> {
+        let metric = self.scripts.remove(&script_id)?;
+        self.retire(script_id, &metric);
+        Some(metric)
+    }
+
+    /// Resets all metrics
+    pub fn clear(&mut self) {
+        for (sid, metric) in std::mem::take(&mut self.scripts) {
+            self.retire(sid, &metric);
+        }
+    }
+
+    fn retire(&mut self, script_id: u32, metric: &ScriptMetric) {
+        self.retired
+            .push(Self::diagnostic_path(script_id, &metric.name, None));
+        self.retired.extend(
+            metric
+                .hooks
+                .keys()
+                .map(|hook| Self::diagnostic_path(script_id, &metric.name, Some(hook))),
+        );
+    }
+
+    /// The diagnostic path under which the time spent in the given script (and optionally hook) each frame is published
+    pub fn diagnostic_path(
+        script_id: u32,
+        script_name: &str,
+        hook: Option<&str>,
+    ) -> DiagnosticPath {
+        let script = format!("{}#{}", script_name.trim_matches('/'), script_id);
+        match hook {
+            Some(hook) => DiagnosticPath::from_components(["script", &script, hook]),
+            None => DiagnosticPath::from_components(["script", &script]),
+        }
+    }
+}
+
+/// Publishes the time spent in each script and hook over the last frame to the [`DiagnosticsStore`],
+/// registering new diagnostics as new scripts come along.
+///
+/// The store does not support removing diagnostics, so the diagnostics of removed scripts are disabled and cleared instead.
+pub fn script_metrics_diagnostics(
+    mut metrics: ResMut<ScriptMetrics>,
+    store: Option<ResMut<DiagnosticsStore>>,
+) {
+    let retired = std::mem::take(&mut metrics.retired);
+    let Some(mut store) = store else {
+        return;
+    };
+
+    for path in retired {
+        if let Some(diagnostic) = store.get_mut(&path) {
+            diagnostic.is_enabled = false;
+            diagnostic.clear_history();
+        }
+    }
+
+    let now = Instant::now();
+    for (sid, metric) in metrics.scripts.iter_mut() {
+        let frame_time = std::mem::take(&mut metric.frame_time);
+        add_measurement(
+            &mut store,
+            ScriptMetrics::diagnostic_path(*sid, &metric.name, None),
+            now,
+            frame_time,
+        );
+
+        for (hook, frame_time) in metric.hook_frame_times.iter_mut() {
+            add_measurement(
+                &mut store,
+                ScriptMetrics::diagnostic_path(*sid, &metric.name, Some(hook)),
+                now,
+                std::mem::take(frame_time),
+            );
+        }
+    }
+}
+
+fn add_measurement(
+    store: &mut DiagnosticsStore,
+    path: DiagnosticPath,
+    time: Instant,
+    value: Duration,
+) {
+    let measurement = DiagnosticMeasurement {
+        time,
+        value: value.as_secs_f64() * 1000.0,
+    };
+
+    match store.get_mut(&path) {
+        Some(diagnostic) => {
+            if diagnostic.is_enabled {
+                diagnostic.add_measurement(measurement)
+            }
+        }
+        None => {
+            let mut diagnostic = Diagnostic::new(path).with_suffix("ms");
+            diagnostic.add_measurement(measurement);
+            store.add(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn removed_scripts_retire_their_diagnostics() {
+        let mut app = App::new();
+        app.init_resource::<ScriptMetrics>()
+            .init_resource::<DiagnosticsStore>()
+            .add_systems(Update, script_metrics_diagnostics);
+
+        let script_data = ScriptData {
+            sid: 0,
+            entity: Entity::from_raw(0),
+            name: "script.lua",
+        };
+        app.world.resource_mut::<ScriptMetrics>().record_call(
+            &script_data,
+            "on_update",
+            Duration::from_millis(1),
+            false,
+        );
+        app.update();
+
+        let script_path = ScriptMetrics::diagnostic_path(0, "script.lua", None);
+        let hook_path = ScriptMetrics::diagnostic_path(0, "script.lua", Some("on_update"));
+        let store = app.world.resource::<DiagnosticsStore>();
+        assert!(store.get_measurement(&script_path).is_some());
+        assert!(store.get_measurement(&hook_path).is_some());
+
+        assert!(app
+            .world
+            .resource_mut::<ScriptMetrics>()
+            .remove(0)
+            .is_some());
+        app.update();
+
+        let store = app.world.resource::<DiagnosticsStore>();
+        assert!(store.get_measurement(&script_path).is_none());
+        assert!(store.get_measurement(&hook_path).is_none());
+        assert!(app.world.resource::<ScriptMetrics>().get(0).is_none());
+    }
+}
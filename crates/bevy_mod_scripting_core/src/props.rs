@@ -0,0 +1,58 @@
+//! Per-instance script properties
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// The properties of a script instance given their names, see [`crate::hosts::Script::with_prop`]
+pub type ScriptProps = HashMap<String, ScriptValue>;
+
+/// A language agnostic value which can be handed to any script host
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum ScriptValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<bool> for ScriptValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for ScriptValue {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<i64> for ScriptValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f32> for ScriptValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value.into())
+    }
+}
+
+impl From<f64> for ScriptValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<String> for ScriptValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for ScriptValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
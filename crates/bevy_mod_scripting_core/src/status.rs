@@ -0,0 +1,167 @@
+//! Queryable lifecycle status of every script instance
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::faults::ScriptFaults;
+
+/// The lifecycle status of a single script instance
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub enum ScriptStatus {
+    /// the script was attached to an entity but its asset has not loaded yet
+    #[default]
+    Pending,
+    /// the script asset is loaded and its context is being created.
+    /// Contexts are currently created within a single system, so this status is only seen if context creation is interrupted
+    Compiling,
+    /// the script context was created successfully and the script receives events
+    Loaded,
+    /// loading or setting up the script failed with the given error,
+    /// the script will not run until its asset is modified or it is re-attached
+    LoadFailed(String),
+    /// the script was disabled, either by its failure policy (see [`ScriptFaults`]) or by toggling it off on its [`crate::hosts::Script`]
+    Disabled,
+}
+
+/// A resource storing the lifecycle status of every script instance given its id.
+///
+/// Kept in sync by the core script systems, scripts are present here for as long as they are attached to an entity.
+#[derive(Resource, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct ScriptStatuses {
+    /// the status of each script given its instance id
+    pub scripts: HashMap<u32, ScriptStatus>,
+    /// scripts which were disabled by the user rather than by their failure policy
+    #[reflect(ignore)]
+    user_disabled: HashSet<u32>,
+}
+
+impl ScriptStatuses {
+    /// Retrieves the status of the given script
+    pub fn get(&self, script_id: u32) -> Option<&ScriptStatus> {
+        self.scripts.get(&script_id)
+    }
+
+    /// Returns true if the given script is loaded and not disabled
+    pub fn is_loaded(&self, script_id: u32) -> bool {
+        self.get(script_id) == Some(&ScriptStatus::Loaded)
+    }
+
+    /// Sets the status of the given script
+    pub fn set(&mut self, script_id: u32, status: ScriptStatus) {
+        self.scripts.insert(script_id, status);
+    }
+
+    /// Records whether the given script was enabled or disabled by the user,
+    /// the status itself is updated by [`script_status_synchronizer`]
+    pub fn set_enabled(&mut self, script_id: u32, enabled: bool) {
+        if enabled {
+            self.user_disabled.remove(&script_id);
+        } else {
+            self.user_disabled.insert(script_id);
+        }
+    }
+
+    /// Stops tracking the given script
+    pub fn remove(&mut self, script_id: u32) -> Option<ScriptStatus> {
+        self.user_disabled.remove(&script_id);
+        self.scripts.remove(&script_id)
+    }
+
+    /// Iterates over the ids of all scripts with the given status
+    pub fn with_status<'a>(&'a self, status: &'a ScriptStatus) -> impl Iterator<Item = u32> + 'a {
+        self.scripts
+            .iter()
+            .filter_map(move |(sid, s)| (s == status).then_some(*sid))
+    }
+}
+
+/// Moves loaded scripts in and out of the `Disabled` status as they are toggled by the user
+/// or disabled and re-enabled by their failure policies
+pub fn script_status_synchronizer(
+    mut statuses: ResMut<ScriptStatuses>,
+    faults: Option<Res<ScriptFaults>>,
+) {
+    let is_disabled = |sid: u32| {
+        statuses.user_disabled.contains(&sid) || faults.as_ref().is_some_and(|f| f.is_disabled(sid))
+    };
+
+    let changed = statuses
+        .scripts
+        .iter()
+        .filter_map(|(sid, status)| match status {
+            ScriptStatus::Loaded if is_disabled(*sid) => Some((*sid, ScriptStatus::Disabled)),
+            ScriptStatus::Disabled if !is_disabled(*sid) => Some((*sid, ScriptStatus::Loaded)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (sid, status) in changed {
+        statuses.set(sid, status);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::faults::FailurePolicy;
+
+    const SID: u32 = 0;
+
+    fn synchronize(world: &mut World) -> Option<ScriptStatus> {
+        world.run_system_once(script_status_synchronizer);
+        world.resource::<ScriptStatuses>().get(SID).cloned()
+    }
+
+    #[test]
+    fn user_disabled_scripts_are_reported_as_disabled() {
+        let mut world = World::new();
+        let mut statuses = ScriptStatuses::default();
+        statuses.set(SID, ScriptStatus::Loaded);
+        statuses.set_enabled(SID, false);
+        world.insert_resource(statuses);
+
+        assert_eq!(synchronize(&mut world), Some(ScriptStatus::Disabled));
+
+        world
+            .resource_mut::<ScriptStatuses>()
+            .set_enabled(SID, true);
+        assert_eq!(synchronize(&mut world), Some(ScriptStatus::Loaded));
+    }
+
+    #[test]
+    fn scripts_stay_disabled_while_faulted_or_toggled_off() {
+        let mut world = World::new();
+        let mut statuses = ScriptStatuses::default();
+        statuses.set(SID, ScriptStatus::Loaded);
+        statuses.set_enabled(SID, false);
+        world.insert_resource(statuses);
+
+        let mut faults = ScriptFaults::default();
+        faults.set_policy(
+            SID,
+            Some(FailurePolicy::DisableAfter {
+                max_errors: 1,
+                window: Duration::from_secs(10),
+            }),
+        );
+        faults.record_error(SID, Instant::now());
+        assert!(faults.is_disabled(SID));
+        world.insert_resource(faults);
+
+        assert_eq!(synchronize(&mut world), Some(ScriptStatus::Disabled));
+
+        // re-enabling the script does not override its failure policy
+        world
+            .resource_mut::<ScriptStatuses>()
+            .set_enabled(SID, true);
+        assert_eq!(synchronize(&mut world), Some(ScriptStatus::Disabled));
+
+        world.resource_mut::<ScriptFaults>().enable(SID);
+        assert_eq!(synchronize(&mut world), Some(ScriptStatus::Loaded));
+    }
+}
@@ -1,11 +1,26 @@
 use std::collections::HashSet;
 
-use bevy::{ecs::system::SystemState, prelude::*};
+use bevy::{
+    ecs::{
+        event::ManualEventReader,
+        system::{SystemParam, SystemState},
+    },
+    prelude::*,
+    utils::Instant,
+};
 use bevy_event_priority::PriorityEventReader;
 
 use crate::{
-    event::ScriptLoaded,
-    prelude::{APIProviders, Script, ScriptCollection, ScriptContexts, ScriptData, ScriptHost},
+    asset::CodeAsset,
+    event::{ReflectedScriptEvent, ScriptDisabled, ScriptEnabled, ScriptLoaded},
+    faults::ScriptFaults,
+    hosts::{ScriptComponent, ScriptExtensions},
+    metrics::ScriptMetrics,
+    prelude::{
+        APIProviders, Recipients, Script, ScriptCollection, ScriptContexts, ScriptData, ScriptHost,
+    },
+    status::ScriptStatuses,
+    world::{apply_script_commands, ScriptChangeTicks},
     ScriptErrorEvent,
 };
 
@@ -14,6 +29,151 @@ use crate::{
 pub enum ScriptSystemSet {
     /// event handling systems are always marked with this label
     EventHandling,
+    /// systems forwarding rust events and component lifecycle changes to scripts run in this set within `PostUpdate`,
+    /// see [`crate::ForwardEventToScripts`] and [`crate::WatchComponentForScripts`]
+    EventForwarding,
+}
+
+/// Events and resources updated whenever script contexts are (re)created
+#[derive(SystemParam)]
+pub struct ScriptLifecycle<'w> {
+    pub loaded: EventWriter<'w, ScriptLoaded>,
+    pub errors: EventWriter<'w, ScriptErrorEvent>,
+    pub faults: ResMut<'w, ScriptFaults>,
+    pub statuses: ResMut<'w, ScriptStatuses>,
+    pub enabled: EventWriter<'w, ScriptEnabled>,
+    pub disabled: EventWriter<'w, ScriptDisabled>,
+    pub metrics: ResMut<'w, ScriptMetrics>,
+    pub change_ticks: ResMut<'w, ScriptChangeTicks>,
+}
+
+impl ScriptLifecycle<'_> {
+    /// Removes the context of the given script along with everything tracked about it
+    pub fn remove_script<C>(&mut self, contexts: &mut ScriptContexts<C>, script_id: u32) {
+        contexts.remove_context(script_id);
+        self.faults.remove(script_id);
+        self.statuses.remove(script_id);
+        self.metrics.remove(script_id);
+        self.change_ticks.remove(script_id);
+    }
+}
+
+/// Routes the scripts of [`ScriptComponent`]s which have the file extensions of this host's asset type
+/// into a [`ScriptCollection`] on the same entity, creating it as needed.
+///
+/// Only scripts created by this system are added or removed, scripts inserted into the collection directly are kept
+pub fn script_component_router<H: ScriptHost>(
+    mut commands: Commands,
+    changed: Query<(Entity, &ScriptComponent), Changed<ScriptComponent>>,
+    mut collections: Query<&mut ScriptCollection<H::ScriptAsset>>,
+    mut removed: RemovedComponents<ScriptComponent>,
+    asset_server: Res<AssetServer>,
+) {
+    let new_script =
+        |path: &String| Script::new(path.clone(), asset_server.load(path.clone())).routed();
+
+    for (entity, component) in changed.iter() {
+        let mut wanted = component
+            .scripts
+            .iter()
+            .filter(|path| H::ScriptAsset::matches_path(path))
+            .collect::<Vec<_>>();
+
+        match collections.get_mut(entity) {
+            Ok(mut collection) => {
+                // keep the routed instances which are still wanted, so their state is preserved
+                collection.scripts.retain(|script| {
+                    if !script.is_routed() {
+                        return true;
+                    }
+
+                    match wanted
+                        .iter()
+                        .position(|path| Some(path.as_str()) == script.path())
+                    {
+                        Some(i) => {
+                            wanted.swap_remove(i);
+                            true
+                        }
+                        None => false,
+                    }
+                });
+                collection
+                    .scripts
+                    .extend(wanted.into_iter().map(new_script));
+            }
+            Err(_) if !wanted.is_empty() => {
+                commands
+                    .entity(entity)
+                    .insert(ScriptCollection::<H::ScriptAsset> {
+                        scripts: wanted.into_iter().map(new_script).collect(),
+                    });
+            }
+            Err(_) => {}
+        }
+    }
+
+    for entity in removed.read() {
+        let Ok(mut collection) = collections.get_mut(entity) else {
+            continue;
+        };
+
+        let count = collection.scripts.len();
+        collection.scripts.retain(|script| !script.is_routed());
+        if collection.scripts.len() != count && collection.scripts.is_empty() {
+            commands
+                .entity(entity)
+                .remove::<ScriptCollection<H::ScriptAsset>>();
+        }
+    }
+}
+
+/// Warns about scripts in [`ScriptComponent`]s which no registered script host can handle
+pub fn script_component_validator(
+    query: Query<(Entity, &ScriptComponent), Changed<ScriptComponent>>,
+    extensions: Res<ScriptExtensions>,
+) {
+    for (entity, component) in query.iter() {
+        for path in component.scripts.iter() {
+            if !extensions.is_routable(path) {
+                warn!(
+                    "Script `{}` on entity {:?} has no script host registered for its file extension",
+                    path, entity
+                );
+            }
+        }
+    }
+}
+
+/// Restores the asset handles of scripts spawned from scenes or otherwise created via reflection,
+/// must run before [`script_add_synchronizer`] so their contexts get created
+pub fn script_scene_resolver<H: ScriptHost>(
+    mut query: Query<
+        &mut ScriptCollection<H::ScriptAsset>,
+        Added<ScriptCollection<H::ScriptAsset>>,
+    >,
+    asset_server: Res<AssetServer>,
+) {
+    for mut scripts in query.iter_mut() {
+        // avoid triggering change detection on collections which need no fixing
+        if scripts
+            .scripts
+            .iter()
+            .all(|s| s.handle() != &Handle::default())
+        {
+            continue;
+        }
+
+        for script in scripts.scripts.iter_mut() {
+            if !script.restore_handle(&asset_server) {
+                warn!(
+                    "Script `{}` ({}) was created via reflection without an asset path, it will not be loaded",
+                    script.name(),
+                    script.id()
+                );
+            }
+        }
+    }
 }
 
 /// Handles creating contexts for new/modified scripts
@@ -32,7 +192,7 @@ pub fn script_add_synchronizer<H: ScriptHost + 'static>(
     mut providers: ResMut<APIProviders<H>>,
     script_assets: Res<Assets<H::ScriptAsset>>,
     mut contexts: ResMut<ScriptContexts<H::ScriptContext>>,
-    mut event_writer: EventWriter<ScriptLoaded>,
+    mut lifecycle: ScriptLifecycle,
 ) {
     debug!("Handling addition/modification of scripts");
 
@@ -46,7 +206,7 @@ pub fn script_add_synchronizer<H: ScriptHost + 'static>(
                     &script_assets,
                     &mut providers,
                     &mut contexts,
-                    &mut event_writer,
+                    &mut lifecycle,
                 )
             })
         } else {
@@ -71,7 +231,31 @@ pub fn script_add_synchronizer<H: ScriptHost + 'static>(
             let added_scripts = script_ids.difference(&context_ids);
 
             for r in removed_scripts {
-                contexts.remove_context(*r);
+                lifecycle.remove_script(&mut contexts, *r);
+            }
+
+            // scripts which were already present might have been toggled
+            for script in new_scripts.scripts.iter() {
+                if !context_ids.contains(&script.id())
+                    || !contexts.set_enabled(script.id(), script.is_enabled())
+                {
+                    continue;
+                }
+                lifecycle
+                    .statuses
+                    .set_enabled(script.id(), script.is_enabled());
+
+                if script.is_enabled() {
+                    lifecycle.enabled.send(ScriptEnabled {
+                        sid: script.id(),
+                        entity,
+                    });
+                } else {
+                    lifecycle.disabled.send(ScriptDisabled {
+                        sid: script.id(),
+                        entity,
+                    });
+                }
             }
 
             for a in added_scripts {
@@ -83,7 +267,7 @@ pub fn script_add_synchronizer<H: ScriptHost + 'static>(
                     &script_assets,
                     &mut providers,
                     &mut contexts,
-                    &mut event_writer,
+                    &mut lifecycle,
                 )
             }
         }
@@ -94,6 +278,7 @@ pub fn script_add_synchronizer<H: ScriptHost + 'static>(
 pub fn script_remove_synchronizer<H: ScriptHost>(
     mut query: RemovedComponents<ScriptCollection<H::ScriptAsset>>,
     mut contexts: ResMut<ScriptContexts<H::ScriptContext>>,
+    mut lifecycle: ScriptLifecycle,
 ) {
     for v in query.read() {
         // we know that this entity used to have a script component
@@ -106,7 +291,7 @@ pub fn script_remove_synchronizer<H: ScriptHost>(
             })
             .collect::<Vec<_>>();
         for script_id in script_ids {
-            contexts.remove_context(script_id);
+            lifecycle.remove_script(&mut contexts, script_id);
         }
     }
 }
@@ -119,7 +304,7 @@ pub fn script_hot_reload_handler<H: ScriptHost>(
     script_assets: Res<Assets<H::ScriptAsset>>,
     mut providers: ResMut<APIProviders<H>>,
     mut contexts: ResMut<ScriptContexts<H::ScriptContext>>,
-    mut event_writer: EventWriter<ScriptLoaded>,
+    mut lifecycle: ScriptLifecycle,
 ) {
     for e in events.read() {
         let (handle, created) = match e {
@@ -146,7 +331,7 @@ pub fn script_hot_reload_handler<H: ScriptHost>(
                         &script_assets,
                         &mut providers,
                         &mut contexts,
-                        &mut event_writer,
+                        &mut lifecycle,
                     );
                 }
             }
@@ -154,8 +339,11 @@ pub fn script_hot_reload_handler<H: ScriptHost>(
     }
 }
 
-/// Lets the script host handle all script events
-pub fn script_event_handler<H: ScriptHost, const MAX: u32, const MIN: u32>(world: &mut World) {
+/// Lets the script host handle all script events, followed by the [`ReflectedScriptEvent`]s within its priority range
+pub fn script_event_handler<H: ScriptHost, const MAX: u32, const MIN: u32>(
+    world: &mut World,
+    mut reflected_reader: Local<ManualEventReader<ReflectedScriptEvent>>,
+) {
     // we need to collect the events to drop the borrow of the world
     let mut state: CachedScriptState<H> = world.remove_resource().unwrap();
 
@@ -168,12 +356,47 @@ pub fn script_event_handler<H: ScriptHost, const MAX: u32, const MIN: u32>(world
 
     world.insert_resource(state);
 
+    // reflected events are shared by all hosts, so each handler works with its own copies
+    // and the events stay in the world for scripts to send more of them
+    let reflected = world
+        .get_resource::<Events<ReflectedScriptEvent>>()
+        .map(|e| {
+            reflected_reader
+                .read(e)
+                .filter(|e| (MAX..=MIN).contains(&e.priority) && e.is_for_host::<H>())
+                .map(ReflectedScriptEvent::clone_value)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
     // should help a lot with performance on frames where no events are fired
-    if events.is_empty() {
-        return;
+    if !events.is_empty() || !reflected.is_empty() {
+        handle_script_events::<H>(world, &events, &reflected);
     }
+}
+
+fn handle_script_events<H: ScriptHost>(
+    world: &mut World,
+    events: &[H::ScriptEvent],
+    reflected: &[ReflectedScriptEvent],
+) {
+    // scripts disabled by their failure policies do not receive events
+    let disabled = match world.get_resource_mut::<ScriptFaults>() {
+        Some(mut faults) => {
+            for sid in faults.refresh(Instant::now()) {
+                debug!("Retrying script {} after its backoff period", sid);
+            }
+            faults.disabled_scripts().collect::<HashSet<_>>()
+        }
+        None => Default::default(),
+    };
 
     let mut ctxts: ScriptContexts<H::ScriptContext> = world.remove_resource().unwrap();
+    // as do scripts disabled by the user
+    let disabled = disabled
+        .into_iter()
+        .chain(ctxts.disabled_scripts())
+        .collect::<HashSet<_>>();
 
     let mut host: H = world.remove_resource().unwrap();
     let mut providers: APIProviders<H> = world.remove_resource().unwrap();
@@ -181,9 +404,34 @@ pub fn script_event_handler<H: ScriptHost, const MAX: u32, const MIN: u32>(world
     // we need a resource scope to be able to simultaneously access the contexts as well
     // as provide world access to scripts
     // afaik there is not really a better way to do this in bevy just now
-    let ctx_iter = ctxts
+    // safety: we have unique access to world, future accesses are protected
+    // by the lock in the pointer
+    if !events.is_empty() {
+        let ctx_iter = enabled_contexts(&mut ctxts, &disabled);
+        host.handle_events(world, events, ctx_iter, &mut providers);
+    }
+
+    if !reflected.is_empty() {
+        let ctx_iter = enabled_contexts(&mut ctxts, &disabled);
+        host.handle_reflected_events(world, reflected, ctx_iter, &mut providers);
+    }
+
+    apply_script_commands(world);
+
+    world.insert_resource(ctxts);
+    world.insert_resource(host);
+    world.insert_resource(providers);
+}
+
+/// Iterates over the loaded contexts of scripts which are not disabled
+fn enabled_contexts<'a, C>(
+    ctxts: &'a mut ScriptContexts<C>,
+    disabled: &'a HashSet<u32>,
+) -> impl Iterator<Item = (ScriptData<'a>, &'a mut C)> {
+    ctxts
         .context_entities
         .iter_mut()
+        .filter(|(sid, _)| !disabled.contains(sid))
         .filter_map(|(sid, (entity, o, name))| {
             let ctx = match o {
                 Some(v) => v,
@@ -198,11 +446,102 @@ pub fn script_event_handler<H: ScriptHost, const MAX: u32, const MIN: u32>(world
                 },
                 ctx,
             ))
-        });
+        })
+}
 
-    // safety: we have unique access to world, future accesses are protected
-    // by the lock in the pointer
-    host.handle_events(world, &events, ctx_iter, &mut providers);
+/// Creates a system forwarding every event of type `T` to the scripts of the host `H` as a [`ReflectedScriptEvent`],
+/// calling the given hook with a copy of the event as its only argument
+pub fn script_event_forwarder<T: Event + Reflect, H: ScriptHost>(
+    hook_name: String,
+    priority: u32,
+    recipients: impl Fn(&T) -> Recipients + Send + Sync + 'static,
+) -> impl FnMut(EventReader<T>, EventWriter<ReflectedScriptEvent>) {
+    move |mut events, mut writer| {
+        writer.send_batch(events.read().map(|event| {
+            let mut forwarded = ReflectedScriptEvent::new(hook_name.clone(), priority)
+                .with_recipients(recipients(event))
+                .for_host::<H>();
+            forwarded.args.push(event.clone_value());
+            forwarded
+        }));
+    }
+}
+
+/// Creates a system sending an `on_component_added` and `on_component_removed` [`ReflectedScriptEvent`] to the scripts
+/// returned by `recipients` whenever a component of type `C` is added to or removed from an entity.
+///
+/// The hooks are called with the entity and the short type path of the component, e.g. `on_component_added(entity, "Transform")`.
+pub fn script_component_watcher<C: Component + TypePath>(
+    priority: u32,
+    recipients: impl Fn(Entity) -> Recipients + Send + Sync + 'static,
+) -> impl FnMut(Query<Entity, Added<C>>, RemovedComponents<C>, EventWriter<ReflectedScriptEvent>) {
+    move |added, mut removed, mut writer| {
+        let event = |hook_name, entity| {
+            ReflectedScriptEvent::new(hook_name, priority)
+                .with_recipients(recipients(entity))
+                .with_arg(entity)
+                .with_arg(C::short_type_path().to_owned())
+        };
+        writer.send_batch(
+            added
+                .iter()
+                .map(|entity| event("on_component_added", entity))
+                .chain(
+                    removed
+                        .read()
+                        .map(|entity| event("on_component_removed", entity)),
+                ),
+        );
+    }
+}
+
+/// Calls the `on_enable` and `on_disable` hooks of scripts which were toggled via [`Script::set_enabled`]
+pub fn script_toggle_handler<H: ScriptHost>(
+    world: &mut World,
+    mut enabled_reader: Local<ManualEventReader<ScriptEnabled>>,
+    mut disabled_reader: Local<ManualEventReader<ScriptDisabled>>,
+) {
+    let enabled = enabled_reader
+        .read(world.resource::<Events<ScriptEnabled>>())
+        .map(|e| e.sid)
+        .collect::<HashSet<_>>();
+    let disabled = disabled_reader
+        .read(world.resource::<Events<ScriptDisabled>>())
+        .map(|e| e.sid)
+        .collect::<HashSet<_>>();
+
+    if enabled.is_empty() && disabled.is_empty() {
+        return;
+    }
+
+    let mut ctxts: ScriptContexts<H::ScriptContext> = world.remove_resource().unwrap();
+    let mut host: H = world.remove_resource().unwrap();
+    let mut providers: APIProviders<H> = world.remove_resource().unwrap();
+
+    for (hook, sids) in [("on_enable", enabled), ("on_disable", disabled)] {
+        if sids.is_empty() {
+            continue;
+        }
+
+        let ctx_iter = ctxts
+            .context_entities
+            .iter_mut()
+            .filter(|(sid, _)| sids.contains(sid))
+            .filter_map(|(sid, (entity, o, name))| {
+                Some((
+                    ScriptData {
+                        sid: *sid,
+                        entity: *entity,
+                        name,
+                    },
+                    o.as_mut()?,
+                ))
+            });
+
+        host.call_lifecycle_hook(world, hook, ctx_iter, &mut providers);
+    }
+
+    apply_script_commands(world);
 
     world.insert_resource(ctxts);
     world.insert_resource(host);
@@ -226,3 +565,80 @@ impl<H: ScriptHost> FromWorld for CachedScriptState<H> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WatchComponentForScripts;
+
+    #[derive(Component, Reflect)]
+    struct Watched;
+
+    #[derive(Component, Reflect)]
+    struct WatchedByEntity;
+
+    /// Reads the hook name, recipients and arguments of all reflected events sent so far
+    fn sent_events(app: &App) -> Vec<(String, Recipients, Entity, String)> {
+        let events = app.world.resource::<Events<ReflectedScriptEvent>>();
+        events
+            .get_reader()
+            .read(events)
+            .map(|e| {
+                (
+                    e.hook_name.clone(),
+                    e.recipients.clone(),
+                    *e.args[0].downcast_ref::<Entity>().unwrap(),
+                    e.args[1].downcast_ref::<String>().unwrap().clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn setup_app() -> App {
+        let mut app = App::new();
+        app.watch_component_for_scripts::<Watched>(0)
+            .watch_component_for_entity_scripts::<WatchedByEntity>(0);
+        app
+    }
+
+    #[test]
+    fn watched_components_notify_all_scripts() {
+        let mut app = setup_app();
+        let entity = app.world.spawn(Watched).id();
+        app.update();
+        app.world.entity_mut(entity).remove::<Watched>();
+        app.update();
+
+        let events = sent_events(&app);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            (hook, Recipients::All, e, ty) if hook == "on_component_added" && *e == entity && ty == "Watched"
+        ));
+        assert!(matches!(
+            &events[1],
+            (hook, Recipients::All, e, ty) if hook == "on_component_removed" && *e == entity && ty == "Watched"
+        ));
+    }
+
+    #[test]
+    fn entity_watched_components_notify_the_entity_scripts() {
+        let mut app = setup_app();
+        let entity = app.world.spawn(WatchedByEntity).id();
+        app.update();
+        app.world.despawn(entity);
+        app.update();
+
+        let events = sent_events(&app);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            (hook, Recipients::Entity(target), e, ty)
+                if hook == "on_component_added" && *target == entity && *e == entity && ty == "WatchedByEntity"
+        ));
+        assert!(matches!(
+            &events[1],
+            (hook, Recipients::Entity(target), e, _) if hook == "on_component_removed" && *target == entity && *e == entity
+        ));
+    }
+}
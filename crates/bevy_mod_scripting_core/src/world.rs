@@ -1,7 +1,15 @@
 use std::ops::Deref;
 use std::sync::Arc;
 
-use bevy::prelude::World;
+use bevy::{
+    ecs::{
+        change_detection::{CHECK_TICK_THRESHOLD, MAX_CHANGE_AGE},
+        component::Tick,
+        system::{CommandQueue, SystemChangeTick},
+    },
+    prelude::{ResMut, Resource, World},
+    utils::HashMap,
+};
 use parking_lot::{
     MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
 };
@@ -89,6 +97,14 @@ impl WorldPointer {
         self.try_write_inner(false)
     }
 
+    /// Returns true if the [WorldPointerGuard] that created this pointer was dropped,
+    /// in which case all attempts to access the world fail.
+    ///
+    /// A pointer which is currently locked is still in scope.
+    pub fn is_out_of_scope(&self) -> bool {
+        self.0.try_read().is_some_and(|ptr| ptr.is_none())
+    }
+
     /// Returns a read guard which can be used for immutable world access.
     ///
     /// Panics if the pointer has gone out of scope. May block if another thread
@@ -157,3 +173,189 @@ impl WorldPointer {
         ))
     }
 }
+
+/// Structural world changes made by scripts (spawning and despawning entities, inserting and removing components, hierarchy edits).
+///
+/// By default these are applied immediately. In deferred mode they are queued instead and applied once the current
+/// script handler finishes calling scripts, so references held by other scripts stay valid throughout the pass.
+/// Entities spawned in deferred mode are reserved immediately and can be used in further changes before they are applied.
+#[derive(Resource, Default)]
+pub struct ScriptCommands {
+    /// whether changes are queued rather than applied immediately
+    pub deferred: bool,
+    queue: CommandQueue,
+}
+
+impl ScriptCommands {
+    /// Creates a buffer in deferred mode
+    pub fn deferred() -> Self {
+        Self {
+            deferred: true,
+            ..Default::default()
+        }
+    }
+
+    /// Queues the given change, to be applied by [`apply_script_commands`]
+    pub fn push(&mut self, command: impl FnOnce(&mut World) + Send + 'static) {
+        self.queue.push(command);
+    }
+}
+
+/// Applies all structural changes queued by scripts in deferred mode, see [`ScriptCommands`]
+pub fn apply_script_commands(world: &mut World) {
+    if let Some(mut commands) = world.get_resource_mut::<ScriptCommands>() {
+        let mut queue = std::mem::take(&mut commands.queue);
+        queue.apply(world);
+    }
+}
+
+/// The change tick of the world during each script's previous run, used to tell which components
+/// were added or changed since then.
+///
+/// All scripts called during the same pass of a script handler share a single change tick, so changes made by a script
+/// are reported to the scripts running after it in that pass, but not to the ones which already ran before it.
+#[derive(Resource, Default)]
+pub struct ScriptChangeTicks {
+    last_run: HashMap<u32, Tick>,
+    current: Option<Tick>,
+    /// the change tick at which the stored ticks were last checked, see [`check_script_change_ticks`]
+    last_check: u32,
+}
+
+impl ScriptChangeTicks {
+    /// The tick of the previous run of the script with the given id, if it ran before
+    pub fn last_run(&self, script_id: u32) -> Option<Tick> {
+        self.last_run.get(&script_id).copied()
+    }
+
+    /// The tick of the previous run of the script currently running.
+    ///
+    /// If the script did not run before (or no script is running) this is the zero tick, so everything counts as changed.
+    pub fn current_last_run(&self) -> Tick {
+        self.current.unwrap_or(Tick::new(0))
+    }
+
+    /// Stops tracking the given script
+    pub fn remove(&mut self, script_id: u32) {
+        self.last_run.remove(&script_id);
+    }
+
+    /// Clamps the stored ticks so they are not mistaken for recent ones once the world's change tick wraps around,
+    /// the same way bevy clamps component ticks
+    pub fn check_change_ticks(&mut self, change_tick: Tick) {
+        for tick in self.last_run.values_mut() {
+            if change_tick.get().wrapping_sub(tick.get()) > MAX_CHANGE_AGE {
+                *tick = Tick::new(change_tick.get().wrapping_sub(MAX_CHANGE_AGE));
+            }
+        }
+    }
+}
+
+/// Runs a script callback, recording the current change tick of the world for the given script, see [`ScriptChangeTicks`].
+///
+/// The world's change tick is not advanced, so changes made by the script itself are not newer than the recorded tick
+/// and are not reported to it on its next run.
+pub fn run_script_callback<O>(world: &WorldPointer, script_id: u32, f: impl FnOnce() -> O) -> O {
+    {
+        let mut w = world.write();
+        let mut ticks = w.get_resource_or_insert_with(ScriptChangeTicks::default);
+        ticks.current = ticks.last_run(script_id);
+    }
+
+    let out = f();
+
+    let mut w = world.write();
+    let tick = w.read_change_tick();
+    let mut ticks = w.resource_mut::<ScriptChangeTicks>();
+    ticks.last_run.insert(script_id, tick);
+    ticks.current = None;
+    out
+}
+
+/// Keeps the ticks stored in [`ScriptChangeTicks`] valid, running the check as often as bevy does for component ticks
+pub fn check_script_change_ticks(
+    mut ticks: ResMut<ScriptChangeTicks>,
+    system_ticks: SystemChangeTick,
+) {
+    let change_tick = system_ticks.this_run();
+    if change_tick.get().wrapping_sub(ticks.last_check) > CHECK_TICK_THRESHOLD {
+        ticks.check_change_ticks(change_tick);
+        ticks.last_check = change_tick.get();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::Component;
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<u32>);
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn push_log(commands: &mut ScriptCommands, i: u32) {
+        commands.push(move |w: &mut World| w.resource_mut::<Log>().0.push(i));
+    }
+
+    #[test]
+    fn commands_are_applied_in_the_order_they_were_queued() {
+        let mut world = World::new();
+        world.init_resource::<Log>();
+        let mut commands = ScriptCommands::deferred();
+        for i in 0..5 {
+            push_log(&mut commands, i);
+        }
+        world.insert_resource(commands);
+
+        assert!(world.resource::<Log>().0.is_empty());
+        apply_script_commands(&mut world);
+        assert_eq!(world.resource::<Log>().0, vec![0, 1, 2, 3, 4]);
+
+        // the queue is drained once applied
+        apply_script_commands(&mut world);
+        assert_eq!(world.resource::<Log>().0, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn later_commands_see_the_effects_of_earlier_ones() {
+        let mut world = World::new();
+        let entity = world.entities().reserve_entity();
+        let mut commands = ScriptCommands::deferred();
+        commands.push(move |w: &mut World| {
+            w.get_or_spawn(entity).unwrap().insert(Marker);
+        });
+        commands.push(move |w: &mut World| {
+            assert!(w.entity(entity).contains::<Marker>());
+            w.despawn(entity);
+        });
+        world.insert_resource(commands);
+
+        apply_script_commands(&mut world);
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn old_ticks_are_clamped() {
+        let mut ticks = ScriptChangeTicks::default();
+        ticks.last_run.insert(0, Tick::new(5));
+        ticks.last_run.insert(1, Tick::new(100));
+
+        let change_tick = Tick::new(MAX_CHANGE_AGE + 50);
+        ticks.check_change_ticks(change_tick);
+        assert_eq!(ticks.last_run(0), Some(Tick::new(50)));
+        assert_eq!(ticks.last_run(1), Some(Tick::new(100)));
+
+        ticks.remove(1);
+        assert_eq!(ticks.last_run(1), None);
+    }
+
+    #[test]
+    fn applying_without_commands_does_nothing() {
+        let mut world = World::new();
+        apply_script_commands(&mut world);
+        assert_eq!(world.iter_entities().count(), 0);
+    }
+}
@@ -1,22 +1,40 @@
 use std::{
+    any::TypeId,
     ops::{Deref, DerefMut},
+    str::FromStr,
     sync::Arc,
 };
 
 use crate::ReflectReference;
 /// Common functionality for all script hosts
 use bevy::{
-    ecs::system::Command,
+    ecs::{
+        component::{ComponentId, Tick},
+        system::Command,
+        world::EntityRef,
+    },
+    log::warn,
     prelude::{
-        AppTypeRegistry, BuildWorldChildren, Children, DespawnChildrenRecursive, DespawnRecursive,
-        Entity, Parent, ReflectComponent, ReflectDefault, ReflectResource,
+        App, AppTypeRegistry, BuildWorldChildren, Children, DespawnChildrenRecursive,
+        DespawnRecursive, Entity, Event, Parent, ReflectComponent, ReflectDefault, ReflectResource,
+        World,
     },
     reflect::{
         DynamicArray, DynamicEnum, DynamicList, DynamicMap, DynamicStruct, DynamicTuple,
-        DynamicTupleStruct, TypeRegistration,
+        DynamicTupleStruct, DynamicVariant, FromReflect, FromType, GetTypeRegistration, Reflect,
+        ReflectFromReflect, Struct, Tuple, TypeInfo, TypePath, TypeRegistration, TypeRegistry,
+        VariantInfo,
     },
 };
-use bevy_mod_scripting_core::{prelude::ScriptError, world::WorldPointer};
+use bevy_mod_scripting_core::{
+    prelude::ScriptError,
+    world::{ScriptChangeTicks, ScriptCommands, WorldPointer},
+};
+
+pub mod script_component;
+pub mod script_fields;
+pub mod script_resource;
+pub mod script_value;
 
 /// Helper trait for retrieving a world pointer from a script context.
 pub trait GetWorld {
@@ -24,6 +42,112 @@ pub trait GetWorld {
     fn get_world(&self) -> Result<WorldPointer, Self::Error>;
 }
 
+/// Type data allowing scripts to send events of the reflected type, see [`RegisterScriptEvent`]
+#[derive(Clone)]
+pub struct ReflectScriptSendable {
+    send: fn(&mut World, &dyn Reflect) -> Result<(), ScriptError>,
+}
+
+impl ReflectScriptSendable {
+    /// Constructs an event of the reflected type from the given value via `FromReflect` and sends it
+    pub fn send(&self, world: &mut World, event: &dyn Reflect) -> Result<(), ScriptError> {
+        (self.send)(world, event)
+    }
+}
+
+impl<T: Event + FromReflect + TypePath> FromType<T> for ReflectScriptSendable {
+    fn from_type() -> Self {
+        Self {
+            send: |world, event| {
+                let event = T::from_reflect(event).ok_or_else(|| {
+                    ScriptError::Other(format!(
+                        "Could not construct event `{}` from `{}`",
+                        T::type_path(),
+                        event.reflect_type_path()
+                    ))
+                })?;
+                world.send_event(event);
+                Ok(())
+            },
+        }
+    }
+}
+
+/// A trait allowing scripts to send events of the given type via `world:send_event`
+pub trait RegisterScriptEvent {
+    /// Registers the event type with the type registry along with [`ReflectScriptSendable`] type data,
+    /// and adds the event to the app if it wasn't already
+    fn register_script_event<T: Event + FromReflect + TypePath + GetTypeRegistration>(
+        &mut self,
+    ) -> &mut Self;
+}
+
+impl RegisterScriptEvent for App {
+    fn register_script_event<T: Event + FromReflect + TypePath + GetTypeRegistration>(
+        &mut self,
+    ) -> &mut Self {
+        self.add_event::<T>()
+            .register_type::<T>()
+            .register_type_data::<T, ReflectScriptSendable>()
+    }
+}
+
+/// Restricts component lookups to components added or changed since the previous run of the current script,
+/// see [`ScriptChangeTicks`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeFilter {
+    Added,
+    Changed,
+}
+
+impl ChangeFilter {
+    /// Returns true if the given component of the entity passes this filter
+    fn matches(&self, w: &World, entity_ref: EntityRef, component_id: ComponentId) -> bool {
+        let last_run = w
+            .get_resource::<ScriptChangeTicks>()
+            .map(ScriptChangeTicks::current_last_run)
+            .unwrap_or(Tick::new(0));
+        let this_run = w.read_change_tick();
+
+        entity_ref
+            .get_change_ticks_by_id(component_id)
+            .is_some_and(|ticks| match self {
+                ChangeFilter::Added => ticks.is_added(last_run, this_run),
+                ChangeFilter::Changed => ticks.is_changed(last_run, this_run),
+            })
+    }
+}
+
+impl FromStr for ChangeFilter {
+    type Err = ScriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(ChangeFilter::Added),
+            "changed" => Ok(ChangeFilter::Changed),
+            _ => Err(ScriptError::Other(format!(
+                "Invalid change filter `{s}`, expected `added` or `changed`"
+            ))),
+        }
+    }
+}
+
+/// Returns all entities which have the given component and pass the given filter
+fn query_component_id(
+    w: &World,
+    component_id: ComponentId,
+    filter: Option<ChangeFilter>,
+) -> Vec<Entity> {
+    w.archetypes()
+        .iter()
+        .filter(|archetype| archetype.contains(component_id))
+        .flat_map(|archetype| archetype.entities().iter().map(|entity| entity.id()))
+        .filter(|entity| {
+            filter.is_none_or(|filter| filter.matches(w, w.entity(*entity), component_id))
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct ScriptTypeRegistration(pub(crate) Arc<TypeRegistration>);
 
@@ -116,37 +240,77 @@ impl ScriptWorld {
         w.get::<Parent>(entity).map(|parent| parent.get())
     }
 
-    pub fn push_child(&self, parent: Entity, child: Entity) {
-        let mut w = self.write();
-        if let Some(mut entity) = w.get_entity_mut(parent) {
-            entity.push_children(&[child]);
-        }
+    /// Returns true if structural changes are queued rather than applied immediately, see [`ScriptCommands`]
+    pub fn is_deferred(&self) -> bool {
+        let w = self.read();
+        w.get_resource::<ScriptCommands>()
+            .is_some_and(|commands| commands.deferred)
     }
 
-    pub fn remove_children(&self, parent: Entity, children: &[Entity]) {
+    /// Applies the given structural change immediately, or queues it if scripts are in deferred mode
+    fn apply_or_defer(&self, command: impl FnOnce(&mut World) + Send + 'static) {
         let mut w = self.write();
+        match w.get_resource_mut::<ScriptCommands>() {
+            Some(mut commands) if commands.deferred => commands.push(command),
+            _ => command(&mut w),
+        };
+    }
 
-        if let Some(mut entity) = w.get_entity_mut(parent) {
-            entity.remove_children(children);
-        }
+    pub fn push_child(&self, parent: Entity, child: Entity) {
+        self.push_children(parent, &[child])
     }
 
-    pub fn insert_children(&self, parent: Entity, index: usize, children: &[Entity]) {
-        let mut w = self.write();
+    pub fn push_children(&self, parent: Entity, children: &[Entity]) {
+        let children = children.to_vec();
+        self.apply_or_defer(move |w| {
+            if let Some(mut entity) = w.get_entity_mut(parent) {
+                entity.push_children(&children);
+            }
+        })
+    }
 
-        if let Some(mut entity) = w.get_entity_mut(parent) {
-            entity.insert_children(index, children);
-        }
+    pub fn remove_children(&self, parent: Entity, children: &[Entity]) {
+        let children = children.to_vec();
+        self.apply_or_defer(move |w| {
+            if let Some(mut entity) = w.get_entity_mut(parent) {
+                entity.remove_children(&children);
+            }
+        })
+    }
+
+    pub fn insert_children(&self, parent: Entity, index: usize, children: &[Entity]) {
+        let children = children.to_vec();
+        self.apply_or_defer(move |w| {
+            if let Some(mut entity) = w.get_entity_mut(parent) {
+                entity.insert_children(index, &children);
+            }
+        })
     }
 
     pub fn despawn_children_recursive(&self, entity: Entity) {
-        let mut w = self.write();
-        DespawnChildrenRecursive { entity }.apply(&mut w);
+        self.apply_or_defer(move |w| DespawnChildrenRecursive { entity }.apply(w))
     }
 
     pub fn despawn_recursive(&self, entity: Entity) {
+        self.apply_or_defer(move |w| DespawnRecursive { entity }.apply(w))
+    }
+
+    /// Spawns a new empty entity, in deferred mode the entity is reserved and spawned once changes are applied
+    pub fn spawn(&self) -> Entity {
         let mut w = self.write();
-        DespawnRecursive { entity }.apply(&mut w);
+        match w.get_resource::<ScriptCommands>() {
+            Some(commands) if commands.deferred => w.entities().reserve_entity(),
+            _ => w.spawn(()).id(),
+        }
+    }
+
+    /// Despawns the given entity, returns true if the entity existed
+    pub fn despawn(&self, entity: Entity) -> bool {
+        let exists = self.read().entities().contains(entity);
+        self.apply_or_defer(move |w| {
+            w.despawn(entity);
+        });
+        exists
     }
 
     pub fn get_type_by_name(&self, type_name: &str) -> Option<ScriptTypeRegistration> {
@@ -162,55 +326,135 @@ impl ScriptWorld {
             .map(|registration| ScriptTypeRegistration::new(Arc::new(registration.clone())))
     }
 
+    /// Returns an error if the given entity does not exist (or was not reserved in deferred mode)
+    pub(crate) fn check_entity(&self, entity: Entity) -> Result<(), ScriptError> {
+        if !self.read().entities().contains(entity) {
+            return Err(ScriptError::Other(format!(
+                "Entity is not valid {:#?}",
+                entity
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the given entity exists and the given type is a component, returning its reflected component data
+    fn check_component(
+        &self,
+        entity: Entity,
+        comp_type: &ScriptTypeRegistration,
+    ) -> Result<ReflectComponent, ScriptError> {
+        self.check_entity(entity)?;
+        comp_type
+            .data::<ReflectComponent>()
+            .cloned()
+            .ok_or_else(|| {
+                ScriptError::Other(format!("Not a component {}", comp_type.short_name()))
+            })
+    }
+
+    /// Runs the given component insertion immediately, or queues it if scripts are in deferred mode.
+    ///
+    /// Returns true if the component was inserted immediately. Deferred insertions can only fail if the entity
+    /// is despawned before changes are applied, which is logged as there is no script left to report the error to
+    pub(crate) fn insert_or_defer(
+        &self,
+        entity: Entity,
+        type_name: String,
+        insert: impl FnOnce(&mut World) -> Result<(), ScriptError> + Send + 'static,
+    ) -> Result<bool, ScriptError> {
+        if !self.is_deferred() {
+            insert(&mut self.write())?;
+            return Ok(true);
+        }
+
+        self.apply_or_defer(move |w| {
+            if let Err(e) = insert(w) {
+                warn!("Deferred insertion of component `{type_name}` into {entity:?} failed: {e}");
+            }
+        });
+        Ok(false)
+    }
+
+    /// Inserts a default instance of the given component type and returns a reference to it.
+    ///
+    /// In deferred mode the component does not exist until changes are applied, so `None` is returned instead
     pub fn add_default_component(
         &self,
         entity: Entity,
         comp_type: ScriptTypeRegistration,
-    ) -> Result<ReflectReference, ScriptError> {
-        let mut w = self.write();
+    ) -> Result<Option<ReflectReference>, ScriptError> {
+        let component_data = self.check_component(entity, &comp_type)?;
+        let value = default_component_value(&comp_type)?;
 
-        // Remove: AppTypeRegistry
-        let registry: AppTypeRegistry = w.remove_resource().unwrap();
+        let type_name = comp_type.short_name().to_owned();
+        let inserted = self.insert_or_defer(entity, type_name, move |w| {
+            insert_component(w, entity, &comp_type, value.as_ref())
+        })?;
 
-        let mut entity_ref = w
-            .get_entity_mut(entity)
-            .ok_or_else(|| ScriptError::Other(format!("Entity is not valid {:#?}", entity)))?;
+        Ok(inserted.then(|| {
+            ReflectReference::new_component_ref(component_data, entity, self.clone().into())
+        }))
+    }
 
-        let component_data = comp_type.data::<ReflectComponent>().ok_or_else(|| {
-            ScriptError::Other(format!("Not a component {}", comp_type.short_name()))
-        })?;
+    /// Inserts the given value as a component of the given type, replacing any existing component of that type,
+    /// and returns a reference to it.
+    ///
+    /// In deferred mode the component does not exist until changes are applied, so `None` is returned instead
+    pub fn insert_component(
+        &self,
+        entity: Entity,
+        comp_type: ScriptTypeRegistration,
+        value: Box<dyn Reflect>,
+    ) -> Result<Option<ReflectReference>, ScriptError> {
+        let component_data = self.check_component(entity, &comp_type)?;
+        // values of other types cannot be converted into the component and would panic on insertion
+        if let Some(info) = value.get_represented_type_info() {
+            if info.type_id() != comp_type.type_id() {
+                return Err(ScriptError::Other(format!(
+                    "Cannot insert a value of type `{}` as component {}",
+                    info.type_path(),
+                    comp_type.short_name()
+                )));
+            }
+        }
 
-        let registry_lock = registry.read();
+        let type_name = comp_type.short_name().to_owned();
+        let inserted = self.insert_or_defer(entity, type_name, move |w| {
+            insert_component(w, entity, &comp_type, value.as_ref())
+        })?;
 
-        // this is just a formality
-        // TODO: maybe get an add_default impl added to ReflectComponent
-        // this means that we don't require ReflectDefault for adding components!
-        match comp_type.0.type_info(){
-            bevy::reflect::TypeInfo::Struct(_) => component_data.insert(&mut entity_ref, &DynamicStruct::default(), &registry_lock),
-            bevy::reflect::TypeInfo::TupleStruct(_) => component_data.insert(&mut entity_ref, &DynamicTupleStruct::default(), &registry_lock),
-            bevy::reflect::TypeInfo::Tuple(_) => component_data.insert(&mut entity_ref, &DynamicTuple::default(), &registry_lock),
-            bevy::reflect::TypeInfo::List(_) => component_data.insert(&mut entity_ref, &DynamicList::default(), &registry_lock),
-            bevy::reflect::TypeInfo::Array(_) => component_data.insert(&mut entity_ref, &DynamicArray::new(Box::new([])), &registry_lock),
-            bevy::reflect::TypeInfo::Map(_) => component_data.insert(&mut entity_ref, &DynamicMap::default(), &registry_lock),
-            bevy::reflect::TypeInfo::Value(_) => component_data.insert(&mut entity_ref,
-                comp_type.data::<ReflectDefault>().ok_or_else(||
-                    ScriptError::Other(format!("Component {} is a value or dynamic type with no `ReflectDefault` type_data, cannot instantiate sensible value",comp_type.short_name())))?
-                    .default()
-                    .as_ref(),
-                    &registry_lock),
-            bevy::reflect::TypeInfo::Enum(_) => component_data.insert(&mut entity_ref, &DynamicEnum::default(), &registry_lock)
-        };
-        // if we do not drop the lock here, line below will complain registry is still borrowed at drop
-        drop(registry_lock);
+        Ok(inserted.then(|| {
+            ReflectReference::new_component_ref(component_data, entity, self.clone().into())
+        }))
+    }
 
-        // Insert: AppTypeRegistry
-        w.insert_resource(registry);
+    /// Spawns a new entity with the given component values, see [`Self::insert_component`].
+    ///
+    /// Either all components are inserted or the entity is despawned again and the error returned
+    pub fn spawn_with(
+        &self,
+        components: Vec<(ScriptTypeRegistration, Box<dyn Reflect>)>,
+    ) -> Result<Entity, ScriptError> {
+        if let Some((comp_type, _)) = components
+            .iter()
+            .find(|(comp_type, _)| comp_type.data::<ReflectComponent>().is_none())
+        {
+            return Err(ScriptError::Other(format!(
+                "Not a component {}",
+                comp_type.short_name()
+            )));
+        }
 
-        Ok(ReflectReference::new_component_ref(
-            component_data.clone(),
-            entity,
-            self.clone().into(),
-        ))
+        let entity = self.spawn();
+        let inserted = components.into_iter().try_for_each(|(comp_type, value)| {
+            self.insert_component(entity, comp_type, value).map(|_| ())
+        });
+        if let Err(e) = inserted {
+            // do not leave a partially built entity behind
+            self.despawn(entity);
+            return Err(e);
+        }
+        Ok(entity)
     }
 
     pub fn get_component(
@@ -233,6 +477,45 @@ impl ScriptWorld {
         }))
     }
 
+    /// Like [`Self::get_component`] but returns `None` if the component was not added or changed (depending on the filter)
+    /// since the previous run of the current script
+    pub fn get_component_filtered(
+        &self,
+        entity: Entity,
+        comp_type: ScriptTypeRegistration,
+        filter: ChangeFilter,
+    ) -> Result<Option<ReflectReference>, ScriptError> {
+        let component = self.get_component(entity, comp_type.clone())?;
+
+        let w = self.read();
+        let passes = w
+            .components()
+            .get_id(comp_type.type_id())
+            .is_some_and(|id| filter.matches(&w, w.entity(entity), id));
+        Ok(component.filter(|_| passes))
+    }
+
+    /// Returns all entities which have a component of the given type,
+    /// optionally only those whose component was added or changed since the previous run of the current script
+    pub fn query_component(
+        &self,
+        comp_type: ScriptTypeRegistration,
+        filter: Option<ChangeFilter>,
+    ) -> Result<Vec<Entity>, ScriptError> {
+        if comp_type.data::<ReflectComponent>().is_none() {
+            return Err(ScriptError::Other(format!(
+                "Not a component {}",
+                comp_type.short_name()
+            )));
+        }
+
+        let w = self.read();
+        Ok(w.components()
+            .get_id(comp_type.type_id())
+            .map(|id| query_component_id(&w, id, filter))
+            .unwrap_or_default())
+    }
+
     pub fn has_component(
         &self,
         entity: Entity,
@@ -255,16 +538,25 @@ impl ScriptWorld {
         entity: Entity,
         comp_type: ScriptTypeRegistration,
     ) -> Result<(), ScriptError> {
-        let mut w = self.write();
+        if !self.read().entities().contains(entity) {
+            return Err(ScriptError::Other(format!(
+                "Entity is not valid {:#?}",
+                entity
+            )));
+        }
 
-        let mut entity_ref = w
-            .get_entity_mut(entity)
-            .ok_or_else(|| ScriptError::Other(format!("Entity is not valid {:#?}", entity)))?;
+        let component_data = comp_type
+            .data::<ReflectComponent>()
+            .ok_or_else(|| {
+                ScriptError::Other(format!("Not a component {}", comp_type.short_name()))
+            })?
+            .clone();
 
-        let component_data = comp_type.data::<ReflectComponent>().ok_or_else(|| {
-            ScriptError::Other(format!("Not a component {}", comp_type.short_name()))
-        })?;
-        component_data.remove(&mut entity_ref);
+        self.apply_or_defer(move |w| {
+            if let Some(mut entity_ref) = w.get_entity_mut(entity) {
+                component_data.remove(&mut entity_ref);
+            }
+        });
         Ok(())
     }
 
@@ -292,6 +584,23 @@ impl ScriptWorld {
 
         Ok(resource_data.reflect(&w).is_some())
     }
+    /// Sends an event of the given type constructed from the given value, the type must be registered via [`RegisterScriptEvent`]
+    pub fn send_event(
+        &self,
+        event_type: ScriptTypeRegistration,
+        event: &dyn Reflect,
+    ) -> Result<(), ScriptError> {
+        let sendable = event_type.data::<ReflectScriptSendable>().ok_or_else(|| {
+            ScriptError::Other(format!(
+                "Not an event which scripts can send {}",
+                event_type.short_name()
+            ))
+        })?;
+
+        let mut w = self.write();
+        sendable.send(&mut w, event)
+    }
+
     pub fn remove_resource(&mut self, res_type: ScriptTypeRegistration) -> Result<(), ScriptError> {
         let mut w = self.write();
 
@@ -302,3 +611,476 @@ impl ScriptWorld {
         Ok(())
     }
 }
+
+/// Instantiates a default value of the given component type, possibly as a dynamic representation of it
+fn default_component_value(
+    comp_type: &ScriptTypeRegistration,
+) -> Result<Box<dyn Reflect>, ScriptError> {
+    // this is just a formality
+    // TODO: maybe get an add_default impl added to ReflectComponent
+    // this means that we don't require ReflectDefault for adding components!
+    Ok(match comp_type.0.type_info() {
+        TypeInfo::Struct(_) => Box::<DynamicStruct>::default(),
+        TypeInfo::TupleStruct(_) => Box::<DynamicTupleStruct>::default(),
+        TypeInfo::Tuple(_) => Box::<DynamicTuple>::default(),
+        TypeInfo::List(_) => Box::<DynamicList>::default(),
+        TypeInfo::Array(_) => Box::new(DynamicArray::new(Box::new([]))),
+        TypeInfo::Map(_) => Box::<DynamicMap>::default(),
+        TypeInfo::Value(_) => comp_type
+            .data::<ReflectDefault>()
+            .ok_or_else(|| {
+                ScriptError::Other(format!("Component {} is a value or dynamic type with no `ReflectDefault` type_data, cannot instantiate sensible value", comp_type.short_name()))
+            })?
+            .default(),
+        TypeInfo::Enum(_) => Box::<DynamicEnum>::default(),
+    })
+}
+
+/// Inserts the given value as a component of the given type into the given entity,
+/// the value may be a dynamic representation of the component
+fn insert_component(
+    w: &mut World,
+    entity: Entity,
+    comp_type: &ScriptTypeRegistration,
+    value: &dyn Reflect,
+) -> Result<(), ScriptError> {
+    let component_data = comp_type
+        .data::<ReflectComponent>()
+        .ok_or_else(|| ScriptError::Other(format!("Not a component {}", comp_type.short_name())))?;
+
+    if w.get_entity(entity).is_none() {
+        return Err(ScriptError::Other(format!(
+            "Entity is not valid {:#?}",
+            entity
+        )));
+    }
+
+    // Remove: AppTypeRegistry
+    let registry: AppTypeRegistry = w.remove_resource().unwrap();
+    {
+        let registry_lock = registry.read();
+        let mut entity_ref = w.entity_mut(entity);
+        component_data.insert(&mut entity_ref, value, &registry_lock);
+    }
+    // Insert: AppTypeRegistry
+    w.insert_resource(registry);
+
+    Ok(())
+}
+
+/// Retrieves the registration of the given type
+pub fn type_registration(
+    world_ptr: &WorldPointer,
+    type_id: TypeId,
+) -> Result<TypeRegistration, ScriptError> {
+    let world = world_ptr.read();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    registry
+        .get(type_id)
+        .cloned()
+        .ok_or_else(|| ScriptError::Other(format!("Type with id {type_id:?} is not registered")))
+}
+
+/// Converts a dynamic value into the concrete type via `ReflectFromReflect` where possible
+pub fn from_dynamic(
+    world_ptr: &WorldPointer,
+    type_id: TypeId,
+    value: Box<dyn Reflect>,
+) -> Box<dyn Reflect> {
+    let world = world_ptr.read();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    registry
+        .get_type_data::<ReflectFromReflect>(type_id)
+        .and_then(|from_reflect| from_reflect.from_reflect(value.as_ref()))
+        .unwrap_or(value)
+}
+
+/// Looks up the variant with the given name of the given enum type
+pub fn enum_variant_info(
+    type_info: &'static TypeInfo,
+    variant: &str,
+) -> Result<&'static VariantInfo, ScriptError> {
+    let TypeInfo::Enum(info) = type_info else {
+        return Err(ScriptError::Other(format!(
+            "`{}` is not an enum",
+            type_info.type_path()
+        )));
+    };
+    info.variant(variant).ok_or_else(|| {
+        ScriptError::Other(format!(
+            "`{}` has no variant `{variant}`",
+            type_info.type_path()
+        ))
+    })
+}
+
+/// Builds a value of the given enum type with the given variant from a dynamic variant value,
+/// fields missing from the value are set to the default values of their types.
+///
+/// The result can be converted into the concrete enum type via `ReflectFromReflect`.
+pub fn new_enum_variant(
+    registry: &TypeRegistry,
+    type_info: &'static TypeInfo,
+    variant: &str,
+    value: DynamicVariant,
+) -> Result<DynamicEnum, ScriptError> {
+    let variant_info = enum_variant_info(type_info, variant)?;
+    let default_field = |field: &dyn std::fmt::Display, field_type: TypeId| {
+        registry
+            .get_type_data::<ReflectDefault>(field_type)
+            .map(|default| default.default())
+            .ok_or_else(|| {
+                ScriptError::Other(format!(
+                    "Missing field `{field}` of variant `{variant}`, its type does not reflect `Default`"
+                ))
+            })
+    };
+
+    let value = match (variant_info, value) {
+        (VariantInfo::Unit(_), DynamicVariant::Unit) => DynamicVariant::Unit,
+        (VariantInfo::Struct(info), DynamicVariant::Struct(fields)) => {
+            if let Some(name) = (0..fields.field_len())
+                .filter_map(|idx| fields.name_at(idx))
+                .find(|name| info.field(name).is_none())
+            {
+                return Err(ScriptError::Other(format!(
+                    "Variant `{variant}` has no field `{name}`"
+                )));
+            }
+
+            let mut complete = DynamicStruct::default();
+            for field in info.iter() {
+                let value = match fields.field(field.name()) {
+                    Some(value) => value.clone_value(),
+                    None => default_field(&field.name(), field.type_id())?,
+                };
+                complete.insert_boxed(field.name(), value);
+            }
+            DynamicVariant::Struct(complete)
+        }
+        (VariantInfo::Tuple(info), DynamicVariant::Tuple(fields)) => {
+            if fields.field_len() > info.field_len() {
+                return Err(ScriptError::Other(format!(
+                    "Variant `{variant}` has {} fields, got {}",
+                    info.field_len(),
+                    fields.field_len()
+                )));
+            }
+
+            let mut complete = DynamicTuple::default();
+            for field in info.iter() {
+                let value = match fields.field(field.index()) {
+                    Some(value) => value.clone_value(),
+                    None => default_field(&field.index(), field.type_id())?,
+                };
+                complete.insert_boxed(value);
+            }
+            DynamicVariant::Tuple(complete)
+        }
+        _ => {
+            return Err(ScriptError::Other(format!(
+                "Fields given for variant `{variant}` of `{}` do not match its kind",
+                type_info.type_path()
+            )))
+        }
+    };
+
+    let mut dynamic = DynamicEnum::new(variant, value);
+    dynamic.set_represented_type(Some(type_info));
+    Ok(dynamic)
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        prelude::{Component, Events, ReflectComponent, ReflectDefault},
+        reflect::{FromReflect, Typed},
+    };
+    use bevy_mod_scripting_core::world::{
+        apply_script_commands, run_script_callback, WorldPointerGuard,
+    };
+
+    use super::*;
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Health(u32);
+
+    #[derive(Reflect, Default)]
+    struct NotAComponent;
+
+    fn setup_world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<NotAComponent>();
+        world.insert_resource(registry);
+        world
+    }
+
+    /// Runs the given closure with a script world pointing at the given world
+    fn with_script_world<O>(world: &mut World, f: impl FnOnce(&ScriptWorld) -> O) -> O {
+        // safety: the world is not used again until the guard is dropped at the end of this function
+        let guard = unsafe { WorldPointerGuard::new(world) };
+        f(&ScriptWorld::new(guard.clone()))
+    }
+
+    #[test]
+    fn deferred_insertions_are_applied_in_order() {
+        let mut world = setup_world();
+        world.insert_resource(ScriptCommands::deferred());
+
+        let entity = with_script_world(&mut world, |w| {
+            let entity = w.spawn();
+            let health = w.get_type_by_name("Health").unwrap();
+            let first = w.insert_component(entity, health.clone(), Box::new(Health(1)));
+            let second = w.insert_component(entity, health, Box::new(Health(2)));
+            // the component does not exist yet, so there is nothing to reference
+            assert!(first.unwrap().is_none());
+            assert!(second.unwrap().is_none());
+            entity
+        });
+        assert!(world.get_entity(entity).is_none());
+
+        apply_script_commands(&mut world);
+        assert_eq!(world.get::<Health>(entity), Some(&Health(2)));
+
+        with_script_world(&mut world, |w| {
+            let health = w.get_type_by_name("Health").unwrap();
+            w.add_default_component(entity, health).unwrap();
+            w.despawn(entity);
+        });
+        assert!(world.get_entity(entity).is_some());
+
+        apply_script_commands(&mut world);
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn deferred_insertions_are_validated_immediately() {
+        let mut world = setup_world();
+        world.insert_resource(ScriptCommands::deferred());
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+
+        with_script_world(&mut world, |w| {
+            let entity = w.spawn();
+            let not_a_component = w.get_type_by_name("NotAComponent").unwrap();
+            assert!(w.add_default_component(entity, not_a_component).is_err());
+
+            let health = w.get_type_by_name("Health").unwrap();
+            assert!(w.add_default_component(despawned, health).is_err());
+        });
+    }
+
+    #[test]
+    fn immediate_insertions_return_a_reference() {
+        let mut world = setup_world();
+        let entity = world.spawn_empty().id();
+
+        with_script_world(&mut world, |w| {
+            let health = w.get_type_by_name("Health").unwrap();
+            assert!(w
+                .insert_component(entity, health, Box::new(Health(3)))
+                .unwrap()
+                .is_some());
+        });
+        assert_eq!(world.get::<Health>(entity), Some(&Health(3)));
+    }
+
+    #[test]
+    fn dynamic_values_are_inserted_as_components() {
+        let mut world = setup_world();
+        let entity = world.spawn_empty().id();
+
+        let mut value = DynamicTupleStruct::default();
+        value.insert(5u32);
+        value.set_represented_type(Some(Health::type_info()));
+
+        with_script_world(&mut world, |w| {
+            let health = w.get_type_by_name("Health").unwrap();
+            w.insert_component(entity, health, Box::new(value)).unwrap();
+        });
+        assert_eq!(world.get::<Health>(entity), Some(&Health(5)));
+    }
+
+    #[test]
+    fn failed_spawns_leave_no_entity_behind() {
+        let mut world = setup_world();
+
+        with_script_world(&mut world, |w| {
+            let health = w.get_type_by_name("Health").unwrap();
+            let not_a_component = w.get_type_by_name("NotAComponent").unwrap();
+
+            // the first component is inserted before the second one fails
+            assert!(w
+                .spawn_with(vec![
+                    (health.clone(), Box::new(Health(1))),
+                    (health.clone(), Box::new(NotAComponent)),
+                ])
+                .is_err());
+            assert!(w
+                .spawn_with(vec![
+                    (health, Box::new(Health(1))),
+                    (not_a_component, Box::new(NotAComponent)),
+                ])
+                .is_err());
+        });
+        assert_eq!(world.entities().len(), 0);
+    }
+
+    /// Runs a script callback returning the entities with a `Health` component which pass the added and changed filters
+    fn filtered(world: &mut World, script_id: u32) -> (Vec<Entity>, Vec<Entity>) {
+        let component_id = world.init_component::<Health>();
+        with_script_world(world, |w| {
+            run_script_callback(w, script_id, || {
+                let w = w.read();
+                let mut added = query_component_id(&w, component_id, Some(ChangeFilter::Added));
+                let mut changed = query_component_id(&w, component_id, Some(ChangeFilter::Changed));
+                added.sort();
+                changed.sort();
+                (added, changed)
+            })
+        })
+    }
+
+    #[test]
+    fn everything_is_new_on_the_first_run() {
+        let mut world = setup_world();
+        let entity = world.spawn(Health(0)).id();
+
+        assert_eq!(filtered(&mut world, 0), (vec![entity], vec![entity]));
+    }
+
+    #[test]
+    fn filters_report_changes_since_the_previous_run() {
+        let mut world = setup_world();
+        let old = world.spawn(Health(0)).id();
+        filtered(&mut world, 0);
+
+        // systems running in between scripts advance the change tick
+        world.increment_change_tick();
+        let new = world.spawn(Health(1)).id();
+        world.get_mut::<Health>(old).unwrap().0 = 2;
+
+        assert_eq!(filtered(&mut world, 0), (vec![new], vec![old, new]));
+
+        world.increment_change_tick();
+        assert_eq!(filtered(&mut world, 0), (vec![], vec![]));
+
+        // other scripts track their own previous run
+        assert_eq!(filtered(&mut world, 1), (vec![old, new], vec![old, new]));
+    }
+
+    #[test]
+    fn changes_made_by_a_script_are_not_reported_to_it() {
+        let mut world = setup_world();
+        let entity = world.spawn(Health(0)).id();
+        with_script_world(&mut world, |w| {
+            run_script_callback(w, 0, || {
+                w.write().get_mut::<Health>(entity).unwrap().0 = 1;
+            })
+        });
+
+        world.increment_change_tick();
+        assert_eq!(filtered(&mut world, 0), (vec![], vec![]));
+    }
+
+    #[derive(Event, Reflect, Debug, PartialEq)]
+    struct Damage {
+        amount: u32,
+        source: Entity,
+    }
+
+    #[test]
+    fn sent_events_reach_their_readers() {
+        let mut app = App::new();
+        app.register_script_event::<Damage>();
+        app.register_type::<Health>();
+        let source = app.world.spawn_empty().id();
+
+        let mut event = DynamicStruct::default();
+        event.insert("amount", 3u32);
+        event.insert("source", source);
+
+        with_script_world(&mut app.world, |w| {
+            let damage = w.get_type_by_name("Damage").unwrap();
+            w.send_event(damage, &event).unwrap();
+
+            // only registered events can be sent
+            let health = w.get_type_by_name("Health").unwrap();
+            assert!(w.send_event(health, &Health(1)).is_err());
+
+            // the value must be convertible into the event type
+            let damage = w.get_type_by_name("Damage").unwrap();
+            assert!(w.send_event(damage, &Health(1)).is_err());
+        });
+
+        let events = app.world.resource::<Events<Damage>>();
+        let mut reader = events.get_reader();
+        let sent: Vec<_> = reader.read(events).collect();
+        assert_eq!(sent, vec![&Damage { amount: 3, source }]);
+    }
+
+    #[derive(Reflect, Debug, PartialEq)]
+    enum Shape {
+        Point,
+        Circle { radius: f32, filled: bool },
+        Rect(f32, f32),
+        Tagged(NoDefault),
+    }
+
+    #[derive(Reflect, Debug, PartialEq)]
+    struct NoDefault(u32);
+
+    fn shape(variant: &str, value: DynamicVariant) -> Result<Shape, ScriptError> {
+        let registry = TypeRegistry::default();
+        new_enum_variant(&registry, Shape::type_info(), variant, value)
+            .map(|dynamic| Shape::from_reflect(&dynamic).unwrap())
+    }
+
+    #[test]
+    fn missing_struct_variant_fields_are_defaulted() {
+        let mut fields = DynamicStruct::default();
+        fields.insert("radius", 2.0f32);
+
+        assert_eq!(
+            shape("Circle", DynamicVariant::Struct(fields)).unwrap(),
+            Shape::Circle {
+                radius: 2.0,
+                filled: false
+            }
+        );
+    }
+
+    #[test]
+    fn missing_tuple_variant_fields_are_defaulted() {
+        let mut fields = DynamicTuple::default();
+        fields.insert(1.5f32);
+
+        assert_eq!(
+            shape("Rect", DynamicVariant::Tuple(fields)).unwrap(),
+            Shape::Rect(1.5, 0.0)
+        );
+        assert_eq!(shape("Point", DynamicVariant::Unit).unwrap(), Shape::Point);
+    }
+
+    #[test]
+    fn invalid_variant_fields_are_rejected() {
+        let mut unknown = DynamicStruct::default();
+        unknown.insert("diameter", 2.0f32);
+        assert!(shape("Circle", DynamicVariant::Struct(unknown)).is_err());
+
+        let mut too_many = DynamicTuple::default();
+        too_many.insert(1.0f32);
+        too_many.insert(2.0f32);
+        too_many.insert(3.0f32);
+        assert!(shape("Rect", DynamicVariant::Tuple(too_many)).is_err());
+
+        // fields without a reflected default must be given
+        assert!(shape("Tagged", DynamicVariant::Tuple(DynamicTuple::default())).is_err());
+
+        assert!(shape("Point", DynamicVariant::Tuple(DynamicTuple::default())).is_err());
+        assert!(shape("Triangle", DynamicVariant::Unit).is_err());
+    }
+}
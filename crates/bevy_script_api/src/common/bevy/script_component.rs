@@ -0,0 +1,333 @@
+//! Component types declared by scripts at runtime
+use std::{alloc::Layout, collections::HashMap};
+
+use bevy::{
+    ecs::component::{ComponentDescriptor, ComponentId, StorageType},
+    prelude::{Entity, Resource, World},
+    ptr::OwningPtr,
+    reflect::{DynamicStruct, Reflect},
+};
+use bevy_mod_scripting_core::prelude::ScriptError;
+
+use crate::ReflectReference;
+
+use super::{
+    query_component_id, script_fields::ScriptFields, ChangeFilter, ScriptTypeRegistration,
+    ScriptWorld,
+};
+
+/// A component type declared by a script, see [`ScriptWorld::register_script_component`].
+///
+/// Script components are bevy components without a rust type, each instance holds a [`DynamicStruct`]
+/// with one field per declared field.
+#[derive(Clone, Debug)]
+pub struct ScriptComponentType {
+    name: String,
+    component_id: ComponentId,
+    fields: ScriptFields,
+}
+
+impl ScriptComponentType {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The id of the dynamic bevy component backing this type
+    pub fn component_id(&self) -> ComponentId {
+        self.component_id
+    }
+
+    /// The declared fields and their types
+    pub fn fields(&self) -> &ScriptFields {
+        &self.fields
+    }
+}
+
+/// The component types declared by scripts, by name
+#[derive(Resource, Default)]
+pub struct ScriptComponentTypes {
+    types: HashMap<String, ScriptComponentType>,
+}
+
+impl ScriptComponentTypes {
+    pub fn get(&self, name: &str) -> Option<&ScriptComponentType> {
+        self.types.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScriptComponentType> {
+        self.types.values()
+    }
+}
+
+/// # Safety
+/// The pointer must point to a valid `DynamicStruct`
+unsafe fn drop_dynamic_struct(ptr: OwningPtr<'_>) {
+    ptr.drop_as::<DynamicStruct>()
+}
+
+impl ScriptWorld {
+    /// Declares a new component type with the given fields, whose types must implement `Default` via reflection.
+    ///
+    /// Declaring an existing type again with the same fields returns the existing type.
+    pub fn register_script_component(
+        &self,
+        name: &str,
+        fields: Vec<(String, ScriptTypeRegistration)>,
+    ) -> Result<ScriptComponentType, ScriptError> {
+        let fields = ScriptFields::new(fields)?;
+
+        let mut w = self.write();
+        let existing = w
+            .get_resource::<ScriptComponentTypes>()
+            .and_then(|types| types.get(name));
+        if let Some(existing) = existing {
+            return if existing.fields == fields {
+                Ok(existing.clone())
+            } else {
+                Err(ScriptError::Other(format!(
+                    "Component `{name}` is already declared with different fields"
+                )))
+            };
+        }
+
+        // SAFETY: the drop function drops a `DynamicStruct` which matches the layout, and `DynamicStruct` is Send + Sync
+        let descriptor = unsafe {
+            ComponentDescriptor::new_with_layout(
+                name.to_owned(),
+                StorageType::Table,
+                Layout::new::<DynamicStruct>(),
+                Some(drop_dynamic_struct),
+            )
+        };
+        let component_type = ScriptComponentType {
+            name: name.to_owned(),
+            component_id: w.init_component_with_descriptor(descriptor),
+            fields,
+        };
+
+        w.get_resource_or_insert_with(ScriptComponentTypes::default)
+            .types
+            .insert(name.to_owned(), component_type.clone());
+        Ok(component_type)
+    }
+
+    /// Retrieves a component type declared by a script
+    pub fn get_script_component_type(&self, name: &str) -> Option<ScriptComponentType> {
+        let w = self.read();
+        w.get_resource::<ScriptComponentTypes>()
+            .and_then(|types| types.get(name))
+            .cloned()
+    }
+
+    /// Inserts a script component built from the given field values, see [`ScriptFields::new_value`], and returns a reference to it.
+    ///
+    /// In deferred mode the component does not exist until changes are applied, so `None` is returned instead
+    pub fn insert_script_component(
+        &self,
+        entity: Entity,
+        comp_type: &ScriptComponentType,
+        values: Vec<(String, Box<dyn Reflect>)>,
+    ) -> Result<Option<ReflectReference>, ScriptError> {
+        self.check_entity(entity)?;
+
+        let value = comp_type.fields.new_value(&comp_type.name, values)?;
+        let component_id = comp_type.component_id;
+        let inserted = self.insert_or_defer(entity, comp_type.name.clone(), move |w| {
+            insert_script_component(w, entity, component_id, value)
+        })?;
+
+        Ok(inserted.then(|| {
+            ReflectReference::new_script_component_ref(component_id, entity, self.clone().into())
+        }))
+    }
+
+    pub fn get_script_component(
+        &self,
+        entity: Entity,
+        comp_type: &ScriptComponentType,
+    ) -> Result<Option<ReflectReference>, ScriptError> {
+        Ok(self.has_script_component(entity, comp_type)?.then(|| {
+            ReflectReference::new_script_component_ref(
+                comp_type.component_id,
+                entity,
+                self.clone().into(),
+            )
+        }))
+    }
+
+    pub fn has_script_component(
+        &self,
+        entity: Entity,
+        comp_type: &ScriptComponentType,
+    ) -> Result<bool, ScriptError> {
+        let w = self.read();
+        let entity_ref = w
+            .get_entity(entity)
+            .ok_or_else(|| ScriptError::Other(format!("Entity is not valid {:#?}", entity)))?;
+
+        Ok(entity_ref.contains_id(comp_type.component_id))
+    }
+
+    /// Returns all entities which have a component of the given script component type,
+    /// optionally only those whose component was added or changed since the previous run of the current script
+    pub fn query_script_component(
+        &self,
+        comp_type: &ScriptComponentType,
+        filter: Option<ChangeFilter>,
+    ) -> Vec<Entity> {
+        query_component_id(&self.read(), comp_type.component_id, filter)
+    }
+}
+
+fn insert_script_component(
+    w: &mut World,
+    entity: Entity,
+    component_id: ComponentId,
+    value: DynamicStruct,
+) -> Result<(), ScriptError> {
+    let mut entity_ref = w
+        .get_entity_mut(entity)
+        .ok_or_else(|| ScriptError::Other(format!("Entity is not valid {:#?}", entity)))?;
+
+    OwningPtr::make(value, |ptr| {
+        // SAFETY: script components are declared with the layout of a `DynamicStruct`
+        unsafe { entity_ref.insert_by_id(component_id, ptr) };
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        borrow::Cow,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use bevy::prelude::{AppTypeRegistry, ReflectDefault};
+    use bevy_mod_scripting_core::world::WorldPointerGuard;
+
+    use crate::ValueIndex;
+
+    use super::*;
+
+    /// The number of [`Token`]s currently alive
+    static TOKENS: AtomicUsize = AtomicUsize::new(0);
+
+    /// A field value which counts its live instances, to check script components are dropped
+    #[derive(Reflect)]
+    #[reflect_value(Default)]
+    struct Token;
+
+    impl Default for Token {
+        fn default() -> Self {
+            TOKENS.fetch_add(1, Ordering::SeqCst);
+            Token
+        }
+    }
+
+    impl Clone for Token {
+        fn clone(&self) -> Self {
+            TOKENS.fetch_add(1, Ordering::SeqCst);
+            Token
+        }
+    }
+
+    impl Drop for Token {
+        fn drop(&mut self) {
+            TOKENS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn setup_world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<u32>();
+        registry.write().register::<Token>();
+        world.insert_resource(registry);
+        world
+    }
+
+    fn fields(w: &ScriptWorld, fields: &[(&str, &str)]) -> Vec<(String, ScriptTypeRegistration)> {
+        fields
+            .iter()
+            .map(|(name, type_name)| (name.to_string(), w.get_type_by_name(type_name).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn script_components_live_in_the_world() {
+        let mut world = setup_world();
+        let entity = world.spawn_empty().id();
+        let other = world.spawn_empty().id();
+
+        // safety: the world is not used again until the guard is dropped
+        let guard = unsafe { WorldPointerGuard::new(&mut world) };
+        let w = ScriptWorld::new(guard.clone());
+        let stats = w
+            .register_script_component("Stats", fields(&w, &[("level", "u32"), ("token", "Token")]))
+            .unwrap();
+
+        let mut level = w
+            .insert_script_component(entity, &stats, vec![("level".into(), Box::new(3u32))])
+            .unwrap()
+            .unwrap()
+            .index(Cow::Borrowed("level"));
+        assert_eq!(level.get_typed(|level: &u32| *level).unwrap(), 3);
+        level.get_mut_typed(|level: &mut u32| *level = 4).unwrap();
+
+        w.insert_script_component(other, &stats, Vec::default())
+            .unwrap();
+        assert_eq!(TOKENS.load(Ordering::SeqCst), 2);
+
+        let mut found = w.query_script_component(&stats, None);
+        found.sort();
+        assert_eq!(found, vec![entity, other]);
+        let level = w
+            .get_script_component(entity, &stats)
+            .unwrap()
+            .unwrap()
+            .index(Cow::Borrowed("level"));
+        assert_eq!(level.get_typed(|level: &u32| *level).unwrap(), 4);
+
+        // removed and despawned components are dropped,
+        // bevy cannot remove components by id yet so all components of the entity are removed
+        w.write().entity_mut(entity).retain::<()>();
+        assert!(!w.has_script_component(entity, &stats).unwrap());
+        assert!(level.get(|_| ()).is_err());
+        assert_eq!(TOKENS.load(Ordering::SeqCst), 1);
+
+        w.despawn(other);
+        assert_eq!(w.query_script_component(&stats, None), vec![]);
+        assert_eq!(TOKENS.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn script_components_are_declared_once() {
+        let mut world = setup_world();
+
+        // safety: the world is not used again until the guard is dropped
+        let guard = unsafe { WorldPointerGuard::new(&mut world) };
+        let w = ScriptWorld::new(guard.clone());
+        let health = w
+            .register_script_component("Health", fields(&w, &[("value", "u32")]))
+            .unwrap();
+
+        let again = w
+            .register_script_component("Health", fields(&w, &[("value", "u32")]))
+            .unwrap();
+        assert_eq!(again.component_id(), health.component_id());
+
+        assert!(w
+            .register_script_component("Health", fields(&w, &[("max", "u32")]))
+            .is_err());
+        assert!(w
+            .register_script_component("Health", fields(&w, &[("value", "Token")]))
+            .is_err());
+        assert_eq!(
+            w.get_script_component_type("Health")
+                .unwrap()
+                .component_id(),
+            health.component_id()
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Field layouts of the types declared by scripts at runtime
+use std::sync::Arc;
+
+use bevy::{
+    prelude::ReflectDefault,
+    reflect::{DynamicStruct, Reflect},
+};
+use bevy_mod_scripting_core::prelude::ScriptError;
+
+use super::ScriptTypeRegistration;
+
+/// The fields of a struct declared by a script, sorted by name.
+///
+/// Values of such structs are [`DynamicStruct`]s holding a value of the declared type for each field.
+#[derive(Clone, Debug)]
+pub struct ScriptFields(Arc<[(String, ScriptTypeRegistration)]>);
+
+impl ScriptFields {
+    /// Creates a new field layout, all field types must implement `Default` via reflection
+    pub fn new(mut fields: Vec<(String, ScriptTypeRegistration)>) -> Result<Self, ScriptError> {
+        if let Some((field, field_type)) = fields
+            .iter()
+            .find(|(_, field_type)| field_type.data::<ReflectDefault>().is_none())
+        {
+            return Err(ScriptError::Other(format!(
+                "Type `{}` of field `{field}` does not reflect `Default`",
+                field_type.short_name()
+            )));
+        }
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(Self(fields.into()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, ScriptTypeRegistration)> {
+        self.0.iter()
+    }
+
+    /// Returns the declared type of the given field
+    pub fn field_type(&self, field: &str) -> Option<&ScriptTypeRegistration> {
+        self.0
+            .iter()
+            .find_map(|(name, field_type)| (name == field).then_some(field_type))
+    }
+
+    /// Builds a value from the given field values, fields which are not given are set to the default value of their type.
+    ///
+    /// The owner is the name of the declared type and is only used in error messages.
+    pub fn new_value(
+        &self,
+        owner: &str,
+        mut values: Vec<(String, Box<dyn Reflect>)>,
+    ) -> Result<DynamicStruct, ScriptError> {
+        if let Some((name, _)) = values
+            .iter()
+            .find(|(name, _)| self.field_type(name).is_none())
+        {
+            return Err(ScriptError::Other(format!(
+                "`{owner}` has no field `{name}`"
+            )));
+        }
+
+        let mut value = DynamicStruct::default();
+        for (name, field_type) in self.0.iter() {
+            let field_value = match values.iter().position(|(n, _)| n == name) {
+                Some(idx) => {
+                    let (_, field_value) = values.swap_remove(idx);
+                    if field_value
+                        .get_represented_type_info()
+                        .is_none_or(|info| info.type_id() != field_type.type_id())
+                    {
+                        return Err(ScriptError::Other(format!(
+                            "Expected a value of type `{}` for field `{name}` of `{owner}`, got `{}`",
+                            field_type.type_name(),
+                            field_value.reflect_type_path()
+                        )));
+                    }
+                    field_value
+                }
+                None => field_type
+                    .data::<ReflectDefault>()
+                    .expect("Checked on creation")
+                    .default(),
+            };
+            value.insert_boxed(name, field_value);
+        }
+        Ok(value)
+    }
+
+    /// Builds a value with every field set to the default value of its type
+    pub fn default_value(&self) -> DynamicStruct {
+        self.new_value("", Vec::default())
+            .expect("Default values always match the layout")
+    }
+}
+
+impl PartialEq for ScriptFields {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .all(|((name_a, type_a), (name_b, type_b))| {
+                    name_a == name_b && type_a.type_id() == type_b.type_id()
+                })
+    }
+}
@@ -0,0 +1,427 @@
+//! Resources declared by scripts at runtime
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use bevy::{
+    asset::ron,
+    log::warn,
+    prelude::{
+        App, AppTypeRegistry, DetectChanges, DetectChangesMut, Last, Plugin, ReflectDefault,
+        ReflectResource, Res, ResMut, Resource,
+    },
+    reflect::{
+        serde::{ReflectSerializer, UntypedReflectDeserializer},
+        DynamicStruct, FromReflect, Reflect, ReflectFromReflect, Struct, TypeRegistry,
+    },
+};
+use bevy_mod_scripting_core::prelude::ScriptError;
+use serde::{
+    de::{DeserializeSeed, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserializer, Serialize, Serializer,
+};
+
+use crate::ReflectReference;
+
+use super::{script_fields::ScriptFields, ScriptTypeRegistration, ScriptWorld};
+
+/// A resource declared by a script, see [`ScriptWorld::register_script_resource`]
+#[derive(Debug)]
+pub struct ScriptResource {
+    fields: ScriptFields,
+    value: DynamicStruct,
+}
+
+impl ScriptResource {
+    /// The declared fields and their types
+    pub fn fields(&self) -> &ScriptFields {
+        &self.fields
+    }
+
+    pub fn value(&self) -> &DynamicStruct {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut DynamicStruct {
+        &mut self.value
+    }
+}
+
+/// The resources declared by scripts, by name.
+///
+/// Rust code can access them via [`ScriptResources::get_as`] and [`ScriptResources::field`].
+///
+/// The types of script resources are only known at runtime, so bevy cannot reflect them directly.
+/// Instead scenes save the reflected `saved` field, which holds the resources as serialized by [`ScriptResourcesSerializer`]
+/// and is kept in sync with them by [`ScriptResourcesPlugin`].
+#[derive(Resource, Reflect, Default, Debug)]
+#[reflect(Resource, Default)]
+pub struct ScriptResources {
+    #[reflect(ignore)]
+    resources: HashMap<String, ScriptResource>,
+    saved: String,
+    /// The value of `saved` after the last sync, if they differ `saved` was replaced by a scene
+    #[reflect(ignore)]
+    synced: String,
+}
+
+impl ScriptResources {
+    pub fn get(&self, name: &str) -> Option<&ScriptResource> {
+        self.resources.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ScriptResource> {
+        self.resources.get_mut(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.resources.contains_key(name)
+    }
+
+    /// Inserts a resource with the given fields set to their default values, unless one with the same name already exists.
+    ///
+    /// Fails if the existing resource was declared with different fields.
+    pub fn insert(&mut self, name: &str, fields: ScriptFields) -> Result<(), ScriptError> {
+        match self.resources.get(name) {
+            Some(existing) if existing.fields != fields => Err(ScriptError::Other(format!(
+                "Resource `{name}` is already declared with different fields"
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                let value = fields.default_value();
+                self.resources
+                    .insert(name.to_owned(), ScriptResource { fields, value });
+                Ok(())
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ScriptResource> {
+        self.resources.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ScriptResource)> {
+        self.resources
+            .iter()
+            .map(|(name, resource)| (name.as_str(), resource))
+    }
+
+    /// Builds a rust value from the resource with the given name via `FromReflect`,
+    /// returns `None` if the resource does not exist or its fields do not match the type
+    pub fn get_as<T: FromReflect>(&self, name: &str) -> Option<T> {
+        T::from_reflect(self.get(name)?.value())
+    }
+
+    /// Retrieves a field of the resource with the given name,
+    /// returns `None` if the resource or field does not exist or if the field is not a `T`
+    pub fn field<T: Reflect>(&self, name: &str, field: &str) -> Option<&T> {
+        self.get(name)?.value().field(field)?.downcast_ref()
+    }
+
+    /// Mutable version of [`Self::field`]
+    pub fn field_mut<T: Reflect>(&mut self, name: &str, field: &str) -> Option<&mut T> {
+        self.get_mut(name)?
+            .value_mut()
+            .field_mut(field)?
+            .downcast_mut()
+    }
+}
+
+/// Saves and restores [`ScriptResources`] alongside the rest of the world in scenes
+pub struct ScriptResourcesPlugin;
+
+impl Plugin for ScriptResourcesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ScriptResources>()
+            .add_systems(Last, sync_script_resources);
+    }
+}
+
+/// Serializes changed script resources into their saved form,
+/// or restores them from their saved form if a scene replaced it
+pub fn sync_script_resources(
+    resources: Option<ResMut<ScriptResources>>,
+    registry: Res<AppTypeRegistry>,
+) {
+    let Some(mut resources) = resources.filter(|resources| resources.is_changed()) else {
+        return;
+    };
+    let registry = registry.read();
+    let resources = resources.bypass_change_detection();
+
+    if resources.saved != resources.synced {
+        let loaded = ron::Options::default().from_str_seed(
+            &resources.saved,
+            ScriptResourcesDeserializer {
+                registry: &registry,
+            },
+        );
+        match loaded {
+            Ok(loaded) => resources.resources = loaded.resources,
+            Err(e) => warn!("Could not restore script resources: {e}"),
+        }
+    } else {
+        let saved = ron::to_string(&ScriptResourcesSerializer {
+            resources,
+            registry: &registry,
+        });
+        match saved {
+            Ok(saved) => resources.saved = saved,
+            Err(e) => warn!("Could not save script resources: {e}"),
+        }
+    }
+    resources.synced = resources.saved.clone();
+}
+
+impl ScriptWorld {
+    /// Declares a resource with the given fields, whose types must implement `Default` via reflection.
+    /// The resource starts out with every field set to its default value.
+    ///
+    /// Declaring an existing resource again with the same fields keeps its current value.
+    pub fn register_script_resource(
+        &self,
+        name: &str,
+        fields: Vec<(String, ScriptTypeRegistration)>,
+    ) -> Result<(), ScriptError> {
+        let fields = ScriptFields::new(fields)?;
+        let mut w = self.write();
+        w.get_resource_or_insert_with(ScriptResources::default)
+            .insert(name, fields)
+    }
+
+    /// Retrieves a reference to a resource declared by a script
+    pub fn get_script_resource(&self, name: &str) -> Option<ReflectReference> {
+        self.has_script_resource(name).then(|| {
+            ReflectReference::new_script_resource_ref(name.to_owned(), self.clone().into())
+        })
+    }
+
+    pub fn has_script_resource(&self, name: &str) -> bool {
+        let w = self.read();
+        w.get_resource::<ScriptResources>()
+            .is_some_and(|resources| resources.contains(name))
+    }
+
+    /// Removes a resource declared by a script, returns true if it existed
+    pub fn remove_script_resource(&self, name: &str) -> bool {
+        let mut w = self.write();
+        w.get_resource_mut::<ScriptResources>()
+            .and_then(|mut resources| resources.remove(name))
+            .is_some()
+    }
+}
+
+/// Serializes [`ScriptResources`] as a map from resource names to maps of their field values,
+/// each field value is serialized along with its type path like in bevy scenes
+pub struct ScriptResourcesSerializer<'a> {
+    pub resources: &'a ScriptResources,
+    pub registry: &'a TypeRegistry,
+}
+
+impl Serialize for ScriptResourcesSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut resources = self.resources.iter().collect::<Vec<_>>();
+        resources.sort_by_key(|(name, _)| *name);
+
+        let mut map = serializer.serialize_map(Some(resources.len()))?;
+        for (name, resource) in resources {
+            map.serialize_entry(
+                name,
+                &ScriptResourceSerializer {
+                    resource,
+                    registry: self.registry,
+                },
+            )?;
+        }
+        map.end()
+    }
+}
+
+struct ScriptResourceSerializer<'a> {
+    resource: &'a ScriptResource,
+    registry: &'a TypeRegistry,
+}
+
+impl Serialize for ScriptResourceSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = &self.resource.value;
+        let mut map = serializer.serialize_map(Some(value.field_len()))?;
+        for (idx, field) in value.iter_fields().enumerate() {
+            map.serialize_entry(
+                value.name_at(idx).expect("Field exists"),
+                &ReflectSerializer::new(field, self.registry),
+            )?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes [`ScriptResources`] serialized by [`ScriptResourcesSerializer`],
+/// the field layouts of the resources are restored from the types of their field values
+pub struct ScriptResourcesDeserializer<'a> {
+    pub registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for ScriptResourcesDeserializer<'_> {
+    type Value = ScriptResources;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScriptResourcesDeserializer<'_> {
+    type Value = ScriptResources;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of script resources")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut resources = ScriptResources::default();
+        while let Some(name) = map.next_key::<String>()? {
+            let resource = map.next_value_seed(ScriptResourceDeserializer {
+                name: &name,
+                registry: self.registry,
+            })?;
+            resources.resources.insert(name, resource);
+        }
+        Ok(resources)
+    }
+}
+
+struct ScriptResourceDeserializer<'a> {
+    name: &'a str,
+    registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for ScriptResourceDeserializer<'_> {
+    type Value = ScriptResource;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScriptResourceDeserializer<'_> {
+    type Value = ScriptResource;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of field values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        use serde::de::Error;
+
+        let mut fields = Vec::default();
+        let mut values = Vec::default();
+        while let Some(field) = map.next_key::<String>()? {
+            let value = map.next_value_seed(UntypedReflectDeserializer::new(self.registry))?;
+            let registration = value
+                .get_represented_type_info()
+                .and_then(|info| self.registry.get(info.type_id()))
+                .ok_or_else(|| {
+                    A::Error::custom(format!(
+                        "Type of field `{field}` of `{}` is not registered",
+                        self.name
+                    ))
+                })?;
+            let value = registration
+                .data::<ReflectFromReflect>()
+                .and_then(|from_reflect| from_reflect.from_reflect(value.as_ref()))
+                .unwrap_or(value);
+
+            fields.push((
+                field.clone(),
+                ScriptTypeRegistration::new(Arc::new(registration.clone())),
+            ));
+            values.push((field, value));
+        }
+
+        let fields = ScriptFields::new(fields).map_err(A::Error::custom)?;
+        let value = fields
+            .new_value(self.name, values)
+            .map_err(A::Error::custom)?;
+        Ok(ScriptResource { fields, value })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        ecs::{entity::EntityHashMap, system::RunSystemOnce},
+        prelude::World,
+        scene::{serde::SceneDeserializer, DynamicSceneBuilder},
+    };
+    use bevy_mod_scripting_core::world::WorldPointerGuard;
+
+    use super::*;
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    struct Stats {
+        level: u32,
+        name: String,
+    }
+
+    fn setup_world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<ScriptResources>();
+        registry.write().register::<u32>();
+        registry.write().register::<String>();
+        world.insert_resource(registry);
+        world
+    }
+
+    #[test]
+    fn script_resources_round_trip_through_scenes() {
+        let mut world = setup_world();
+        {
+            // safety: the world is not used again until the guard is dropped
+            let guard = unsafe { WorldPointerGuard::new(&mut world) };
+            let w = ScriptWorld::new(guard.clone());
+            let fields = ["level", "name"]
+                .into_iter()
+                .zip(["u32", "String"])
+                .map(|(name, type_name)| (name.to_owned(), w.get_type_by_name(type_name).unwrap()))
+                .collect();
+            w.register_script_resource("Stats", fields).unwrap();
+        }
+        let mut resources = world.resource_mut::<ScriptResources>();
+        *resources.field_mut::<u32>("Stats", "level").unwrap() = 7;
+        *resources.field_mut::<String>("Stats", "name").unwrap() = "hero".to_owned();
+        world.run_system_once(sync_script_resources);
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let scene = DynamicSceneBuilder::from_world(&world)
+            .extract_resources()
+            .build()
+            .serialize_ron(&registry)
+            .unwrap();
+
+        let mut loaded = setup_world();
+        let scene = ron::Options::default()
+            .from_str_seed(
+                &scene,
+                SceneDeserializer {
+                    type_registry: &registry.read(),
+                },
+            )
+            .unwrap();
+        scene
+            .write_to_world(&mut loaded, &mut EntityHashMap::default())
+            .unwrap();
+        loaded.run_system_once(sync_script_resources);
+
+        let resources = loaded.resource::<ScriptResources>();
+        assert_eq!(resources.field::<u32>("Stats", "level"), Some(&7));
+        assert_eq!(
+            resources.get_as::<Stats>("Stats"),
+            Some(Stats {
+                level: 7,
+                name: "hero".to_owned()
+            })
+        );
+        assert_eq!(resources.get_as::<Stats>("Missing"), None);
+        assert_eq!(resources.field::<String>("Stats", "level"), None);
+    }
+}
@@ -0,0 +1,325 @@
+//! Conversion of script values into reflected values following the reflect info of the target type
+use std::any::TypeId;
+
+use bevy::{
+    prelude::{AppTypeRegistry, Entity, ReflectDefault},
+    reflect::{
+        DynamicArray, DynamicList, DynamicMap, DynamicStruct, DynamicTuple, DynamicTupleStruct,
+        DynamicVariant, Map, Reflect, TypeInfo, TypeRegistration, VariantInfo,
+    },
+};
+use bevy_mod_scripting_core::world::WorldPointer;
+
+use crate::{error::ReflectionError, ReflectReference};
+
+use super::{enum_variant_info, new_enum_variant, type_registration};
+
+/// Access to the values of a scripting language, used to convert them into reflected values,
+/// see [`dynamic_from_script`]
+pub trait ScriptValueAccess {
+    type Value;
+    type Error: From<ReflectionError>;
+
+    /// Wraps the given message in a runtime error of the language
+    fn error(&self, msg: String) -> Self::Error;
+
+    /// Returns the name of the type of the given value, used in error messages
+    fn type_name<'v>(&self, value: &'v Self::Value) -> &'v str;
+
+    /// Returns the unit value of the language, i.e. `nil` in lua
+    fn unit(&self) -> Self::Value;
+
+    fn is_unit(&self, value: &Self::Value) -> bool;
+
+    /// Returns true if values of the given type are converted by a proxy of the language instead of their reflect info
+    fn has_proxy(&self, registration: &TypeRegistration) -> bool;
+
+    /// Returns the reference held by the given value if it is a reflected value
+    fn reflected_value(&self, value: &Self::Value)
+        -> Result<Option<ReflectReference>, Self::Error>;
+
+    /// Returns the entity represented by the given value if it is one
+    fn entity(&self, value: &Self::Value) -> Result<Option<Entity>, Self::Error>;
+
+    /// Returns the given value as a string if it is one
+    fn string(&self, value: &Self::Value) -> Result<Option<String>, Self::Error>;
+
+    /// Returns true if the value holds key value pairs, see [`Self::entries`]
+    fn is_map(&self, value: &Self::Value) -> bool;
+
+    /// Returns true if the value holds a sequence of items, see [`Self::items`]
+    fn is_sequence(&self, value: &Self::Value) -> bool;
+
+    /// Returns the key value pairs of a value for which [`Self::is_map`] holds
+    fn entries(&self, value: Self::Value) -> Result<Vec<(Self::Value, Self::Value)>, Self::Error>;
+
+    /// Returns the items of a value for which [`Self::is_sequence`] holds
+    fn items(&self, value: Self::Value) -> Result<Vec<Self::Value>, Self::Error>;
+
+    /// Assigns the given value to the referenced value via the proxies of the language
+    fn apply(&self, ref_: &mut ReflectReference, value: Self::Value) -> Result<(), Self::Error>;
+}
+
+/// Returns the given map key as the name of a field or variant
+fn name<A: ScriptValueAccess>(access: &A, key: &A::Value) -> Result<String, A::Error> {
+    access.string(key)?.ok_or_else(|| {
+        access.error(format!(
+            "Expected a field or variant name, got a {}",
+            access.type_name(key)
+        ))
+    })
+}
+
+/// Builds a dynamic value of the given enum type with the given variant.
+///
+/// Struct variants take a map of field values, tuple variants a sequence of field values or the value of their only field.
+/// Missing fields are set to the default values of their types.
+pub fn dynamic_variant_from_script<A: ScriptValueAccess>(
+    access: &A,
+    world_ptr: &WorldPointer,
+    registration: &TypeRegistration,
+    variant: &str,
+    fields: A::Value,
+) -> Result<Box<dyn Reflect>, A::Error> {
+    let type_info = registration.type_info();
+    let variant_info =
+        enum_variant_info(type_info, variant).map_err(|e| access.error(e.to_string()))?;
+
+    let value = match variant_info {
+        VariantInfo::Unit(_) if access.is_unit(&fields) => DynamicVariant::Unit,
+        // all fields are set to their defaults
+        VariantInfo::Struct(_) if access.is_unit(&fields) => {
+            DynamicVariant::Struct(Default::default())
+        }
+        VariantInfo::Tuple(_) if access.is_unit(&fields) => {
+            DynamicVariant::Tuple(Default::default())
+        }
+        VariantInfo::Struct(info) if access.is_map(&fields) => {
+            let mut dynamic = DynamicStruct::default();
+            for (name, value) in access.entries(fields)? {
+                let name = self::name(access, &name)?;
+                let field = info.field(&name).ok_or_else(|| {
+                    access.error(format!("Variant `{variant}` has no field `{name}`"))
+                })?;
+                dynamic.insert_boxed(
+                    &name,
+                    dynamic_from_script(access, world_ptr, field.type_id(), value)?,
+                );
+            }
+            DynamicVariant::Struct(dynamic)
+        }
+        VariantInfo::Tuple(info) if info.field_len() == 1 => {
+            let mut dynamic = DynamicTuple::default();
+            dynamic.insert_boxed(dynamic_from_script(
+                access,
+                world_ptr,
+                info.field_at(0).expect("Variant has one field").type_id(),
+                fields,
+            )?);
+            DynamicVariant::Tuple(dynamic)
+        }
+        VariantInfo::Tuple(info) if access.is_sequence(&fields) => {
+            let mut dynamic = DynamicTuple::default();
+            for (i, value) in access.items(fields)?.into_iter().enumerate() {
+                let field = info
+                    .field_at(i)
+                    .ok_or_else(|| access.error(format!("Variant `{variant}` has no field {i}")))?;
+                dynamic.insert_boxed(dynamic_from_script(
+                    access,
+                    world_ptr,
+                    field.type_id(),
+                    value,
+                )?);
+            }
+            DynamicVariant::Tuple(dynamic)
+        }
+        _ => {
+            return Err(access.error(format!(
+                "Cannot construct variant `{variant}` of `{}` from a {}",
+                type_info.type_path(),
+                access.type_name(&fields)
+            )))
+        }
+    };
+
+    let world = world_ptr.read();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    new_enum_variant(&registry, type_info, variant, value)
+        .map(|value| Box::new(value) as Box<dyn Reflect>)
+        .map_err(|e| access.error(e.to_string()))
+}
+
+/// Builds a dynamic value of the given reflected type from a script value.
+///
+/// Maps and sequences are converted field by field (or item by item) following the type's reflect info,
+/// missing fields are taken from the type's `Default` implementation if it has one.
+/// Enums without a proxy are built from the name of a variant,
+/// or a map holding the variant's fields under its name, see [`dynamic_variant_from_script`].
+/// Any other value is assigned to a default instance of the type via [`ScriptValueAccess::apply`].
+pub fn dynamic_from_script<A: ScriptValueAccess>(
+    access: &A,
+    world_ptr: &WorldPointer,
+    type_id: TypeId,
+    value: A::Value,
+) -> Result<Box<dyn Reflect>, A::Error> {
+    let registration =
+        type_registration(world_ptr, type_id).map_err(|e| access.error(e.to_string()))?;
+    let type_info = registration.type_info();
+    let type_path = type_info.type_path();
+
+    if let Some(ref_) = access.reflected_value(&value)? {
+        let value = ref_.get(|s| s.clone_value())?;
+        return match value.get_represented_type_info() {
+            Some(info) if info.type_id() == type_id => Ok(value),
+            _ => Err(access.error(format!(
+                "Expected a value of type `{type_path}`, got `{}`",
+                value.reflect_type_path()
+            ))),
+        };
+    } else if type_id == TypeId::of::<Entity>() {
+        if let Some(entity) = access.entity(&value)? {
+            return Ok(Box::new(entity));
+        }
+    }
+
+    // enums with a proxy (such as `Option`) are converted by the proxy instead
+    if let TypeInfo::Enum(_) = type_info {
+        if !access.has_proxy(&registration) {
+            if let Some(variant) = access.string(&value)? {
+                return dynamic_variant_from_script(
+                    access,
+                    world_ptr,
+                    &registration,
+                    &variant,
+                    access.unit(),
+                );
+            } else if access.is_map(&value) {
+                let mut entries = access.entries(value)?.into_iter();
+                return match (entries.next(), entries.next()) {
+                    (Some((variant, fields)), None) => dynamic_variant_from_script(
+                        access,
+                        world_ptr,
+                        &registration,
+                        &name(access, &variant)?,
+                        fields,
+                    ),
+                    _ => Err(access.error(format!(
+                        "Expected a map holding a single variant of `{type_path}`"
+                    ))),
+                };
+            }
+        }
+    }
+
+    let dynamic: Box<dyn Reflect> = match type_info {
+        TypeInfo::Struct(info) if access.is_map(&value) => {
+            let mut dynamic = DynamicStruct::default();
+            for (name, value) in access.entries(value)? {
+                let name = self::name(access, &name)?;
+                let field = info
+                    .field(&name)
+                    .ok_or_else(|| access.error(format!("`{type_path}` has no field `{name}`")))?;
+                dynamic.insert_boxed(
+                    &name,
+                    dynamic_from_script(access, world_ptr, field.type_id(), value)?,
+                );
+            }
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        TypeInfo::TupleStruct(info) if access.is_sequence(&value) => {
+            let mut dynamic = DynamicTupleStruct::default();
+            for (i, value) in access.items(value)?.into_iter().enumerate() {
+                let field = info
+                    .field_at(i)
+                    .ok_or_else(|| access.error(format!("`{type_path}` has no field {i}")))?;
+                dynamic.insert_boxed(dynamic_from_script(
+                    access,
+                    world_ptr,
+                    field.type_id(),
+                    value,
+                )?);
+            }
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        TypeInfo::Tuple(info) if access.is_sequence(&value) => {
+            let mut dynamic = DynamicTuple::default();
+            for (i, value) in access.items(value)?.into_iter().enumerate() {
+                let field = info
+                    .field_at(i)
+                    .ok_or_else(|| access.error(format!("`{type_path}` has no field {i}")))?;
+                dynamic.insert_boxed(dynamic_from_script(
+                    access,
+                    world_ptr,
+                    field.type_id(),
+                    value,
+                )?);
+            }
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        TypeInfo::List(info) if access.is_sequence(&value) => {
+            let mut dynamic = DynamicList::default();
+            for value in access.items(value)? {
+                dynamic.push_box(dynamic_from_script(
+                    access,
+                    world_ptr,
+                    info.item_type_id(),
+                    value,
+                )?);
+            }
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        TypeInfo::Array(info) if access.is_sequence(&value) => {
+            let items = access.items(value)?;
+            if items.len() != info.capacity() {
+                return Err(access.error(format!(
+                    "Cannot construct `{type_path}` from {} items, expected {}",
+                    items.len(),
+                    info.capacity()
+                )));
+            }
+            let items = items
+                .into_iter()
+                .map(|value| dynamic_from_script(access, world_ptr, info.item_type_id(), value))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut dynamic = DynamicArray::new(items.into_boxed_slice());
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        TypeInfo::Map(info) if access.is_map(&value) => {
+            let mut dynamic = DynamicMap::default();
+            for (key, value) in access.entries(value)? {
+                dynamic.insert_boxed(
+                    dynamic_from_script(access, world_ptr, info.key_type_id(), key)?,
+                    dynamic_from_script(access, world_ptr, info.value_type_id(), value)?,
+                );
+            }
+            dynamic.set_represented_type(Some(type_info));
+            Box::new(dynamic)
+        }
+        _ => {
+            let default = registration.data::<ReflectDefault>().ok_or_else(|| {
+                access.error(format!(
+                    "Cannot construct `{type_path}` from a {}, the type does not reflect `Default`",
+                    access.type_name(&value)
+                ))
+            })?;
+            let mut ref_ = ReflectReference::new_owned_ref(default.default(), world_ptr.clone());
+            access.apply(&mut ref_, value)?;
+            return Ok(ref_.get(|s| s.clone_value())?);
+        }
+    };
+
+    // fill in missing fields with defaults
+    match registration.data::<ReflectDefault>() {
+        Some(default) => {
+            let mut value = default.default();
+            value.apply(dynamic.as_ref());
+            Ok(value)
+        }
+        None => Ok(dynamic),
+    }
+}
@@ -1,6 +1,6 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 
-use bevy::reflect::{FromReflect, TypePath};
+use bevy::reflect::{FromReflect, Map, Reflect, ReflectMut, ReflectRef, TypePath};
 
 use crate::{error::ReflectionError, ReflectReference, ValueIndex};
 
@@ -137,3 +137,149 @@ impl<T: FromReflect + TypePath> IntoIterator for ScriptVec<T> {
         }
     }
 }
+
+/// A reference to a rust map, keys and values are accessed through reflection so this works with any map type
+pub struct ScriptMap<K, V> {
+    pub(crate) ref_: ReflectReference,
+    _ph: PhantomData<(K, V)>,
+}
+
+impl<K, V> Clone for ScriptMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            ref_: self.ref_.clone(),
+            _ph: PhantomData,
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for ScriptMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptMap")
+            .field("ref_", &self.ref_)
+            .finish()
+    }
+}
+
+fn as_map(s: &dyn Reflect) -> Result<&dyn Map, ReflectionError> {
+    match s.reflect_ref() {
+        ReflectRef::Map(m) => Ok(m),
+        _ => Err(ReflectionError::CannotDowncast {
+            from: s.reflect_type_path().to_owned().into(),
+            to: "Map".into(),
+        }),
+    }
+}
+
+fn as_map_mut(s: &mut dyn Reflect) -> Result<&mut dyn Map, ReflectionError> {
+    let from = s.reflect_type_path().to_owned();
+    match s.reflect_mut() {
+        ReflectMut::Map(m) => Ok(m),
+        _ => Err(ReflectionError::CannotDowncast {
+            from: from.into(),
+            to: "Map".into(),
+        }),
+    }
+}
+
+fn from_reflect<T: FromReflect + TypePath>(value: &dyn Reflect) -> Result<T, ReflectionError> {
+    T::from_reflect(value).ok_or_else(|| ReflectionError::CannotDowncast {
+        from: value.reflect_type_path().to_owned().into(),
+        to: T::type_path().into(),
+    })
+}
+
+impl<K: FromReflect + TypePath + Clone, V: FromReflect + TypePath> ScriptMap<K, V> {
+    pub fn new_ref(ref_: ReflectReference) -> Self {
+        Self {
+            ref_,
+            _ph: PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool, ReflectionError> {
+        Ok(self.len()? == 0)
+    }
+
+    pub fn len(&self) -> Result<usize, ReflectionError> {
+        self.ref_.get(|s| as_map(s).map(|m| m.len()))?
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool, ReflectionError> {
+        self.ref_.get(|s| as_map(s).map(|m| m.get(key).is_some()))?
+    }
+
+    /// Returns a reference to the value under the given key, if there is one
+    pub fn get(&self, key: K) -> Result<Option<ReflectReference>, ReflectionError> {
+        Ok(self
+            .contains_key(&key)?
+            .then(|| self.ref_.index(Arc::new(key) as Arc<dyn Reflect>)))
+    }
+
+    /// Inserts a value under the given key, returning the value previously stored there
+    pub fn insert(&mut self, key: K, val: V) -> Result<Option<V>, ReflectionError> {
+        self.ref_.get_mut(|s| {
+            as_map_mut(s)?
+                .insert_boxed(Box::new(key), Box::new(val))
+                .map(|old| from_reflect(old.as_ref()))
+                .transpose()
+        })?
+    }
+
+    /// Removes the value under the given key, returning it if there was one
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, ReflectionError> {
+        self.ref_.get_mut(|s| {
+            as_map_mut(s)?
+                .remove(key)
+                .map(|old| from_reflect(old.as_ref()))
+                .transpose()
+        })?
+    }
+
+    /// Returns all keys currently in the map
+    pub fn keys(&self) -> Result<Vec<K>, ReflectionError> {
+        self.ref_.get(|s| {
+            as_map(s)?
+                .iter()
+                .map(|(key, _)| from_reflect(key))
+                .collect()
+        })?
+    }
+
+    /// Returns an iterator over the keys currently in the map and references to their values,
+    /// changes made to the map while iterating are not reflected in the keys returned
+    pub fn iter(&self) -> Result<ScriptMapIterator<K, V>, ReflectionError> {
+        Ok(ScriptMapIterator {
+            keys: self.keys()?.into_iter(),
+            base: self.clone(),
+        })
+    }
+}
+
+impl<K: Reflect, V> ValueIndex<K> for ScriptMap<K, V> {
+    type Output = ReflectReference;
+
+    fn index(&self, key: K) -> Self::Output {
+        self.ref_.index(Arc::new(key) as Arc<dyn Reflect>)
+    }
+}
+
+impl<K, V> From<ScriptMap<K, V>> for ReflectReference {
+    fn from(m: ScriptMap<K, V>) -> Self {
+        m.ref_
+    }
+}
+
+pub struct ScriptMapIterator<K, V> {
+    keys: std::vec::IntoIter<K>,
+    base: ScriptMap<K, V>,
+}
+
+impl<K: Reflect + Clone, V> Iterator for ScriptMapIterator<K, V> {
+    type Item = (K, ReflectReference);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        Some((key.clone(), self.base.index(key)))
+    }
+}
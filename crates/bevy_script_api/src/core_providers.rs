@@ -1,4 +1,4 @@
-use crate::lua::RegisterForeignLuaType;
+use crate::{common::bevy::script_resource::ScriptResourcesPlugin, lua::RegisterForeignLuaType};
 
 pub struct LuaCoreBevyAPIProvider;
 
@@ -110,5 +110,11 @@ impl bevy_mod_scripting_core::hosts::APIProvider for LuaCoreBevyAPIProvider {
         app.register_foreign_lua_type::<i8>();
         app.register_foreign_lua_type::<String>();
         app.register_foreign_lua_type::<bool>();
+        app.insert_resource(bevy_mod_scripting_lua::LuaReflectConverter(
+            crate::lua::reflect_to_lua,
+        ));
+        if !app.is_plugin_added::<ScriptResourcesPlugin>() {
+            app.add_plugins(ScriptResourcesPlugin);
+        }
     }
 }
@@ -9,6 +9,10 @@ pub enum ReflectionError {
     InsufficientProvenance { path: String, msg: String },
     #[error("Invalid reflection path: `{path}`. {msg}")]
     InvalidReflectionPath { path: String, msg: String },
+    #[error("Base reference `{base}` is stale, the value it pointed to has been dropped")]
+    StaleReference { base: String },
+    #[error("Cannot access `{path}` while it is already borrowed mutably")]
+    BorrowConflict { path: String },
     #[error("Cannot downcast from `{from}` to `{to}`")]
     CannotDowncast {
         from: Cow<'static, str>,
@@ -24,7 +24,10 @@ pub mod prelude {
     #[cfg(feature = "lua")]
     pub use crate::{
         core_providers::LuaCoreBevyAPIProvider,
-        lua::{std::LuaVec, FromLuaProxy, IntoLuaProxy, LuaProxyable, ReflectLuaProxyable},
+        lua::{
+            std::{LuaMap, LuaVec},
+            FromLuaProxy, IntoLuaProxy, LuaProxyable, ReflectLuaProxyable,
+        },
         providers::LuaBevyAPIProvider,
         LuaProxy,
     };
@@ -32,11 +35,17 @@ pub mod prelude {
     #[cfg(feature = "rhai")]
     pub use crate::rhai::{
         bevy::RhaiBevyAPIProvider,
-        std::{RhaiCopy, RhaiVec},
+        std::{RhaiCopy, RhaiMap, RhaiVec},
         FromRhaiProxy, ReflectRhaiProxyable, RhaiProxyable, ToRhaiProxy,
     };
 
-    pub use crate::{common::bevy::GetWorld, ValueIndex};
+    pub use crate::{
+        common::bevy::{
+            script_resource::{ScriptResources, ScriptResourcesPlugin},
+            GetWorld, ReflectScriptSendable, RegisterScriptEvent,
+        },
+        ValueIndex,
+    };
 }
 
 #[cfg(feature = "lua")]
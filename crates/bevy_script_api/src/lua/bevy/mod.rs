@@ -1,10 +1,11 @@
-use crate::common::bevy::{ScriptTypeRegistration, ScriptWorld};
+use crate::common::bevy::{
+    script_component::ScriptComponentType, ChangeFilter, ScriptTypeRegistration, ScriptWorld,
+};
 use crate::providers::bevy_ecs::LuaEntity;
-use crate::{impl_from_lua_with_clone, impl_tealr_type};
+use crate::{impl_from_lua_with_clone, impl_tealr_type, ReflectReference};
 
 use std::sync::Arc;
 
-use bevy::hierarchy::BuildWorldChildren;
 use bevy::prelude::AppTypeRegistry;
 
 use bevy::prelude::ReflectResource;
@@ -12,11 +13,11 @@ use bevy_mod_scripting_core::prelude::*;
 use bevy_mod_scripting_lua::tealr;
 
 use tealr::mlu::{
-    mlua::{self},
+    mlua::{self, IntoLua},
     TealData, TealDataMethods,
 };
 
-use super::util::LuaIndex;
+use super::{reflect_from_lua, util::LuaIndex, variant_from_lua};
 
 pub type LuaTypeRegistration = ScriptTypeRegistration;
 impl_tealr_type!(LuaTypeRegistration);
@@ -96,8 +97,28 @@ impl TealData for LuaWorld {
                 .map(|registration| LuaTypeRegistration::new(Arc::new(registration.clone()))))
         });
 
+        methods.document("Constructs a value of the given enum type with the given variant.");
+        methods.document("Struct variants take a table of field values, tuple variants a list of field values or the value of their only field.");
+        methods.document("Missing fields are set to the default values of their types.");
+        methods.add_method(
+            "new_variant",
+            |ctx,
+             world,
+             (enum_type, variant, fields): (LuaTypeRegistration, String, mlua::Value)| {
+                let value = variant_from_lua(
+                    ctx,
+                    world.clone().into(),
+                    enum_type.type_id(),
+                    &variant,
+                    fields,
+                )?;
+                ReflectReference::new_owned_ref(value, world.clone().into()).into_lua(ctx)
+            },
+        );
+
         methods.document("Inserts a component of the given type to the given entity by instantiating a default version of it.");
         methods.document("The component can then be modified using field access.");
+        methods.document("Returns `nil` in deferred mode, since the component is only inserted once changes are applied.");
         methods.add_method(
             "add_default_component",
             |_, world, (entity, comp_type): (LuaEntity, LuaTypeRegistration)| {
@@ -107,14 +128,45 @@ impl TealData for LuaWorld {
             },
         );
 
+        methods.document("Inserts a component of the given type to the given entity, built from the given value.");
+        methods.document("The value can be a table with the component's fields or a value of the component type itself, missing fields are filled in with defaults if the type has any.");
+        methods
+            .document("Returns a reference to the inserted component, or `nil` in deferred mode.");
+        methods.add_method(
+            "insert_component",
+            |ctx, world, (entity, comp_type, value): (LuaEntity, LuaTypeRegistration, mlua::Value)| {
+                let value = reflect_from_lua(ctx, world.clone().into(), comp_type.type_id(), value)?;
+                world
+                    .insert_component(entity.inner()?, comp_type, value)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
         methods.document("Retrieves a component of the given type from the given entity.");
         methods.document("If such a component does not exist returns `nil`.");
+        methods.document("Optionally takes a filter, either `\"added\"` or `\"changed\"`, in which case `nil` is also returned if the component was not added or changed since the previous run of this script.");
         methods.add_method(
             "get_component",
-            |_, world, (entity, comp_type): (LuaEntity, LuaTypeRegistration)| {
-                world
-                    .get_component(entity.inner()?, comp_type)
-                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            |_, world, (entity, comp_type, filter): (LuaEntity, LuaTypeRegistration, Option<String>)| {
+                match change_filter(filter)? {
+                    Some(filter) => world.get_component_filtered(entity.inner()?, comp_type, filter),
+                    None => world.get_component(entity.inner()?, comp_type),
+                }
+                .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
+        methods.document("Returns all entities which contain a component of the given type.");
+        methods.document("Optionally takes a filter, either `\"added\"` or `\"changed\"`, to only return entities whose component was added or changed since the previous run of this script.");
+        methods.add_method(
+            "query_component",
+            |_, world, (comp_type, filter): (LuaTypeRegistration, Option<String>)| {
+                Ok(world
+                    .query_component(comp_type, change_filter(filter)?)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))?
+                    .into_iter()
+                    .map(LuaEntity::new)
+                    .collect::<Vec<_>>())
             },
         );
 
@@ -139,32 +191,146 @@ impl TealData for LuaWorld {
             },
         );
 
-        methods.document("Retrieves a resource of the given type from the world.");
-        methods.document("If such a resource does not exist returns `nil`.");
-        methods.add_method("get_resource", |_, world, res_type: LuaTypeRegistration| {
-            world
-                .get_resource(res_type)
-                .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
-        });
+        methods.document("Declares a new component type from a table of field names to the names of their types, for example `{ hp = \"i64\" }`.");
+        methods.document("Field types must be registered rust types which implement `Default`, declaring the same component twice with the same fields does nothing.");
+        methods.add_method(
+            "declare_component",
+            |_, world, (name, fields): (String, mlua::Table)| {
+                world
+                    .register_script_component(&name, script_fields(world, fields)?)
+                    .map(|_| ())
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
 
         methods.document(
-            "Removes the given resource from the world, if one doesn't exist it does nothing.",
+            "Inserts a component of a type declared via `declare_component` to the given entity.",
         );
+        methods.document("The component is built from a table of field values, missing fields are set to their defaults.");
+        methods
+            .document("Returns a reference to the inserted component, or `nil` in deferred mode.");
         methods.add_method(
-            "remove_resource",
-            |_, world, res_type: LuaTypeRegistration| {
-                let mut w = world.write();
+            "add_script_component",
+            |ctx, world, (entity, name, values): (LuaEntity, String, Option<mlua::Table>)| {
+                let comp_type = script_component_type(world, &name)?;
+                let values = values
+                    .map(|values| {
+                        values
+                            .pairs::<String, mlua::Value>()
+                            .map(|pair| {
+                                let (field, value) = pair?;
+                                let field_type =
+                                    comp_type.fields().field_type(&field).ok_or_else(|| {
+                                        mlua::Error::RuntimeError(format!(
+                                            "Component `{name}` has no field `{field}`"
+                                        ))
+                                    })?;
+                                let value = reflect_from_lua(
+                                    ctx,
+                                    world.clone().into(),
+                                    field_type.type_id(),
+                                    value,
+                                )?;
+                                Ok((field, value))
+                            })
+                            .collect::<mlua::Result<Vec<_>>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default();
 
-                let resource_data = res_type.data::<ReflectResource>().ok_or_else(|| {
-                    mlua::Error::RuntimeError(format!("Not a resource {}", res_type.short_name()))
-                })?;
-                resource_data.remove(&mut w);
-                Ok(())
+                world
+                    .insert_script_component(entity.inner()?, &comp_type, values)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
             },
         );
 
-        methods.document("Returns `true` if the world contains a resource of the given type.");
-        methods.add_method("has_resource", |_, world, res_type: LuaTypeRegistration| {
+        methods.document("Retrieves a component of a type declared via `declare_component` from the given entity.");
+        methods.document("If such a component does not exist returns `nil`.");
+        methods.add_method(
+            "get_script_component",
+            |_, world, (entity, name): (LuaEntity, String)| {
+                let comp_type = script_component_type(world, &name)?;
+                world
+                    .get_script_component(entity.inner()?, &comp_type)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
+        methods.document("Returns `true` if the given entity contains a component of a type declared via `declare_component`.");
+        methods.add_method(
+            "has_script_component",
+            |_, world, (entity, name): (LuaEntity, String)| {
+                let comp_type = script_component_type(world, &name)?;
+                world
+                    .has_script_component(entity.inner()?, &comp_type)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
+        methods.document("Returns all entities which contain a component of a type declared via `declare_component`.");
+        methods.document("Takes the same optional filter as `query_component`.");
+        methods.add_method(
+            "query_script_component",
+            |_, world, (name, filter): (String, Option<String>)| {
+                let comp_type = script_component_type(world, &name)?;
+                Ok(world
+                    .query_script_component(&comp_type, change_filter(filter)?)
+                    .into_iter()
+                    .map(LuaEntity::new)
+                    .collect::<Vec<_>>())
+            },
+        );
+
+        methods.document("Declares a new resource from a table of field names to the names of their types, for example `{ points = \"i64\" }`.");
+        methods.document("The resource starts out with default field values, declaring the same resource twice with the same fields keeps its current value.");
+        methods.add_method(
+            "declare_resource",
+            |_, world, (name, fields): (String, mlua::Table)| {
+                world
+                    .register_script_resource(&name, script_fields(world, fields)?)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
+        methods.document("Retrieves a resource of the given type from the world, resources declared via `declare_resource` are retrieved by their name.");
+        methods.document("If such a resource does not exist returns `nil`.");
+        methods.add_method(
+            "get_resource",
+            |ctx, world, res_type: mlua::Value| match res_type {
+                mlua::Value::String(name) => Ok(world.get_script_resource(name.to_str()?)),
+                res_type => world
+                    .get_resource(ctx.unpack(res_type)?)
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string())),
+            },
+        );
+
+        methods.document(
+            "Removes the given resource from the world, if one doesn't exist it does nothing.",
+        );
+        methods.document("Resources declared via `declare_resource` are removed by their name.");
+        methods.add_method("remove_resource", |ctx, world, res_type: mlua::Value| {
+            let res_type: LuaTypeRegistration = match res_type {
+                mlua::Value::String(name) => {
+                    world.remove_script_resource(name.to_str()?);
+                    return Ok(());
+                }
+                res_type => ctx.unpack(res_type)?,
+            };
+            let mut w = world.write();
+
+            let resource_data = res_type.data::<ReflectResource>().ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("Not a resource {}", res_type.short_name()))
+            })?;
+            resource_data.remove(&mut w);
+            Ok(())
+        });
+
+        methods.document("Returns `true` if the world contains a resource of the given type, or declared via `declare_resource` with the given name.");
+        methods.add_method("has_resource", |ctx, world, res_type: mlua::Value| {
+            let res_type: LuaTypeRegistration = match res_type {
+                mlua::Value::String(name) => return Ok(world.has_script_resource(name.to_str()?)),
+                res_type => ctx.unpack(res_type)?,
+            };
             let w = world.read();
 
             let resource_data = res_type.data::<ReflectResource>().ok_or_else(|| {
@@ -174,6 +340,23 @@ impl TealData for LuaWorld {
             Ok(resource_data.reflect(&w).is_some())
         });
 
+        methods.document("Sends an event of the given type (given by its short or fully qualified name), built from the given value.");
+        methods.document("Tables are converted field by field, the event type must be registered as script sendable on the rust side.");
+        methods.add_method(
+            "send_event",
+            |ctx, world, (event_type, event): (String, mlua::Value)| {
+                let event_type = world.get_type_by_name(&event_type).ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("No type named `{event_type}` is registered"))
+                })?;
+                let event =
+                    reflect_from_lua(ctx, world.clone().into(), event_type.type_id(), event)?;
+
+                world
+                    .send_event(event_type, event.as_ref())
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+            },
+        );
+
         methods.document("Retrieves children entities of the parent entity if it has any.");
         methods.add_method("get_children", |_, world, parent: LuaEntity| {
             Ok(world
@@ -192,16 +375,12 @@ impl TealData for LuaWorld {
         methods.add_method(
             "push_children",
             |_, world, (parent, children): (LuaEntity, Vec<LuaEntity>)| {
-                let mut w = world.write();
                 let children = children
                     .iter()
                     .map(|e| e.inner())
                     .collect::<Result<Vec<_>, _>>()?;
 
-                if let Some(mut entity) = w.get_entity_mut(parent.inner()?) {
-                    entity.push_children(&children);
-                }
-
+                world.push_children(parent.inner()?, &children);
                 Ok(())
             },
         );
@@ -278,19 +457,68 @@ impl TealData for LuaWorld {
         });
 
         methods.document("Spawns a new entity and returns its Entity ID");
-        methods.add_method("spawn", |_, world, ()| {
-            let mut w = world.write();
+        methods.add_method("spawn", |_, world, ()| Ok(LuaEntity::new(world.spawn())));
+
+        methods
+            .document("Spawns a new entity with the given components and returns its Entity ID.");
+        methods.document("The components are given as a table from type names to component values, as accepted by `insert_component`.");
+        methods.add_method("spawn_with", |ctx, world, components: mlua::Table| {
+            let components = components
+                .pairs::<String, mlua::Value>()
+                .map(|pair| {
+                    let (type_name, value) = pair?;
+                    let comp_type = world.get_type_by_name(&type_name).ok_or_else(|| {
+                        mlua::Error::RuntimeError(format!(
+                            "No type named `{type_name}` is registered"
+                        ))
+                    })?;
+                    let value =
+                        reflect_from_lua(ctx, world.clone().into(), comp_type.type_id(), value)?;
+                    Ok((comp_type, value))
+                })
+                .collect::<mlua::Result<Vec<_>>>()?;
 
-            Ok(LuaEntity::new(w.spawn(()).id()))
+            world
+                .spawn_with(components)
+                .map(LuaEntity::new)
+                .map_err(|e| mlua::Error::RuntimeError(e.to_string()))
         });
 
         methods.document(
             "Despawns the given entity if it exists, returns true if deletion was successfull",
         );
         methods.add_method("despawn", |_, world, entity: LuaEntity| {
-            let mut w = world.write();
-
-            Ok(w.despawn(entity.inner()?))
+            Ok(world.despawn(entity.inner()?))
         });
     }
 }
+
+fn script_component_type(world: &LuaWorld, name: &str) -> mlua::Result<ScriptComponentType> {
+    world.get_script_component_type(name).ok_or_else(|| {
+        mlua::Error::RuntimeError(format!("No component named `{name}` was declared"))
+    })
+}
+
+/// Resolves a table of field names to type names into declared fields
+fn script_fields(
+    world: &LuaWorld,
+    fields: mlua::Table,
+) -> mlua::Result<Vec<(String, ScriptTypeRegistration)>> {
+    fields
+        .pairs::<String, String>()
+        .map(|pair| {
+            let (field, type_name) = pair?;
+            let field_type = world.get_type_by_name(&type_name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("No type named `{type_name}` is registered"))
+            })?;
+            Ok((field, field_type))
+        })
+        .collect()
+}
+
+fn change_filter(filter: Option<String>) -> Result<Option<ChangeFilter>, mlua::Error> {
+    filter
+        .map(|filter| filter.parse())
+        .transpose()
+        .map_err(|e: ScriptError| mlua::Error::RuntimeError(e.to_string()))
+}
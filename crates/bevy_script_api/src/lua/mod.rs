@@ -1,18 +1,23 @@
 use ::std::any::TypeId;
 use ::std::borrow::Cow;
 
-use crate::common::bevy::GetWorld;
+use crate::common::bevy::{
+    from_dynamic,
+    script_value::{dynamic_from_script, dynamic_variant_from_script, ScriptValueAccess},
+    type_registration, GetWorld,
+};
+use crate::providers::bevy_ecs::LuaEntity;
 use crate::{impl_from_lua_with_clone, impl_tealr_type};
-use ::bevy::prelude::{App, AppTypeRegistry};
+use ::bevy::prelude::{App, AppTypeRegistry, Entity};
 
-use ::bevy::reflect::{FromType, GetTypeRegistration, Reflect};
+use ::bevy::reflect::{FromType, GetTypeRegistration, Reflect, TypeRegistration};
 
 use bevy_mod_scripting_core::world::WorldPointer;
 use bevy_mod_scripting_lua::tealr::{self, ToTypename};
 
 use tealr::mlu::mlua::MetaMethod;
 use tealr::mlu::{
-    mlua::{self, FromLua, IntoLua, Lua, UserData, Value},
+    mlua::{self, FromLua, IntoLua, Lua, Table, UserData, Value},
     TealData, TealDataMethods,
 };
 
@@ -88,7 +93,8 @@ impl ValueIndex<Value<'_>> for ReflectReference {
 /// Mainly necessary for separation of concerns on the [`ReflectReference`] type, but might have other uses potentially.
 ///
 /// This is not the same as [`LuaProxyable`], internally this in fact will use [`LuaProxyable`] so treating it like so will cause inifnite loops.
-pub(crate) trait ApplyLua {
+#[doc(hidden)]
+pub trait ApplyLua {
     /// set the proxied object with the given lua value
     fn apply_lua<'lua>(&mut self, ctx: &'lua Lua, v: Value<'lua>) -> mlua::Result<()>;
 }
@@ -147,6 +153,129 @@ impl<'lua> IntoLua<'lua> for ReflectReference {
     }
 }
 
+/// Converts a copy of the given reflected value into the most convenient lua representation, see [`ReflectReference::into_lua`].
+///
+/// Used to pass the arguments of reflected script events to lua scripts
+pub fn reflect_to_lua<'lua>(
+    lua: &'lua Lua,
+    world_ptr: WorldPointer,
+    value: &dyn Reflect,
+) -> mlua::Result<Value<'lua>> {
+    ReflectReference::new_owned_copy(value, world_ptr).into_lua(lua)
+}
+
+/// Builds a value of the given reflected type from a lua value.
+///
+/// Tables are converted field by field (or item by item) into dynamic values following the type's reflect info,
+/// missing fields are taken from the type's `Default` implementation if it has one.
+/// Enums without a [`ReflectLuaProxyable`] registration are built from the name of a variant,
+/// or a table holding the variant's fields under its name, see [`variant_from_lua`].
+/// Any other value is assigned to a default instance of the type via its [`ReflectLuaProxyable`] registration,
+/// the result is converted into the concrete type via `ReflectFromReflect` where possible.
+pub fn reflect_from_lua<'lua>(
+    lua: &'lua Lua,
+    world_ptr: WorldPointer,
+    type_id: TypeId,
+    value: Value<'lua>,
+) -> mlua::Result<Box<dyn Reflect>> {
+    let value = dynamic_from_script(&LuaValues(lua), &world_ptr, type_id, value)?;
+    Ok(from_dynamic(&world_ptr, type_id, value))
+}
+
+/// Builds a value of the given enum type with the given variant.
+///
+/// Struct variants take a table of field values, tuple variants a list of field values or the value of their only field.
+/// Missing fields are set to the default values of their types.
+pub fn variant_from_lua<'lua>(
+    lua: &'lua Lua,
+    world_ptr: WorldPointer,
+    type_id: TypeId,
+    variant: &str,
+    fields: Value<'lua>,
+) -> mlua::Result<Box<dyn Reflect>> {
+    let registration = type_registration(&world_ptr, type_id)
+        .map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+    let value =
+        dynamic_variant_from_script(&LuaValues(lua), &world_ptr, &registration, variant, fields)?;
+    Ok(from_dynamic(&world_ptr, type_id, value))
+}
+
+/// Gives the conversions in [`crate::common::bevy::script_value`] access to lua values
+struct LuaValues<'lua>(&'lua Lua);
+
+impl<'lua> ScriptValueAccess for LuaValues<'lua> {
+    type Value = Value<'lua>;
+    type Error = mlua::Error;
+
+    fn error(&self, msg: String) -> Self::Error {
+        mlua::Error::RuntimeError(msg)
+    }
+
+    fn type_name<'v>(&self, value: &'v Self::Value) -> &'v str {
+        value.type_name()
+    }
+
+    fn unit(&self) -> Self::Value {
+        Value::Nil
+    }
+
+    fn is_unit(&self, value: &Self::Value) -> bool {
+        value.is_nil()
+    }
+
+    fn has_proxy(&self, registration: &TypeRegistration) -> bool {
+        registration.data::<ReflectLuaProxyable>().is_some()
+    }
+
+    fn reflected_value(
+        &self,
+        value: &Self::Value,
+    ) -> Result<Option<ReflectReference>, Self::Error> {
+        match value {
+            Value::UserData(ud) if ud.is::<ReflectedValue>() => {
+                Ok(Some(ud.borrow::<ReflectedValue>()?.ref_.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn entity(&self, value: &Self::Value) -> Result<Option<Entity>, Self::Error> {
+        match value {
+            Value::UserData(ud) if ud.is::<LuaEntity>() => {
+                Ok(Some(ud.borrow::<LuaEntity>()?.inner()?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn string(&self, value: &Self::Value) -> Result<Option<String>, Self::Error> {
+        match value {
+            Value::String(s) => Ok(Some(s.to_str()?.to_owned())),
+            _ => Ok(None),
+        }
+    }
+
+    fn is_map(&self, value: &Self::Value) -> bool {
+        value.is_table()
+    }
+
+    fn is_sequence(&self, value: &Self::Value) -> bool {
+        value.is_table()
+    }
+
+    fn entries(&self, value: Self::Value) -> Result<Vec<(Self::Value, Self::Value)>, Self::Error> {
+        Table::from_lua(value, self.0)?.pairs().collect()
+    }
+
+    fn items(&self, value: Self::Value) -> Result<Vec<Self::Value>, Self::Error> {
+        Table::from_lua(value, self.0)?.sequence_values().collect()
+    }
+
+    fn apply(&self, ref_: &mut ReflectReference, value: Self::Value) -> Result<(), Self::Error> {
+        ref_.apply_lua(self.0, value)
+    }
+}
+
 impl ToTypename for ReflectReference {
     fn to_typename() -> tealr::Type {
         tealr::Type::new_single("ReflectedValue", tealr::KindOfType::External)
@@ -164,6 +293,19 @@ impl TealData for ReflectedValue {
             val.ref_.get(|s| Ok(format!("{:?}", &s)))?
         });
 
+        methods.document("Returns the name of the active variant if this value is an enum.");
+        methods.add_method("variant_name", |_, val, ()| Ok(val.ref_.variant_name()?));
+
+        methods.document("Returns the value at the given reflection path relative to this value, e.g. `translation.x` or `items[2].name`.");
+        methods.add_method("get_path", |_, val, path: String| {
+            Ok(val.ref_.sub_path(&path)?)
+        });
+
+        methods.document("Sets the value at the given reflection path relative to this value.");
+        methods.add_method_mut("set_path", |ctx, val, (path, new_val): (String, Value)| {
+            val.ref_.sub_path(&path)?.apply_lua(ctx, new_val)
+        });
+
         methods.add_meta_method_mut(MetaMethod::Index, |_, val, field: Value| {
             let r = val.ref_.index(field)?;
             Ok(r)
@@ -1,5 +1,9 @@
+use std::collections::HashMap as StdHashMap;
+use std::hash::{BuildHasher, Hash};
 use std::sync::Arc;
 
+use bevy::utils::hashbrown::HashMap as BevyHashMap;
+
 use bevy::reflect::FromReflect;
 use bevy::reflect::Reflect;
 
@@ -17,7 +21,7 @@ use tealr::TypeBody;
 
 use paste::paste;
 
-use crate::common::std::ScriptVec;
+use crate::common::std::{ScriptMap, ScriptVec};
 use crate::{
     error::ReflectionError,
     script_ref::{ReflectReference, ValueIndex},
@@ -472,3 +476,320 @@ impl<'lua, T: for<'a> IntoLuaProxy<'a> + Clone + FromReflect + LuaProxyable> Int
         proxies.into_lua(lua)
     }
 }
+
+/// Composite trait composing the various traits required for a type `K` to be used as the key of a LuaMap<K, V>
+pub trait LuaMapKey:
+    FromReflect + TypePath + Clone + Eq + Hash + for<'a> FromLuaProxy<'a> + for<'a> IntoLuaProxy<'a>
+{
+}
+impl<
+        K: FromReflect
+            + TypePath
+            + Clone
+            + Eq
+            + Hash
+            + for<'a> FromLuaProxy<'a>
+            + for<'a> IntoLuaProxy<'a>,
+    > LuaMapKey for K
+{
+}
+
+/// Composite trait composing the various traits required for a type `V` to be used as the value of a LuaMap<K, V>
+pub trait LuaMapValue:
+    FromReflect + TypePath + Clone + LuaProxyable + for<'a> FromLuaProxy<'a> + for<'a> IntoLuaProxy<'a>
+{
+}
+impl<
+        V: FromReflect
+            + TypePath
+            + Clone
+            + LuaProxyable
+            + for<'a> FromLuaProxy<'a>
+            + for<'a> IntoLuaProxy<'a>,
+    > LuaMapValue for V
+{
+}
+
+/// A reference to a rust map (map reference proxy), works with both `std` and `bevy` hash maps
+pub type LuaMap<K, V> = ScriptMap<K, V>;
+
+impl<K: LuaMapKey, V: LuaMapValue> UserData for LuaMap<K, V> {
+    fn add_methods<'lua, M: tealr::mlu::mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        let mut x = tealr::mlu::UserDataWrapper::from_user_data_methods(methods);
+        <Self as tealr::mlu::TealData>::add_methods(&mut x);
+    }
+    fn add_fields<'lua, F: tealr::mlu::mlua::UserDataFields<'lua, Self>>(fields: &mut F) {
+        let mut wrapper = tealr::mlu::UserDataWrapper::from_user_data_fields(fields);
+        <Self as tealr::mlu::TealData>::add_fields(&mut wrapper)
+    }
+}
+
+impl<K, V> ToTypename for LuaMap<K, V> {
+    fn to_typename() -> tealr::Type {
+        tealr::Type::new_single("LuaMap", tealr::KindOfType::External)
+    }
+}
+
+impl<K: LuaMapKey, V: LuaMapValue> TypeBody for LuaMap<K, V> {
+    fn get_type_body() -> tealr::TypeGenerator {
+        let mut gen = tealr::RecordGenerator::new::<Self>(false);
+        gen.is_user_data = true;
+        <Self as TealData>::add_fields(&mut gen);
+        <Self as TealData>::add_methods(&mut gen);
+        gen.into()
+    }
+}
+
+impl<K: LuaMapKey, V: LuaMapValue> TealData for LuaMap<K, V> {
+    fn add_methods<'lua, M: TealDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.document_type("A reference to a HashMap<K, V> Rust type.");
+        methods.document_type("Indexing with a missing key returns `nil`.");
+
+        methods.add_meta_method(MetaMethod::ToString, |_, s, ()| {
+            Ok(s.ref_.get(|s| format!("{:?}", s))?)
+        });
+
+        methods.add_meta_method(MetaMethod::Index, |ctx, s, key: Value| {
+            Ok(s.get(K::from_lua_proxy(key, ctx)?)?)
+        });
+
+        methods.add_meta_method_mut(
+            MetaMethod::NewIndex,
+            |ctx, s, (key, value): (Value, Value)| {
+                s.insert(K::from_lua_proxy(key, ctx)?, V::from_lua_proxy(value, ctx)?)?;
+                Ok(())
+            },
+        );
+
+        bevy_mod_scripting_lua::__cfg_feature_any_lua52_lua53_lua54_luajit52!(
+            methods.add_meta_method(
+                MetaMethod::Pairs,
+                |ctx, s, _: ()| {
+                    let mut entries = s.iter()?;
+                    TypedFunction::from_rust_mut(
+                        move |ctx, ()| {
+                            Ok(match entries.next() {
+                                Some((key, value)) => {
+                                    (key.to_lua_proxy(ctx)?, value.into_lua(ctx)?)
+                                }
+                                None => (Value::Nil, Value::Nil),
+                            })
+                        },
+                        ctx,
+                    )
+                },
+            );
+        );
+        methods.add_meta_method(MetaMethod::Len, |_, s, ()| Ok(s.len()?));
+
+        methods.add_method("len", |_, s, ()| Ok(s.len()?));
+
+        methods.add_method("contains_key", |ctx, s, key: Value| {
+            Ok(s.contains_key(&K::from_lua_proxy(key, ctx)?)?)
+        });
+
+        methods.add_method("keys", |ctx, s, ()| {
+            s.keys()?
+                .into_iter()
+                .map(|key| key.to_lua_proxy(ctx))
+                .collect::<mlua::Result<Vec<_>>>()
+        });
+
+        methods.add_method("to_table", |ctx, s, ()| {
+            let table = ctx.create_table()?;
+            for (key, value) in s.iter()? {
+                table.raw_set(key.to_lua_proxy(ctx)?, value.into_lua(ctx)?)?;
+            }
+            Ok(table)
+        });
+
+        methods.add_method_mut("insert", |ctx, s, (key, value): (Value, Value)| {
+            s.insert(K::from_lua_proxy(key, ctx)?, V::from_lua_proxy(value, ctx)?)?
+                .to_lua_proxy(ctx)
+        });
+
+        methods.add_method_mut("remove", |ctx, s, key: Value| {
+            s.remove(&K::from_lua_proxy(key, ctx)?)?.to_lua_proxy(ctx)
+        });
+    }
+}
+
+/// Implements the lua proxy traits for a hash map type, making it available to scripts as a [`LuaMap`]
+macro_rules! impl_lua_proxy_for_map {
+    ($map_ty:ident) => {
+        impl<K: LuaMapKey, V: LuaMapValue, S: TypePath + BuildHasher + Default + Send + Sync>
+            LuaProxyable for $map_ty<K, V, S>
+        {
+            fn ref_to_lua(self_: ReflectReference, lua: &Lua) -> mlua::Result<Value> {
+                LuaMap::<K, V>::new_ref(self_).into_lua(lua)
+            }
+
+            fn apply_lua<'lua>(
+                self_: &mut ReflectReference,
+                lua: &'lua Lua,
+                new_val: Value<'lua>,
+            ) -> mlua::Result<()> {
+                match &new_val {
+                    Value::UserData(ud) => {
+                        let lua_map = ud.borrow::<LuaMap<K, V>>()?;
+                        self_.apply(&lua_map.ref_)?;
+                    }
+                    Value::Table(table) => {
+                        // entries of the table are inserted into the map, overwriting existing ones
+                        let mut map = LuaMap::<K, V>::new_ref(self_.clone());
+                        for entry in table.clone().pairs::<Value, Value>() {
+                            let (key, value) = entry?;
+                            map.insert(
+                                K::from_lua_proxy(key, lua)?,
+                                V::from_lua_proxy(value, lua)?,
+                            )?;
+                        }
+                    }
+                    _ => {
+                        return Err(mlua::Error::FromLuaConversionError {
+                            from: new_val.type_name(),
+                            to: "userdata or table",
+                            message: Some(
+                                "LuaMap can only be assigned with itself or a table".to_owned(),
+                            ),
+                        })
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        impl<
+                'lua,
+                K: LuaMapKey,
+                V: LuaMapValue,
+                S: TypePath + BuildHasher + Default + Send + Sync,
+            > FromLuaProxy<'lua> for $map_ty<K, V, S>
+        {
+            fn from_lua_proxy(new_val: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+                match new_val {
+                    Value::UserData(ud) => {
+                        let lua_map = ud.borrow::<LuaMap<K, V>>()?;
+                        lua_map
+                            .iter()?
+                            .map(|(key, value)| Ok((key, value.get_typed(|v: &V| v.clone())?)))
+                            .collect()
+                    }
+                    Value::Table(table) => table
+                        .pairs::<Value, Value>()
+                        .map(|entry| {
+                            let (key, value) = entry?;
+                            Ok((K::from_lua_proxy(key, lua)?, V::from_lua_proxy(value, lua)?))
+                        })
+                        .collect(),
+                    _ => Err(mlua::Error::FromLuaConversionError {
+                        from: new_val.type_name(),
+                        to: "userdata or table",
+                        message: Some(
+                            "LuaMap can only be assigned with itself or a table".to_owned(),
+                        ),
+                    }),
+                }
+            }
+        }
+
+        impl<'lua, K: LuaMapKey, V: LuaMapValue, S> IntoLuaProxy<'lua> for $map_ty<K, V, S> {
+            fn to_lua_proxy(self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+                let proxies = lua.create_table()?;
+                for (key, value) in self.into_iter() {
+                    proxies.raw_set(key.to_lua_proxy(lua)?, value.to_lua_proxy(lua)?)?;
+                }
+
+                proxies.into_lua(lua)
+            }
+        }
+    };
+}
+
+impl_lua_proxy_for_map!(StdHashMap);
+impl_lua_proxy_for_map!(BevyHashMap);
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use bevy::{
+        prelude::{App, Component, Entity, ReflectComponent},
+        reflect::FromType,
+    };
+    use bevy_mod_scripting_core::{
+        hosts::APIProvider,
+        world::{WorldPointer, WorldPointerGuard},
+    };
+
+    use crate::{core_providers::LuaCoreBevyAPIProvider, lua::RegisterForeignLuaType};
+
+    use super::*;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Inventory {
+        items: StdHashMap<String, u32>,
+    }
+
+    fn items_ref(world_ptr: WorldPointer, entity: Entity) -> ReflectReference {
+        ReflectReference::new_component_ref(
+            <ReflectComponent as FromType<Inventory>>::from_type(),
+            entity,
+            world_ptr,
+        )
+        .index(Cow::Borrowed("items"))
+    }
+
+    #[test]
+    fn maps_can_be_read_and_modified() {
+        let mut app = App::new();
+        LuaCoreBevyAPIProvider.register_with_app(&mut app);
+        app.register_type::<Inventory>()
+            .register_foreign_lua_type::<StdHashMap<String, u32>>();
+        let entity = app
+            .world
+            .spawn(Inventory {
+                items: [("potion".to_owned(), 3), ("arrows".to_owned(), 10)].into(),
+            })
+            .id();
+
+        let lua = Lua::new();
+        let result = {
+            // safety: the world is not used again until the guard is dropped
+            let guard = unsafe { WorldPointerGuard::new(&mut app.world) };
+            lua.globals()
+                .set("items", items_ref(guard.clone(), entity))
+                .unwrap();
+            lua.load(
+                r#"
+                local len = #items
+                local potions = items["potion"]
+                local missing = items["missing"] == nil
+                items["sword"] = 1
+                items:insert("shield", 4)
+                local removed = items:remove("potion")
+                local total = 0
+                for _, count in pairs(items) do
+                    total = total + count
+                end
+                return len, potions, missing, removed, items:contains_key("sword"), items:len(), total
+                "#,
+            )
+            .eval::<(usize, u32, bool, u32, bool, usize, u32)>()
+            .unwrap()
+        };
+
+        assert_eq!(result, (2, 3, true, 3, true, 3, 15));
+        assert_eq!(
+            app.world.get::<Inventory>(entity).unwrap().items,
+            [
+                ("arrows".to_owned(), 10),
+                ("sword".to_owned(), 1),
+                ("shield".to_owned(), 4)
+            ]
+            .into()
+        );
+    }
+}
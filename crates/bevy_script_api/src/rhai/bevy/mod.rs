@@ -9,11 +9,16 @@ use bevy_mod_scripting_rhai::{
 use rhai::plugin::*;
 
 use crate::{
-    common::bevy::{ScriptTypeRegistration, ScriptWorld},
-    ReflectedValue,
+    common::bevy::{
+        script_component::ScriptComponentType, script_resource::ScriptResourcesPlugin,
+        ChangeFilter, ScriptTypeRegistration, ScriptWorld,
+    },
+    ReflectReference, ReflectedValue,
 };
 
-use super::{RegisterForeignRhaiType, ToDynamic};
+use super::{
+    reflect_from_rhai, runtime_error, variant_from_rhai, RegisterForeignRhaiType, ToDynamic,
+};
 
 #[allow(deprecated)]
 impl CustomType for ScriptTypeRegistration {
@@ -40,6 +45,33 @@ impl CustomType for ScriptWorld {
                     .map(Dynamic::from)
                     .unwrap_or_default()
             })
+            .with_fn(
+                "new_variant",
+                |self_: ScriptWorld, enum_type: ScriptTypeRegistration, variant: &str| {
+                    let value = variant_from_rhai(
+                        self_.clone().into(),
+                        enum_type.type_id(),
+                        variant,
+                        Dynamic::UNIT,
+                    )?;
+                    ReflectReference::new_owned_ref(value, self_.into()).to_dynamic()
+                },
+            )
+            .with_fn(
+                "new_variant",
+                |self_: ScriptWorld,
+                 enum_type: ScriptTypeRegistration,
+                 variant: &str,
+                 fields: Dynamic| {
+                    let value = variant_from_rhai(
+                        self_.clone().into(),
+                        enum_type.type_id(),
+                        variant,
+                        fields,
+                    )?;
+                    ReflectReference::new_owned_ref(value, self_.into()).to_dynamic()
+                },
+            )
             .with_fn(
                 "add_default_component",
                 |self_: ScriptWorld, entity: Entity, type_registration: ScriptTypeRegistration| {
@@ -51,7 +83,26 @@ impl CustomType for ScriptWorld {
                                 Position::NONE,
                             ))
                         })
-                        .and_then(|ok| ok.to_dynamic())
+                        .and_then(reference_or_unit)
+                },
+            )
+            .with_fn(
+                "insert_component",
+                |self_: ScriptWorld,
+                 entity: Entity,
+                 comp_type: ScriptTypeRegistration,
+                 value: Dynamic| {
+                    let value =
+                        reflect_from_rhai(self_.clone().into(), comp_type.type_id(), value)?;
+                    self_
+                        .insert_component(entity, comp_type, value)
+                        .map_err(|e| {
+                            Box::new(EvalAltResult::ErrorRuntime(
+                                Dynamic::from(e.to_string()),
+                                Position::NONE,
+                            ))
+                        })
+                        .and_then(reference_or_unit)
                 },
             )
             .with_fn(
@@ -70,6 +121,35 @@ impl CustomType for ScriptWorld {
                     }
                 },
             )
+            .with_fn(
+                "get_component",
+                |self_: ScriptWorld,
+                 entity: Entity,
+                 comp_type: ScriptTypeRegistration,
+                 filter: &str| {
+                    let filter = change_filter(filter)?;
+                    let component = self_
+                        .get_component_filtered(entity, comp_type, filter)
+                        .map_err(|e| runtime_error(e.to_string()))?;
+                    if let Some(c) = component {
+                        c.to_dynamic()
+                    } else {
+                        Ok(Default::default())
+                    }
+                },
+            )
+            .with_fn(
+                "query_component",
+                |self_: ScriptWorld, comp_type: ScriptTypeRegistration| {
+                    query_component(&self_, comp_type, None)
+                },
+            )
+            .with_fn(
+                "query_component",
+                |self_: ScriptWorld, comp_type: ScriptTypeRegistration, filter: &str| {
+                    query_component(&self_, comp_type, Some(change_filter(filter)?))
+                },
+            )
             .with_fn(
                 "has_compoennt",
                 |self_: ScriptWorld, entity: Entity, comp_type: ScriptTypeRegistration| {
@@ -92,6 +172,71 @@ impl CustomType for ScriptWorld {
                     })
                 },
             )
+            .with_fn(
+                "declare_component",
+                |self_: ScriptWorld, name: &str, fields: rhai::Map| {
+                    self_
+                        .register_script_component(name, script_fields(&self_, fields)?)
+                        .map(|_| ())
+                        .map_err(|e| runtime_error(e.to_string()))
+                },
+            )
+            .with_fn(
+                "add_script_component",
+                |self_: ScriptWorld, entity: Entity, name: &str| {
+                    add_script_component(self_, entity, name, Default::default())
+                },
+            )
+            .with_fn("add_script_component", add_script_component)
+            .with_fn(
+                "get_script_component",
+                |self_: ScriptWorld, entity: Entity, name: &str| {
+                    let comp_type = script_component_type(&self_, name)?;
+                    match self_
+                        .get_script_component(entity, &comp_type)
+                        .map_err(|e| runtime_error(e.to_string()))?
+                    {
+                        Some(c) => c.to_dynamic(),
+                        None => Ok(Default::default()),
+                    }
+                },
+            )
+            .with_fn(
+                "has_script_component",
+                |self_: ScriptWorld, entity: Entity, name: &str| {
+                    let comp_type = script_component_type(&self_, name)?;
+                    self_
+                        .has_script_component(entity, &comp_type)
+                        .map_err(|e| runtime_error(e.to_string()))
+                },
+            )
+            .with_fn(
+                "query_script_component",
+                |self_: ScriptWorld, name: &str| {
+                    let comp_type = script_component_type(&self_, name)?;
+                    Ok::<_, Box<EvalAltResult>>(
+                        self_
+                            .query_script_component(&comp_type, None)
+                            .into_iter()
+                            .map(Dynamic::from)
+                            .collect::<Vec<_>>(),
+                    )
+                },
+            )
+            .with_fn(
+                "query_script_component",
+                |self_: ScriptWorld, name: &str, filter: &str| {
+                    let comp_type = script_component_type(&self_, name)?;
+                    let filter = change_filter(filter)?;
+                    Ok::<_, Box<EvalAltResult>>(
+                        self_
+                            .query_script_component(&comp_type, Some(filter))
+                            .into_iter()
+                            .map(Dynamic::from)
+                            .collect::<Vec<_>>(),
+                    )
+                },
+            )
             .with_fn(
                 "get_resource",
                 |self_: ScriptWorld, res_type: ScriptTypeRegistration| {
@@ -131,6 +276,46 @@ impl CustomType for ScriptWorld {
                     })
                 },
             )
+            .with_fn(
+                "declare_resource",
+                |self_: ScriptWorld, name: &str, fields: rhai::Map| {
+                    self_
+                        .register_script_resource(name, script_fields(&self_, fields)?)
+                        .map_err(|e| runtime_error(e.to_string()))
+                },
+            )
+            .with_fn("get_resource", |self_: ScriptWorld, name: &str| match self_
+                .get_script_resource(name)
+            {
+                Some(r) => r.to_dynamic(),
+                None => Ok(Default::default()),
+            })
+            .with_fn("has_resource", |self_: &mut ScriptWorld, name: &str| {
+                self_.has_script_resource(name)
+            })
+            .with_fn("remove_resource", |self_: &mut ScriptWorld, name: &str| {
+                self_.remove_script_resource(name);
+            })
+            .with_fn(
+                "send_event",
+                |self_: &mut ScriptWorld, event_type: &str, event: Dynamic| {
+                    let event_type = self_.get_type_by_name(event_type).ok_or_else(|| {
+                        Box::new(EvalAltResult::ErrorRuntime(
+                            format!("No type named `{event_type}` is registered").into(),
+                            Position::NONE,
+                        ))
+                    })?;
+                    let event =
+                        reflect_from_rhai(self_.clone().into(), event_type.type_id(), event)?;
+
+                    self_.send_event(event_type, event.as_ref()).map_err(|e| {
+                        Box::new(EvalAltResult::ErrorRuntime(
+                            e.to_string().into(),
+                            Position::NONE,
+                        ))
+                    })
+                },
+            )
             .with_fn("get_parent", |self_: ScriptWorld, entity: Entity| {
                 if let Some(parent) = self_.get_parent(entity) {
                     Dynamic::from(parent)
@@ -200,14 +385,39 @@ impl CustomType for ScriptWorld {
                 "despawn_recursive",
                 |self_: &mut ScriptWorld, entity: Entity| self_.despawn_recursive(entity),
             )
-            .with_fn("spawn", |self_: &mut ScriptWorld| {
-                let mut w = self_.write();
-                w.spawn(()).id()
-            })
-            .with_fn("despawn", |self_: &mut ScriptWorld, entity: Entity| {
-                let mut w = self_.write();
+            .with_fn("spawn", |self_: &mut ScriptWorld| self_.spawn())
+            .with_fn(
+                "spawn_with",
+                |self_: &mut ScriptWorld, components: rhai::Map| {
+                    let components = components
+                        .into_iter()
+                        .map(|(type_name, value)| {
+                            let comp_type =
+                                self_.get_type_by_name(&type_name).ok_or_else(|| {
+                                    Box::new(EvalAltResult::ErrorRuntime(
+                                        format!("No type named `{type_name}` is registered").into(),
+                                        Position::NONE,
+                                    ))
+                                })?;
+                            let value = reflect_from_rhai(
+                                self_.clone().into(),
+                                comp_type.type_id(),
+                                value,
+                            )?;
+                            Ok((comp_type, value))
+                        })
+                        .collect::<Result<Vec<_>, Box<EvalAltResult>>>()?;
 
-                w.despawn(entity)
+                    self_.spawn_with(components).map_err(|e| {
+                        Box::new(EvalAltResult::ErrorRuntime(
+                            e.to_string().into(),
+                            Position::NONE,
+                        ))
+                    })
+                },
+            )
+            .with_fn("despawn", |self_: &mut ScriptWorld, entity: Entity| {
+                self_.despawn(entity)
             })
             .with_fn("to_string", |self_: &mut ScriptWorld| self_.to_string())
             .with_fn("to_debug", |self_: &mut ScriptWorld| format!("{:?}", self_));
@@ -268,5 +478,85 @@ impl APIProvider for RhaiBevyAPIProvider {
         app.register_foreign_rhai_type::<u128>();
         app.register_foreign_rhai_type::<usize>();
         app.register_foreign_rhai_type::<String>();
+        app.insert_resource(bevy_mod_scripting_rhai::RhaiReflectConverter(
+            crate::rhai::reflect_to_rhai,
+        ));
+        if !app.is_plugin_added::<ScriptResourcesPlugin>() {
+            app.add_plugins(ScriptResourcesPlugin);
+        }
     }
 }
+
+fn script_component_type(
+    world: &ScriptWorld,
+    name: &str,
+) -> Result<ScriptComponentType, Box<EvalAltResult>> {
+    world
+        .get_script_component_type(name)
+        .ok_or_else(|| runtime_error(format!("No component named `{name}` was declared")))
+}
+
+/// Resolves a map of field names to type names into declared fields
+fn script_fields(
+    world: &ScriptWorld,
+    fields: rhai::Map,
+) -> Result<Vec<(String, ScriptTypeRegistration)>, Box<EvalAltResult>> {
+    fields
+        .into_iter()
+        .map(|(field, type_name)| {
+            let type_name = type_name.into_immutable_string()?;
+            let field_type = world.get_type_by_name(&type_name).ok_or_else(|| {
+                runtime_error(format!("No type named `{type_name}` is registered"))
+            })?;
+            Ok((field.to_string(), field_type))
+        })
+        .collect()
+}
+
+fn add_script_component(
+    world: ScriptWorld,
+    entity: Entity,
+    name: &str,
+    values: rhai::Map,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    let comp_type = script_component_type(&world, name)?;
+    let values = values
+        .into_iter()
+        .map(|(field, value)| {
+            let field_type = comp_type.fields().field_type(&field).ok_or_else(|| {
+                runtime_error(format!("Component `{name}` has no field `{field}`"))
+            })?;
+            let value = reflect_from_rhai(world.clone().into(), field_type.type_id(), value)?;
+            Ok((field.to_string(), value))
+        })
+        .collect::<Result<Vec<_>, Box<EvalAltResult>>>()?;
+
+    world
+        .insert_script_component(entity, &comp_type, values)
+        .map_err(|e| runtime_error(e.to_string()))
+        .and_then(reference_or_unit)
+}
+
+/// Converts the reference to an inserted component to a rhai value, components inserted in deferred mode have no reference yet
+fn reference_or_unit(reference: Option<ReflectReference>) -> Result<Dynamic, Box<EvalAltResult>> {
+    reference.map_or(Ok(Dynamic::UNIT), |r| r.to_dynamic())
+}
+
+fn change_filter(filter: &str) -> Result<ChangeFilter, Box<EvalAltResult>> {
+    filter
+        .parse()
+        .map_err(|e: ScriptError| runtime_error(e.to_string()))
+}
+
+fn query_component(
+    world: &ScriptWorld,
+    comp_type: ScriptTypeRegistration,
+    filter: Option<ChangeFilter>,
+) -> Result<Vec<Dynamic>, Box<EvalAltResult>> {
+    Ok(world
+        .query_component(comp_type, filter)
+        .map_err(|e| runtime_error(e.to_string()))?
+        .into_iter()
+        .map(Dynamic::from)
+        .collect())
+}
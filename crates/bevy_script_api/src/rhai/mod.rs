@@ -1,13 +1,23 @@
 use ::std::borrow::Cow;
 
 use ::bevy::{
-    prelude::{App, AppTypeRegistry},
-    reflect::{FromType, GetTypeRegistration, Reflect},
+    prelude::{App, AppTypeRegistry, Entity},
+    reflect::{FromType, GetTypeRegistration, Reflect, TypeRegistration},
 };
+use bevy_mod_scripting_core::world::WorldPointer;
 #[allow(deprecated)]
-use bevy_mod_scripting_rhai::rhai::{CustomType, Dynamic, EvalAltResult, INT};
+use bevy_mod_scripting_rhai::rhai::{
+    Array, CustomType, Dynamic, EvalAltResult, ImmutableString, Map, INT,
+};
 
-use crate::{ReflectReference, ReflectedValue, ValueIndex};
+use crate::{
+    common::bevy::{
+        from_dynamic,
+        script_value::{dynamic_from_script, dynamic_variant_from_script, ScriptValueAccess},
+        type_registration,
+    },
+    ReflectReference, ReflectedValue, ValueIndex,
+};
 
 pub mod bevy;
 pub mod std;
@@ -117,6 +127,123 @@ impl ToDynamic for ReflectReference {
     }
 }
 
+/// Converts a copy of the given reflected value into the most convenient rhai representation, see [`ToDynamic`].
+///
+/// Used to pass the arguments of reflected script events to rhai scripts
+pub fn reflect_to_rhai(
+    world_ptr: WorldPointer,
+    value: &dyn Reflect,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    ReflectReference::new_owned_copy(value, world_ptr).to_dynamic()
+}
+
+/// Builds a value of the given reflected type from a rhai value.
+///
+/// Object maps and arrays are converted field by field (or item by item) into dynamic values following the type's reflect info,
+/// missing fields are taken from the type's `Default` implementation if it has one.
+/// Enums without a [`ReflectRhaiProxyable`] registration are built from the name of a variant,
+/// or an object map holding the variant's fields under its name, see [`variant_from_rhai`].
+/// Any other value is assigned to a default instance of the type via its [`ReflectRhaiProxyable`] registration,
+/// the result is converted into the concrete type via `ReflectFromReflect` where possible.
+pub fn reflect_from_rhai(
+    world_ptr: WorldPointer,
+    type_id: TypeId,
+    value: Dynamic,
+) -> Result<Box<dyn Reflect>, Box<EvalAltResult>> {
+    let value = dynamic_from_script(&RhaiValues, &world_ptr, type_id, value)?;
+    Ok(from_dynamic(&world_ptr, type_id, value))
+}
+
+/// Builds a value of the given enum type with the given variant.
+///
+/// Struct variants take an object map of field values, tuple variants an array of field values or the value of their only field.
+/// Missing fields are set to the default values of their types.
+pub fn variant_from_rhai(
+    world_ptr: WorldPointer,
+    type_id: TypeId,
+    variant: &str,
+    fields: Dynamic,
+) -> Result<Box<dyn Reflect>, Box<EvalAltResult>> {
+    let registration =
+        type_registration(&world_ptr, type_id).map_err(|e| runtime_error(e.to_string()))?;
+    let value =
+        dynamic_variant_from_script(&RhaiValues, &world_ptr, &registration, variant, fields)?;
+    Ok(from_dynamic(&world_ptr, type_id, value))
+}
+
+/// Gives the conversions in [`crate::common::bevy::script_value`] access to rhai values
+struct RhaiValues;
+
+impl ScriptValueAccess for RhaiValues {
+    type Value = Dynamic;
+    type Error = Box<EvalAltResult>;
+
+    fn error(&self, msg: String) -> Self::Error {
+        runtime_error(msg)
+    }
+
+    fn type_name<'v>(&self, value: &'v Self::Value) -> &'v str {
+        value.type_name()
+    }
+
+    fn unit(&self) -> Self::Value {
+        Dynamic::UNIT
+    }
+
+    fn is_unit(&self, value: &Self::Value) -> bool {
+        value.is_unit()
+    }
+
+    fn has_proxy(&self, registration: &TypeRegistration) -> bool {
+        registration.data::<ReflectRhaiProxyable>().is_some()
+    }
+
+    fn reflected_value(
+        &self,
+        value: &Self::Value,
+    ) -> Result<Option<ReflectReference>, Self::Error> {
+        Ok(value
+            .read_lock::<ReflectedValue>()
+            .map(|value| value.ref_.clone()))
+    }
+
+    fn entity(&self, value: &Self::Value) -> Result<Option<Entity>, Self::Error> {
+        Ok(value.clone().try_cast::<Entity>())
+    }
+
+    fn string(&self, value: &Self::Value) -> Result<Option<String>, Self::Error> {
+        Ok(value.read_lock::<ImmutableString>().map(|s| s.to_string()))
+    }
+
+    fn is_map(&self, value: &Self::Value) -> bool {
+        value.is_map()
+    }
+
+    fn is_sequence(&self, value: &Self::Value) -> bool {
+        value.is_array()
+    }
+
+    fn entries(&self, value: Self::Value) -> Result<Vec<(Self::Value, Self::Value)>, Self::Error> {
+        Ok(value
+            .cast::<Map>()
+            .into_iter()
+            .map(|(key, value)| (key.to_string().into(), value))
+            .collect())
+    }
+
+    fn items(&self, value: Self::Value) -> Result<Vec<Self::Value>, Self::Error> {
+        Ok(value.cast::<Array>())
+    }
+
+    fn apply(&self, ref_: &mut ReflectReference, value: Self::Value) -> Result<(), Self::Error> {
+        ref_.apply_rhai(value)
+    }
+}
+
+pub(crate) fn runtime_error(msg: String) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(msg.into(), Position::NONE))
+}
+
 pub trait ApplyRhai {
     fn apply_rhai(&mut self, value: Dynamic) -> Result<(), Box<EvalAltResult>>;
 }
@@ -181,9 +308,21 @@ impl CustomType for ReflectedValue {
             .with_indexer_set_result(|obj: &mut ReflectedValue, index: Dynamic, value: Dynamic| {
                 obj.ref_.index(index)?.apply_rhai(value)
             })
+            .with_result_fn("get_path", |self_: &mut ReflectedValue, path: &str| {
+                self_.ref_.sub_path(path)?.to_dynamic()
+            })
+            .with_result_fn(
+                "set_path",
+                |self_: &mut ReflectedValue, path: &str, value: Dynamic| {
+                    self_.ref_.sub_path(path)?.apply_rhai(value)
+                },
+            )
             .with_fn("to_debug", |self_: &mut ReflectedValue| {
                 format!("{:?}", self_)
             })
+            .with_result_fn("variant_name", |self_: &mut ReflectedValue| {
+                self_.ref_.variant_name().map_err(Into::into)
+            })
             .with_fn("to_string", |self_: &mut ReflectedValue| {
                 self_
                     .ref_
@@ -192,3 +331,81 @@ impl CustomType for ReflectedValue {
             });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use ::bevy::{
+        prelude::{
+            App, Component, Entity, Quat, ReflectComponent, ReflectDefault, Transform, Vec3, World,
+        },
+        reflect::Reflect,
+    };
+    use bevy_mod_scripting_core::{hosts::APIProvider, world::WorldPointerGuard};
+    use bevy_mod_scripting_rhai::rhai::{Dynamic, Engine, EvalAltResult};
+
+    use crate::{common::bevy::ScriptWorld, rhai::bevy::RhaiBevyAPIProvider};
+
+    use super::{reflect_from_rhai, runtime_error};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Health(u32);
+
+    fn setup_app() -> App {
+        let mut app = App::new();
+        RhaiBevyAPIProvider.register_with_app(&mut app);
+        app.register_type::<Transform>()
+            .register_type::<Vec3>()
+            .register_type::<Quat>()
+            .register_type::<Health>();
+        app
+    }
+
+    /// Evaluates the given rhai expression and inserts the result as a component of the given type into a new entity
+    fn insert_from_rhai(
+        world: &mut World,
+        type_name: &str,
+        expr: &str,
+    ) -> Result<Entity, Box<EvalAltResult>> {
+        let value = Engine::new().eval_expression::<Dynamic>(expr)?;
+        let entity = world.spawn_empty().id();
+
+        // safety: the world is not used again until the guard is dropped at the end of this function
+        let guard = unsafe { WorldPointerGuard::new(world) };
+        let world = ScriptWorld::new(guard.clone());
+        let comp_type = world.get_type_by_name(type_name).unwrap();
+        let value = reflect_from_rhai(guard.clone(), comp_type.type_id(), value)?;
+        world
+            .insert_component(entity, comp_type, value)
+            .map_err(|e| runtime_error(e.to_string()))?;
+        Ok(entity)
+    }
+
+    #[test]
+    fn object_maps_become_components() {
+        let mut app = setup_app();
+
+        let entity = insert_from_rhai(
+            &mut app.world,
+            "Transform",
+            "#{ translation: #{ x: 1.0, y: 2.0 } }",
+        )
+        .unwrap();
+        // missing fields are taken from the default transform
+        assert_eq!(
+            app.world.get::<Transform>(entity),
+            Some(&Transform::from_xyz(1.0, 2.0, 0.0))
+        );
+
+        let entity = insert_from_rhai(&mut app.world, "Health", "[5]").unwrap();
+        assert_eq!(app.world.get::<Health>(entity), Some(&Health(5)));
+    }
+
+    #[test]
+    fn unknown_fields_are_errors() {
+        let mut app = setup_app();
+
+        assert!(insert_from_rhai(&mut app.world, "Transform", "#{ speed: 1.0 }").is_err());
+        assert!(insert_from_rhai(&mut app.world, "Health", "[5, 6]").is_err());
+    }
+}
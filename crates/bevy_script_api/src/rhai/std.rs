@@ -1,16 +1,22 @@
 use std::{
     any::type_name,
+    collections::HashMap as StdHashMap,
     fmt::{Debug, Display},
+    hash::{BuildHasher, Hash},
     iter::Map,
 };
 
-use bevy::reflect::{FromReflect, Reflect, TypePath};
+use bevy::{
+    reflect::{FromReflect, Reflect, TypePath},
+    utils::hashbrown::HashMap as BevyHashMap,
+};
 #[allow(deprecated)]
-use bevy_mod_scripting_rhai::rhai::{CustomType, Dynamic, Engine, EvalAltResult, Position};
+use bevy_mod_scripting_rhai::rhai::{self, CustomType, Dynamic, Engine, EvalAltResult, Position};
 
 use crate::{
-    common::std::ScriptVec, error::ReflectionError, ReflectReference, ReflectionPathElement,
-    ValueIndex,
+    common::std::{ScriptMap, ScriptMapIterator, ScriptVec},
+    error::ReflectionError,
+    ReflectReference, ReflectionPathElement, ValueIndex,
 };
 
 use super::{ApplyRhai, FromRhaiProxy, RhaiProxyable, ToDynamic, ToRhaiProxy};
@@ -425,3 +431,327 @@ impl RegisterVecType for Engine {
         self
     }
 }
+
+/// Composite trait composing the various traits required for a type `K` to be used as the key of a RhaiMap<K, V>
+pub trait RhaiMapKey:
+    FromReflect + TypePath + Clone + Eq + Hash + FromRhaiProxy + ToRhaiProxy
+{
+}
+impl<K: FromReflect + TypePath + Clone + Eq + Hash + FromRhaiProxy + ToRhaiProxy> RhaiMapKey for K {}
+
+/// Composite trait composing the various traits required for a type `V` to be used as the value of a RhaiMap<K, V>
+pub trait RhaiMapValue: RhaiVecElem + ToRhaiProxy {}
+impl<V: RhaiVecElem + ToRhaiProxy> RhaiMapValue for V {}
+
+/// A ScriptMap wrapper which iterates over `[key, value]` pairs
+pub struct RhaiMap<K: RhaiMapKey, V: RhaiMapValue>(pub ScriptMap<K, V>);
+
+impl<K: RhaiMapKey, V: RhaiMapValue> Clone for RhaiMap<K, V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K: RhaiMapKey, V: RhaiMapValue> Debug for RhaiMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl<K: RhaiMapKey, V: RhaiMapValue> RhaiMap<K, V> {
+    pub fn new_ref(self_: crate::ReflectReference) -> Self {
+        Self(ScriptMap::<K, V>::new_ref(self_))
+    }
+}
+
+impl<K: RhaiMapKey, V: RhaiMapValue> std::ops::Deref for RhaiMap<K, V> {
+    type Target = ScriptMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K: RhaiMapKey, V: RhaiMapValue> std::ops::DerefMut for RhaiMap<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+pub struct RhaiMapIterator<K, V>(Result<ScriptMapIterator<K, V>, Option<ReflectionError>>);
+
+impl<K: RhaiMapKey, V> Iterator for RhaiMapIterator<K, V> {
+    type Item = Result<Dynamic, Box<EvalAltResult>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            Ok(entries) => {
+                let (key, value) = entries.next()?;
+                Some(
+                    key.to_rhai_proxy()
+                        .and_then(|key| Ok(Dynamic::from_array(vec![key, value.to_dynamic()?]))),
+                )
+            }
+            // report the error once then stop
+            Err(e) => e.take().map(|e| Err(e.into())),
+        }
+    }
+}
+
+impl<K: RhaiMapKey, V: RhaiMapValue> IntoIterator for RhaiMap<K, V> {
+    type Item = Result<Dynamic, Box<EvalAltResult>>;
+
+    type IntoIter = RhaiMapIterator<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        RhaiMapIterator(self.0.iter().map_err(Some))
+    }
+}
+
+/// Implements the rhai proxy traits for a hash map type, making it available to scripts as a [`RhaiMap`]
+macro_rules! impl_rhai_proxy_for_map {
+    ($map_ty:ident) => {
+        impl<K: RhaiMapKey, V: RhaiMapValue, S: TypePath + BuildHasher + Default + Send + Sync>
+            RhaiProxyable for $map_ty<K, V, S>
+        {
+            fn ref_to_rhai(self_: crate::ReflectReference) -> Result<Dynamic, Box<EvalAltResult>> {
+                Ok(Dynamic::from(RhaiMap::<K, V>::new_ref(self_)))
+            }
+
+            fn apply_rhai(
+                self_: &mut crate::ReflectReference,
+                new_val: Dynamic,
+            ) -> Result<(), Box<EvalAltResult>> {
+                if new_val.is::<rhai::Map>() {
+                    // entries of the object map are inserted into the map, overwriting existing ones
+                    let mut map = ScriptMap::<K, V>::new_ref(self_.clone());
+                    for (key, value) in new_val.cast::<rhai::Map>() {
+                        map.insert(
+                            K::from_rhai_proxy(Dynamic::from(key))?,
+                            V::from_rhai_proxy(value)?,
+                        )?;
+                    }
+                    Ok(())
+                } else if new_val.is::<RhaiMap<K, V>>() {
+                    let map = new_val.cast::<RhaiMap<K, V>>();
+                    self_.apply(&map.ref_)?;
+                    Ok(())
+                } else {
+                    Err(Box::new(EvalAltResult::ErrorMismatchDataType(
+                        "Map or HashMap".to_owned(),
+                        new_val.type_name().to_owned(),
+                        Position::NONE,
+                    )))
+                }
+            }
+        }
+
+        impl<K: RhaiMapKey, V: RhaiMapValue, S: TypePath + BuildHasher + Default + Send + Sync>
+            FromRhaiProxy for $map_ty<K, V, S>
+        {
+            fn from_rhai_proxy(self_: Dynamic) -> Result<Self, Box<EvalAltResult>> {
+                if self_.is::<RhaiMap<K, V>>() {
+                    let map = self_.cast::<RhaiMap<K, V>>();
+                    map.iter()?
+                        .map(|(key, value)| Ok((key, value.get_typed(|v: &V| v.clone())?)))
+                        .collect()
+                } else if self_.is::<rhai::Map>() {
+                    self_
+                        .cast::<rhai::Map>()
+                        .into_iter()
+                        .map(|(key, value)| {
+                            Ok((
+                                K::from_rhai_proxy(Dynamic::from(key))?,
+                                V::from_rhai_proxy(value)?,
+                            ))
+                        })
+                        .collect()
+                } else {
+                    Err(Box::new(EvalAltResult::ErrorMismatchDataType(
+                        "Map or HashMap".to_owned(),
+                        self_.type_name().to_owned(),
+                        Position::NONE,
+                    )))
+                }
+            }
+        }
+
+        impl<K: RhaiMapKey, V: RhaiMapValue, S> ToRhaiProxy for $map_ty<K, V, S> {
+            /// Converts the map into an object map, keys are converted to strings
+            fn to_rhai_proxy(self) -> Result<Dynamic, Box<EvalAltResult>> {
+                self.into_iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            key.to_rhai_proxy()?.to_string().into(),
+                            value.to_rhai_proxy()?,
+                        ))
+                    })
+                    .collect::<Result<rhai::Map, Box<EvalAltResult>>>()
+                    .map(Dynamic::from)
+            }
+        }
+    };
+}
+
+impl_rhai_proxy_for_map!(StdHashMap);
+impl_rhai_proxy_for_map!(BevyHashMap);
+
+#[allow(deprecated)]
+impl<K: RhaiMapKey, V: RhaiMapValue> CustomType for RhaiMap<K, V> {
+    fn build(mut builder: bevy_mod_scripting_rhai::rhai::TypeBuilder<Self>) {
+        builder
+            .with_name(type_name::<ScriptMap<K, V>>())
+            .with_fn("to_debug", |map: &mut RhaiMap<K, V>| format!("{:?}", map))
+            .with_fn("to_string", |map: &mut RhaiMap<K, V>| {
+                map.ref_
+                    .get(|s| format!("{:?}", &s))
+                    .map_err::<Box<EvalAltResult>, _>(|e| e.into())
+            })
+            .with_result_fn("is_empty", |map: &mut RhaiMap<K, V>| {
+                map.is_empty().map_err(Into::into)
+            })
+            .with_result_fn("len", |map: &mut RhaiMap<K, V>| {
+                map.len().map(|v| v as INT).map_err(Into::into)
+            })
+            .with_result_fn("contains_key", |map: &mut RhaiMap<K, V>, key: Dynamic| {
+                map.contains_key(&K::from_rhai_proxy(key)?)
+                    .map_err(Into::into)
+            })
+            .with_result_fn("keys", |map: &mut RhaiMap<K, V>| {
+                map.keys()?
+                    .into_iter()
+                    .map(ToRhaiProxy::to_rhai_proxy)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .with_result_fn(
+                "insert",
+                |map: &mut RhaiMap<K, V>, key: Dynamic, val: Dynamic| {
+                    map.insert(K::from_rhai_proxy(key)?, V::from_rhai_proxy(val)?)?
+                        .to_rhai_proxy()
+                },
+            )
+            .with_result_fn("remove", |map: &mut RhaiMap<K, V>, key: Dynamic| {
+                map.remove(&K::from_rhai_proxy(key)?)?.to_rhai_proxy()
+            })
+            .with_result_fn(
+                "index$get$",
+                |map: &mut RhaiMap<K, V>, key: Dynamic| match map.get(K::from_rhai_proxy(key)?)? {
+                    Some(value) => value.to_dynamic(),
+                    None => Ok(Dynamic::UNIT),
+                },
+            )
+            .with_result_fn(
+                "index$set$",
+                |map: &mut RhaiMap<K, V>, key: Dynamic, value: Dynamic| {
+                    map.insert(K::from_rhai_proxy(key)?, V::from_rhai_proxy(value)?)?;
+                    Ok(())
+                },
+            );
+    }
+}
+
+/// A trait for making monomorphization of HashMap<K, V> implementations for any K and V easier, see [`RegisterVecType`]
+pub trait RegisterMapType {
+    fn register_map_functions<K: RhaiMapKey, V: RhaiMapValue>(&mut self) -> &mut Self;
+}
+
+impl RegisterMapType for Engine {
+    fn register_map_functions<K: RhaiMapKey, V: RhaiMapValue>(&mut self) -> &mut Self {
+        self.build_type::<RhaiMap<K, V>>();
+        self.register_iterator_result::<RhaiMap<K, V>, _>();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use bevy::{
+        prelude::{App, Component, Entity, ReflectComponent},
+        reflect::FromType,
+    };
+    use bevy_mod_scripting_core::{
+        hosts::APIProvider,
+        world::{WorldPointer, WorldPointerGuard},
+    };
+    use bevy_mod_scripting_rhai::rhai::{Array, Scope};
+
+    use crate::rhai::{bevy::RhaiBevyAPIProvider, RegisterForeignRhaiType};
+
+    use super::*;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Inventory {
+        items: StdHashMap<String, u32>,
+    }
+
+    fn items_ref(world_ptr: WorldPointer, entity: Entity) -> ReflectReference {
+        ReflectReference::new_component_ref(
+            <ReflectComponent as FromType<Inventory>>::from_type(),
+            entity,
+            world_ptr,
+        )
+        .index(Cow::Borrowed("items"))
+    }
+
+    #[test]
+    fn maps_can_be_read_and_modified() {
+        let mut app = App::new();
+        RhaiBevyAPIProvider.register_with_app(&mut app);
+        app.register_type::<Inventory>()
+            .register_foreign_rhai_type::<StdHashMap<String, u32>>();
+        let entity = app
+            .world
+            .spawn(Inventory {
+                items: [("potion".to_owned(), 3), ("arrows".to_owned(), 10)].into(),
+            })
+            .id();
+
+        let mut engine = Engine::new();
+        engine.register_map_functions::<String, u32>();
+        let result = {
+            // safety: the world is not used again until the guard is dropped
+            let guard = unsafe { WorldPointerGuard::new(&mut app.world) };
+            let mut scope = Scope::new();
+            scope.push(
+                "items",
+                items_ref(guard.clone(), entity).to_dynamic().unwrap(),
+            );
+            engine
+                .eval_with_scope::<Array>(
+                    &mut scope,
+                    r#"
+                    let len = items.len();
+                    let potions = items["potion"];
+                    let missing = items["missing"] == ();
+                    items["sword"] = 1;
+                    items.insert("shield", 4);
+                    let removed = items.remove("potion");
+                    let total = 0;
+                    for entry in items {
+                        total += entry[1];
+                    }
+                    [len, potions, missing, removed, items.contains_key("sword"), items.len(), total]
+                    "#,
+                )
+                .unwrap()
+        };
+
+        let result = result
+            .into_iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(result, ["2", "3", "true", "3", "true", "3", "15"]);
+        assert_eq!(
+            app.world.get::<Inventory>(entity).unwrap().items,
+            [
+                ("arrows".to_owned(), 10),
+                ("sword".to_owned(), 1),
+                ("shield".to_owned(), 4)
+            ]
+            .into()
+        );
+    }
+}
@@ -1,7 +1,14 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::component::ComponentId,
+    prelude::*,
+    reflect::{ReflectFromReflect, ReflectRef},
+};
 use parking_lot::RwLock;
 use std::fmt::Debug;
-use std::{borrow::Cow, sync::Weak};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Weak},
+};
 
 use bevy_mod_scripting_core::world::WorldPointer;
 
@@ -43,6 +50,22 @@ impl ReflectReference {
         }
     }
 
+    /// Creates a reference to a component declared by a script,
+    /// the component id must belong to a [`crate::common::bevy::script_component::ScriptComponentType`]
+    pub(crate) fn new_script_component_ref(
+        component_id: ComponentId,
+        entity: Entity,
+        world_ptr: WorldPointer,
+    ) -> Self {
+        Self {
+            path: ReflectionPath::new(ReflectBase::ScriptComponent {
+                component_id,
+                entity,
+            }),
+            world_ptr,
+        }
+    }
+
     pub fn new_resource_ref(res: ReflectResource, world_ptr: WorldPointer) -> Self {
         Self {
             path: ReflectionPath::new(ReflectBase::Resource { res }),
@@ -50,6 +73,14 @@ impl ReflectReference {
         }
     }
 
+    /// Creates a reference to a resource declared by a script
+    pub fn new_script_resource_ref(name: String, world_ptr: WorldPointer) -> Self {
+        Self {
+            path: ReflectionPath::new(ReflectBase::ScriptResource { name }),
+            world_ptr,
+        }
+    }
+
     /// Creates a reference to a script owned value
     pub fn new_script_ref<T: Reflect>(ptr: Weak<RwLock<T>>, world_ptr: WorldPointer) -> Self {
         Self {
@@ -58,6 +89,32 @@ impl ReflectReference {
         }
     }
 
+    /// Creates a reference owning the given value, the value lives for as long as any reference to it
+    pub fn new_owned_ref(val: Box<dyn Reflect>, world_ptr: WorldPointer) -> Self {
+        Self {
+            path: ReflectionPath::new(ReflectBase::Owned {
+                val: Arc::new(RwLock::new(val)),
+            }),
+            world_ptr,
+        }
+    }
+
+    /// Creates a reference owning a copy of the given value.
+    ///
+    /// The copy is made via `ReflectFromReflect` if the type registers it, so that it keeps its concrete type
+    /// (and with it any script proxy registrations), otherwise the copy is a dynamic value.
+    pub fn new_owned_copy(val: &dyn Reflect, world_ptr: WorldPointer) -> Self {
+        let copy = {
+            let world = world_ptr.read();
+            let registry = world.resource::<AppTypeRegistry>().read();
+            val.get_represented_type_info()
+                .and_then(|info| registry.get_type_data::<ReflectFromReflect>(info.type_id()))
+                .and_then(|from_reflect| from_reflect.from_reflect(val))
+        };
+
+        Self::new_owned_ref(copy.unwrap_or_else(|| val.clone_value()), world_ptr)
+    }
+
     /// Creates a new script reference which points to a sub component of the original data,
     /// This also updates the pointer
     pub(crate) fn sub_ref(&self, elem: ReflectionPathElement) -> ReflectReference {
@@ -69,8 +126,17 @@ impl ReflectReference {
         }
     }
 
+    /// Creates a new script reference to the value at the given reflection path relative to this reference,
+    /// for example `translation.x` or `items[2].name`. See [`ReflectionPathElement::parse_path`].
+    pub fn sub_path(&self, path: &str) -> Result<ReflectReference, ReflectionError> {
+        Ok(ReflectionPathElement::parse_path(path)?
+            .into_iter()
+            .fold(self.clone(), |ref_, elem| ref_.sub_ref(elem)))
+    }
+
     /// Retrieves the underlying `dyn Reflect` reference and applies function which can retrieve a value.
-    /// Panics if the reference is invalid or world is already borrowed mutably.
+    /// Returns an error if the reference is invalid or the value is already borrowed mutably.
+    /// Returns an error if the world is already borrowed mutably or is no longer accessible.
     #[inline(always)]
     pub fn get<O, F>(&self, f: F) -> Result<O, ReflectionError>
     where
@@ -79,25 +145,25 @@ impl ReflectReference {
         self.path.get(self.world_ptr.clone(), f)
     }
 
+    /// Like [`Self::get`] but downcasts the value to `T` first.
+    /// Returns an error if the referenced value is not of type `T`.
     pub fn get_typed<T, O, F>(&self, f: F) -> Result<O, ReflectionError>
     where
         F: FnOnce(&T) -> O,
         T: Reflect,
     {
         self.path.get(self.world_ptr.clone(), |reflect| {
-            (f)(reflect.downcast_ref::<T>().unwrap_or_else(|| {
-                panic!(
-                    "Expected `{}` found `{}`",
-                    ::std::any::type_name::<T>(),
-                    reflect.get_represented_type_info().unwrap().type_path()
-                )
-            }))
-        })
+            reflect
+                .downcast_ref::<T>()
+                .map(f)
+                .ok_or_else(|| downcast_error::<T>(reflect))
+        })?
     }
 
     /// Retrieves the underlying `dyn Reflect` reference and applies function which can retrieve a value.
     /// If this is a component it is marked as changed.
-    /// Panics if the reference is invalid or if the world/value is already borrowed or if r is not a mutable pointer.
+    /// Returns an error if the reference is invalid or the value is already borrowed.
+    /// Returns an error if the world is already borrowed or is no longer accessible.
     #[inline(always)]
     pub fn get_mut<O, F>(&mut self, f: F) -> Result<O, ReflectionError>
     where
@@ -106,14 +172,31 @@ impl ReflectReference {
         self.path.get_mut(self.world_ptr.clone(), f)
     }
 
+    /// Like [`Self::get_mut`] but downcasts the value to `T` first.
+    /// Returns an error if the referenced value is not of type `T`.
     pub fn get_mut_typed<T, O, F>(&mut self, f: F) -> Result<O, ReflectionError>
     where
         F: FnOnce(&mut T) -> O,
         T: Reflect,
     {
         self.path.get_mut(self.world_ptr.clone(), |reflect| {
-            (f)(reflect.downcast_mut().unwrap())
-        })
+            if reflect.is::<T>() {
+                Ok(f(reflect.downcast_mut::<T>().unwrap()))
+            } else {
+                Err(downcast_error::<T>(reflect))
+            }
+        })?
+    }
+
+    /// Returns the name of the active variant if this is a reference to an enum
+    pub fn variant_name(&self) -> Result<String, ReflectionError> {
+        self.get(|s| match s.reflect_ref() {
+            ReflectRef::Enum(e) => Ok(e.variant_name().to_owned()),
+            _ => Err(ReflectionError::CannotDowncast {
+                from: s.reflect_type_path().to_owned().into(),
+                to: "Enum".into(),
+            }),
+        })?
     }
 
     /// applies another [`ReflectReference`] to self by carefuly acquiring locks and cloning if necessary.
@@ -148,6 +231,13 @@ impl ReflectReference {
     }
 }
 
+fn downcast_error<T: Reflect>(reflect: &dyn Reflect) -> ReflectionError {
+    ReflectionError::CannotDowncast {
+        from: reflect.reflect_type_path().to_owned().into(),
+        to: std::any::type_name::<T>().into(),
+    }
+}
+
 /// A version of index for returning values instead of references
 pub trait ValueIndex<Idx> {
     type Output;
@@ -171,6 +261,14 @@ impl ValueIndex<Cow<'static, str>> for ReflectReference {
     }
 }
 
+impl ValueIndex<Arc<dyn Reflect>> for ReflectReference {
+    type Output = Self;
+
+    fn index(&self, key: Arc<dyn Reflect>) -> Self::Output {
+        self.sub_ref(ReflectionPathElement::MapAccess(key))
+    }
+}
+
 /// A value representing a type which has no special UserData implementation,
 /// It exposes the much less convenient reflect interface of the underlying type.
 #[derive(Clone, Debug)]
@@ -183,3 +281,66 @@ impl From<ReflectedValue> for ReflectReference {
         ref_.ref_
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        prelude::{Resource, World},
+        reflect::FromType,
+    };
+    use bevy_mod_scripting_core::world::WorldPointerGuard;
+
+    use super::*;
+
+    #[derive(Resource, Reflect, Default)]
+    struct Counter(u32);
+
+    fn counter_ref(world_ptr: WorldPointer) -> ReflectReference {
+        ReflectReference::new_resource_ref(
+            <ReflectResource as FromType<Counter>>::from_type(),
+            world_ptr,
+        )
+    }
+
+    #[test]
+    fn type_mismatch_is_an_error() {
+        let mut world = World::new();
+        world.insert_resource(Counter(3));
+        // safety: the world is not used again until the guard is dropped
+        let guard = unsafe { WorldPointerGuard::new(&mut world) };
+        let mut ref_ = counter_ref(guard.clone());
+
+        assert_eq!(ref_.get_typed(|c: &Counter| c.0).unwrap(), 3);
+        assert!(matches!(
+            ref_.get_typed(|_: &String| ()),
+            Err(ReflectionError::CannotDowncast { .. })
+        ));
+        assert!(matches!(
+            ref_.get_mut_typed(|_: &mut u32| ()),
+            Err(ReflectionError::CannotDowncast { .. })
+        ));
+    }
+
+    #[test]
+    fn inaccessible_world_is_an_error() {
+        let mut world = World::new();
+        world.insert_resource(Counter(3));
+        // safety: the world is not used again until the guard is dropped
+        let guard = unsafe { WorldPointerGuard::new(&mut world) };
+        let ref_ = counter_ref(guard.clone());
+
+        {
+            let _lock = guard.write();
+            assert!(matches!(
+                ref_.get(|_| ()),
+                Err(ReflectionError::BorrowConflict { .. })
+            ));
+        }
+
+        drop(guard);
+        assert!(matches!(
+            ref_.get(|_| ()),
+            Err(ReflectionError::StaleReference { .. })
+        ));
+    }
+}
@@ -1,15 +1,16 @@
-use parking_lot::RwLock;
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock};
 use std::fmt;
 use std::fmt::{Debug, Display};
 use std::sync::Arc;
 use std::{borrow::Cow, sync::Weak};
 
 use bevy::{
-    prelude::{Entity, ReflectComponent, ReflectResource},
-    reflect::{Reflect, ReflectMut, ReflectRef},
+    ecs::component::ComponentId,
+    prelude::{Entity, ReflectComponent, ReflectResource, World},
+    reflect::{DynamicStruct, Reflect, ReflectMut, ReflectRef},
 };
 
-use crate::error::ReflectionError;
+use crate::{common::bevy::script_resource::ScriptResources, error::ReflectionError};
 use bevy_mod_scripting_core::world::WorldPointer;
 
 /// The base of a reflect path, i.e. the top-level object or source.
@@ -23,11 +24,21 @@ pub(crate) enum ReflectBase {
         comp: ReflectComponent,
         entity: Entity,
     },
+    /// A reference to a component declared by a script, stored as a `DynamicStruct`
+    ScriptComponent {
+        component_id: ComponentId,
+        entity: Entity,
+    },
     /// A bevy resource reference
     Resource { res: ReflectResource },
+    /// A reference to a resource declared by a script, stored in [`ScriptResources`]
+    ScriptResource { name: String },
 
     /// A script owned reflect type (for example a vector constructed in lua)
     ScriptOwned { val: Weak<RwLock<dyn Reflect>> },
+
+    /// A reflect value owned by the reference itself (for example an argument passed to a script callback)
+    Owned { val: Arc<RwLock<Box<dyn Reflect>>> },
 }
 
 /// Safety: we can safely send this value across thread boundaries
@@ -42,8 +53,17 @@ impl fmt::Debug for ReflectBase {
             Self::Component { entity, .. } => {
                 f.debug_struct("Component").field("entity", entity).finish()
             }
+            Self::ScriptComponent { entity, .. } => f
+                .debug_struct("ScriptComponent")
+                .field("entity", entity)
+                .finish(),
             Self::ScriptOwned { .. } => write!(f, "ScriptOwned"),
+            Self::Owned { .. } => write!(f, "Owned"),
             Self::Resource { .. } => f.debug_struct("Resource").finish(),
+            Self::ScriptResource { name } => f
+                .debug_struct("ScriptResource")
+                .field("name", name)
+                .finish(),
         }
     }
 }
@@ -56,8 +76,19 @@ impl fmt::Display for ReflectBase {
                 f.write_str(&entity.index().to_string())?;
                 f.write_str(")")
             }
+            ReflectBase::ScriptComponent { entity, .. } => {
+                f.write_str("(ScriptComponent on ")?;
+                f.write_str(&entity.index().to_string())?;
+                f.write_str(")")
+            }
             ReflectBase::Resource { .. } => f.write_str("(Resource)"),
+            ReflectBase::ScriptResource { name } => {
+                f.write_str("(ScriptResource ")?;
+                f.write_str(name)?;
+                f.write_str(")")
+            }
             ReflectBase::ScriptOwned { .. } => f.write_str("(ScriptOwned)"),
+            ReflectBase::Owned { .. } => f.write_str("(Owned)"),
         }
     }
 }
@@ -74,10 +105,12 @@ pub enum ReflectionPathElement {
         get: Arc<Get>,
         get_mut: Arc<GetMut>,
     },
-    /// Access to a struct field
+    /// Access to a field of a struct or of the active variant of an enum
     FieldAccess(Cow<'static, str>),
-    /// Access to a TupleStruct, Tuple, List or Array element
-    IndexAccess(usize), // TODO: Map access
+    /// Access to a TupleStruct, Tuple, List or Array element, or to a field of the active tuple variant of an enum
+    IndexAccess(usize),
+    /// Access to a Map value by its key
+    MapAccess(Arc<dyn Reflect>),
 }
 
 impl Debug for ReflectionPathElement {
@@ -89,6 +122,7 @@ impl Debug for ReflectionPathElement {
                 .finish(),
             Self::FieldAccess(arg0) => f.debug_tuple("FieldAccess").field(arg0).finish(),
             Self::IndexAccess(arg0) => f.debug_tuple("IndexAccess").field(arg0).finish(),
+            Self::MapAccess(arg0) => f.debug_tuple("MapAccess").field(arg0).finish(),
         }
     }
 }
@@ -110,11 +144,67 @@ impl Display for ReflectionPathElement {
                 f.write_str(&i.to_string())?;
                 f.write_str("]")
             }
+            ReflectionPathElement::MapAccess(key) => write!(f, "[{key:?}]"),
         }
     }
 }
 
 impl ReflectionPathElement {
+    /// Parses a bevy style reflection path such as `translation.x` or `items[2].name` into its elements.
+    ///
+    /// Field names consisting only of digits (as in `.0`) access tuple elements,
+    /// and quoted keys (as in `["name"]`) access values of maps with `String` keys.
+    pub fn parse_path(path: &str) -> Result<Vec<Self>, ReflectionError> {
+        let invalid = |msg: &str| ReflectionError::InvalidReflectionPath {
+            path: path.to_owned(),
+            msg: msg.to_owned(),
+        };
+
+        let mut elements = Vec::default();
+        let mut rest = path.strip_prefix('.').unwrap_or(path);
+        // the first field may omit the leading dot
+        let mut expect_field = !rest.starts_with('[');
+
+        while !rest.is_empty() || expect_field {
+            if expect_field {
+                let end = rest.find(['.', '[', ']']).unwrap_or(rest.len());
+                let (field, tail) = rest.split_at(end);
+                if field.is_empty() {
+                    return Err(invalid("Expected a field name"));
+                }
+                elements.push(match field.parse::<usize>() {
+                    Ok(index) => Self::IndexAccess(index),
+                    Err(_) => Self::FieldAccess(field.to_owned().into()),
+                });
+                rest = tail;
+                expect_field = false;
+            } else if let Some(tail) = rest.strip_prefix('.') {
+                rest = tail;
+                expect_field = true;
+            } else if let Some(tail) = rest.strip_prefix("[\"") {
+                let (key, tail) = tail
+                    .split_once("\"]")
+                    .ok_or_else(|| invalid("Expected a closing `\"]`"))?;
+                elements.push(Self::MapAccess(Arc::new(key.to_owned())));
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix('[') {
+                let (index, tail) = tail
+                    .split_once(']')
+                    .ok_or_else(|| invalid("Expected a closing `]`"))?;
+                let index = index
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("Expected a non-negative integer index"))?;
+                elements.push(Self::IndexAccess(index));
+                rest = tail;
+            } else {
+                return Err(invalid("Expected `.` or `[`"));
+            }
+        }
+
+        Ok(elements)
+    }
+
     pub(crate) fn sub_ref<'a>(
         &self,
         base: &'a dyn Reflect,
@@ -129,6 +219,13 @@ impl ReflectionPathElement {
                             msg: "No such field".to_owned(),
                         })
                 }
+                ReflectRef::Enum(e) => {
+                    e.field(field)
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: format!("Variant `{}` has no such field", e.variant_name()),
+                        })
+                }
                 _ => Err(ReflectionError::InvalidReflectionPath {
                     path: self.to_string(),
                     msg: "No such field".to_owned(),
@@ -163,11 +260,31 @@ impl ReflectionPathElement {
                             msg: "No such element".to_owned(),
                         })
                 }
+                ReflectRef::Enum(e) => {
+                    e.field_at(*index)
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: format!("Variant `{}` has no such element", e.variant_name()),
+                        })
+                }
                 _ => Err(ReflectionError::InvalidReflectionPath {
                     path: self.to_string(),
                     msg: "No such element".to_owned(),
                 }),
             },
+            ReflectionPathElement::MapAccess(key) => match base.reflect_ref() {
+                ReflectRef::Map(m) => {
+                    m.get(key.as_ref())
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: "No such key".to_owned(),
+                        })
+                }
+                _ => Err(ReflectionError::InvalidReflectionPath {
+                    path: self.to_string(),
+                    msg: "No such key".to_owned(),
+                }),
+            },
         }
     }
 
@@ -185,6 +302,14 @@ impl ReflectionPathElement {
                             msg: "No such field".to_owned(),
                         })
                 }
+                ReflectMut::Enum(e) => {
+                    let variant = e.variant_name().to_owned();
+                    e.field_mut(field)
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: format!("Variant `{variant}` has no such field"),
+                        })
+                }
                 _ => Err(ReflectionError::InvalidReflectionPath {
                     path: self.to_string(),
                     msg: "No such field".to_owned(),
@@ -219,11 +344,32 @@ impl ReflectionPathElement {
                             msg: "No such element".to_owned(),
                         })
                 }
+                ReflectMut::Enum(e) => {
+                    let variant = e.variant_name().to_owned();
+                    e.field_at_mut(*index)
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: format!("Variant `{variant}` has no such element"),
+                        })
+                }
                 _ => Err(ReflectionError::InvalidReflectionPath {
                     path: self.to_string(),
                     msg: "No such element".to_owned(),
                 }),
             },
+            ReflectionPathElement::MapAccess(key) => match base.reflect_mut() {
+                ReflectMut::Map(m) => {
+                    m.get_mut(key.as_ref())
+                        .ok_or_else(|| ReflectionError::InvalidReflectionPath {
+                            path: self.to_string(),
+                            msg: "No such key".to_owned(),
+                        })
+                }
+                _ => Err(ReflectionError::InvalidReflectionPath {
+                    path: self.to_string(),
+                    msg: "No such key".to_owned(),
+                }),
+            },
         }
     }
 }
@@ -300,13 +446,56 @@ impl ReflectionPath {
         }
     }
 
+    /// The error for a script owned base whose value has been dropped,
+    /// i.e. a reference to a value from a previous script invocation
+    fn stale_reference(&self) -> ReflectionError {
+        ReflectionError::StaleReference {
+            base: self.base.to_string(),
+        }
+    }
+
+    /// The error for a base which is already borrowed mutably
+    fn borrow_conflict(&self) -> ReflectionError {
+        ReflectionError::BorrowConflict {
+            path: self.to_string(),
+        }
+    }
+
+    /// The error for a world which cannot be accessed, either because the world pointer went out of scope
+    /// (i.e. a reference kept from a previous script invocation) or because the world is already locked
+    fn world_access_error(&self, world_ptr: &WorldPointer) -> ReflectionError {
+        if world_ptr.is_out_of_scope() {
+            self.stale_reference()
+        } else {
+            self.borrow_conflict()
+        }
+    }
+
+    fn read_world<'w>(
+        &self,
+        world_ptr: &'w WorldPointer,
+    ) -> Result<MappedRwLockReadGuard<'w, World>, ReflectionError> {
+        world_ptr
+            .try_read()
+            .ok_or_else(|| self.world_access_error(world_ptr))
+    }
+
+    fn write_world<'w>(
+        &self,
+        world_ptr: &'w WorldPointer,
+    ) -> Result<MappedRwLockWriteGuard<'w, World>, ReflectionError> {
+        world_ptr
+            .try_write()
+            .ok_or_else(|| self.world_access_error(world_ptr))
+    }
+
     pub fn get<O, F>(&self, world_ptr: WorldPointer, f: F) -> Result<O, ReflectionError>
     where
         F: FnOnce(&dyn Reflect) -> O,
     {
         match &self.base {
             ReflectBase::Component { comp, entity } => {
-                let g = world_ptr.read();
+                let g = self.read_world(&world_ptr)?;
 
                 let entity_ref =
                     g.get_entity(*entity)
@@ -323,8 +512,31 @@ impl ReflectionPath {
                 })?)?;
                 Ok(f(ref_))
             }
+            ReflectBase::ScriptComponent {
+                component_id,
+                entity,
+            } => {
+                let g = self.read_world(&world_ptr)?;
+
+                let entity_ref =
+                    g.get_entity(*entity)
+                        .ok_or_else(|| ReflectionError::InvalidBaseReference {
+                            base: self.base.to_string(),
+                            reason: "This entity does not exist".to_owned(),
+                        })?;
+
+                let ptr = entity_ref.get_by_id(*component_id).ok_or_else(|| {
+                    ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "Given component does not exist on this entity".to_owned(),
+                    }
+                })?;
+                // SAFETY: script components are always stored as `DynamicStruct`s
+                let value = unsafe { ptr.deref::<DynamicStruct>() };
+                Ok(f(self.walk_path(value)?))
+            }
             ReflectBase::Resource { res } => {
-                let g = world_ptr.read();
+                let g = self.read_world(&world_ptr)?;
 
                 let ref_ = self.walk_path(res.reflect(&g).ok_or_else(|| {
                     ReflectionError::InvalidBaseReference {
@@ -334,14 +546,28 @@ impl ReflectionPath {
                 })?)?;
                 Ok(f(ref_))
             }
+            ReflectBase::ScriptResource { name } => {
+                let g = self.read_world(&world_ptr)?;
+
+                let resource = g
+                    .get_resource::<ScriptResources>()
+                    .and_then(|resources| resources.get(name))
+                    .ok_or_else(|| ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "Given resource does not exist in this world".to_owned(),
+                    })?;
+                Ok(f(self.walk_path(resource.value())?))
+            }
             ReflectBase::ScriptOwned { val } => {
-                let g = val
-                    .upgrade()
-                    .expect("Trying to access cached value from previous frame");
+                let g = val.upgrade().ok_or_else(|| self.stale_reference())?;
 
-                let g = g.try_read().expect("Rust safety violation: attempted to borrow value {self:?} while it was already mutably borrowed");
+                let g = g.try_read().ok_or_else(|| self.borrow_conflict())?;
                 Ok(f(self.walk_path(&*g)?))
             }
+            ReflectBase::Owned { val } => {
+                let g = val.try_read().ok_or_else(|| self.borrow_conflict())?;
+                Ok(f(self.walk_path(g.as_ref())?))
+            }
         }
     }
 
@@ -351,9 +577,14 @@ impl ReflectionPath {
     {
         match &self.base {
             ReflectBase::Component { comp, entity } => {
-                let mut g = world_ptr.write();
+                let mut g = self.write_world(&world_ptr)?;
 
-                let mut e = g.entity_mut(*entity);
+                let mut e = g.get_entity_mut(*entity).ok_or_else(|| {
+                    ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "This entity does not exist".to_owned(),
+                    }
+                })?;
                 let ref_ = self.walk_path_mut(
                     comp.reflect_mut(&mut e)
                         .ok_or_else(|| ReflectionError::InvalidBaseReference {
@@ -364,8 +595,31 @@ impl ReflectionPath {
                 )?;
                 Ok(f(ref_))
             }
+            ReflectBase::ScriptComponent {
+                component_id,
+                entity,
+            } => {
+                let mut g = self.write_world(&world_ptr)?;
+
+                let mut e = g.get_entity_mut(*entity).ok_or_else(|| {
+                    ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "This entity does not exist".to_owned(),
+                    }
+                })?;
+                let ptr = e
+                    .get_mut_by_id(*component_id)
+                    .ok_or_else(|| ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "Given component does not exist on this entity".to_owned(),
+                    })?
+                    .into_inner();
+                // SAFETY: script components are always stored as `DynamicStruct`s
+                let value = unsafe { ptr.deref_mut::<DynamicStruct>() };
+                Ok(f(self.walk_path_mut(value)?))
+            }
             ReflectBase::Resource { res } => {
-                let mut g = world_ptr.write();
+                let mut g = self.write_world(&world_ptr)?;
 
                 let ref_ = self.walk_path_mut(
                     res.reflect_mut(&mut g)
@@ -377,13 +631,101 @@ impl ReflectionPath {
                 )?;
                 Ok(f(ref_))
             }
+            ReflectBase::ScriptResource { name } => {
+                let mut g = self.write_world(&world_ptr)?;
+
+                let resources = g
+                    .get_resource_mut::<ScriptResources>()
+                    .filter(|resources| resources.contains(name))
+                    .ok_or_else(|| ReflectionError::InvalidBaseReference {
+                        base: self.base.to_string(),
+                        reason: "Given resource does not exist in this world".to_owned(),
+                    })?;
+                let resource = resources.into_inner().get_mut(name).expect("Checked above");
+                Ok(f(self.walk_path_mut(resource.value_mut())?))
+            }
             ReflectBase::ScriptOwned { val } => {
-                let g = val
-                    .upgrade()
-                    .expect("Trying to access cached value from previous frame");
-                let mut g = g.try_write().expect("Rust safety violation: attempted to borrow value {self:?} while it was already mutably borrowed");
+                let g = val.upgrade().ok_or_else(|| self.stale_reference())?;
+                let mut g = g.try_write().ok_or_else(|| self.borrow_conflict())?;
                 Ok(f(self.walk_path_mut(&mut *g)?))
             }
+            ReflectBase::Owned { val } => {
+                let mut g = val.try_write().ok_or_else(|| self.borrow_conflict())?;
+                Ok(f(self.walk_path_mut(g.as_mut())?))
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashMap;
+
+    use super::*;
+    use ReflectionPathElement as E;
+
+    fn assert_invalid(path: &str) {
+        assert!(
+            matches!(
+                E::parse_path(path),
+                Err(ReflectionError::InvalidReflectionPath { .. })
+            ),
+            "expected `{path}` to be rejected"
+        );
+    }
+
+    #[test]
+    fn parses_fields_indices_and_keys() {
+        let elements = E::parse_path("a.b[3][\"key\"].0").unwrap();
+
+        assert_eq!(elements.len(), 5);
+        assert!(matches!(&elements[0], E::FieldAccess(f) if f == "a"));
+        assert!(matches!(&elements[1], E::FieldAccess(f) if f == "b"));
+        assert!(matches!(&elements[2], E::IndexAccess(3)));
+        assert!(
+            matches!(&elements[3], E::MapAccess(k) if k.downcast_ref::<String>().is_some_and(|k| k == "key"))
+        );
+        assert!(matches!(&elements[4], E::IndexAccess(0)));
+    }
+
+    #[test]
+    fn leading_dots_and_brackets_are_optional() {
+        assert_eq!(E::parse_path(".a.b").unwrap().len(), 2);
+        assert!(matches!(
+            E::parse_path("[1].a").unwrap()[..],
+            [E::IndexAccess(1), E::FieldAccess(_)]
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_brackets() {
+        assert_invalid("a[3");
+        assert_invalid("a[x]");
+        assert_invalid("a[-1]");
+        assert_invalid("a[]");
+        assert_invalid("a]");
+        assert_invalid("a[\"key\"");
+        assert_invalid("a[\"key]");
+        assert_invalid("a[3]b");
+    }
+
+    #[test]
+    fn rejects_empty_segments() {
+        assert_invalid("");
+        assert_invalid(".");
+        assert_invalid("a..b");
+        assert_invalid("a.");
+        assert_invalid("a.[3]");
+    }
+
+    #[test]
+    fn keys_access_string_maps() {
+        let map = HashMap::from([("key".to_owned(), 4u32)]);
+        let elements = E::parse_path("[\"key\"]").unwrap();
+        let value = elements[0].sub_ref(&map).unwrap();
+        assert_eq!(value.downcast_ref::<u32>(), Some(&4));
+
+        let elements = E::parse_path("[\"other\"]").unwrap();
+        assert!(elements[0].sub_ref(&map).is_err());
+    }
+}
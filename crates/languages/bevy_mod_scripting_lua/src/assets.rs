@@ -1,9 +1,23 @@
 use bevy::{
-    asset::{io::Reader, Asset, AssetLoader, AsyncReadExt},
+    asset::{
+        io::{Reader, Writer},
+        processor::LoadAndSave,
+        saver::{AssetSaver, SavedAsset},
+        Asset, AssetLoader, AsyncReadExt, AsyncWriteExt,
+    },
     reflect::TypePath,
     utils::BoxedFuture,
 };
 use bevy_mod_scripting_core::asset::CodeAsset;
+use serde::{Deserialize, Serialize};
+use tealr::mlu::mlua::{ffi::LUA_SIGNATURE, Lua};
+
+#[cfg(feature = "teal")]
+use crate::teal::TealCompiler;
+#[cfg(feature = "teal")]
+use bevy::asset::io::file::FileAssetReader;
+#[cfg(feature = "teal")]
+use std::{path::PathBuf, sync::OnceLock};
 
 use anyhow::Error;
 
@@ -11,146 +25,211 @@ use anyhow::Error;
 /// A lua code file in bytes
 pub struct LuaFile {
     pub bytes: Vec<u8>,
+    /// the name given to this chunk when it's compiled to bytecode
+    pub chunk_name: String,
 }
 
 impl CodeAsset for LuaFile {
     fn bytes(&self) -> &[u8] {
         self.bytes.as_slice()
     }
+
+    #[cfg(feature = "teal")]
+    fn extensions() -> &'static [&'static str] {
+        &["lua", "tl"]
+    }
+    #[cfg(not(feature = "teal"))]
+    fn extensions() -> &'static [&'static str] {
+        &["lua"]
+    }
+}
+
+impl LuaFile {
+    /// Returns true if this file contains a precompiled Lua chunk rather than source code
+    pub fn is_binary(&self) -> bool {
+        self.bytes.starts_with(LUA_SIGNATURE)
+    }
+}
+
+/// Whether Lua scripts are stored as source code or precompiled bytecode
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LuaChunkMode {
+    /// scripts are stored as source code and compiled when their context is created
+    #[default]
+    Text,
+    /// scripts are compiled to bytecode as they're loaded, files which already contain bytecode are kept as is
+    Binary,
+}
+
+/// Settings for the [`LuaLoader`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LuaLoaderSettings {
+    /// the chunk name embedded in compiled chunks, shown in error messages and stack traces.
+    /// Defaults to the asset path, scripts loaded as text are named after their script instead
+    pub chunk_name: Option<String>,
+    /// whether the script is kept as source code or compiled to bytecode
+    pub mode: LuaChunkMode,
+    /// strips debug information such as line numbers and local variable names from compiled chunks
+    pub strip_debug: bool,
+}
+
+/// Compiles Lua source code to bytecode, bytecode is returned unchanged
+pub fn compile_lua_chunk(
+    bytes: &[u8],
+    chunk_name: &str,
+    strip_debug: bool,
+) -> Result<Vec<u8>, Error> {
+    if bytes.starts_with(LUA_SIGNATURE) {
+        return Ok(bytes.to_vec());
+    }
+
+    let lua = Lua::new();
+    let function = lua.load(bytes).set_name(chunk_name).into_function()?;
+    Ok(function.dump(strip_debug))
 }
 
-#[derive(Default)]
 /// Asset loader for lua scripts
-pub struct LuaLoader;
-
-fn old_lua_load<'a>(
-    bytes: &'a [u8],
-    load_context: &'a mut bevy::asset::LoadContext,
-) -> BoxedFuture<'a, Result<Vec<u8>, Error>> {
-    match load_context.path().extension().map(|s| s.to_str().unwrap()) {
-        #[cfg(all(feature = "teal", debug_assertions))]
-        Some("tl") => {
-            use bevy::asset::io::file::FileAssetReader;
-            use std::fs;
-            use std::path::PathBuf;
-            use std::process::Command;
-
-            let scripts_dir = &FileAssetReader::get_base_path()
-                .join("assets")
-                .join("scripts");
-
-            let temp_file_path = &std::env::temp_dir().join("bevy_mod_scripting.temp.lua");
-            bevy::prelude::info!("tl file path {}", scripts_dir.to_str().unwrap());
-            // optionally put the output in the /build folder
-            let build_dir_path: Option<PathBuf> =
-                if load_context.path().starts_with("scripts/build/") {
-                    Some(
-                        load_context
-                            .path()
-                            .strip_prefix("scripts/")
-                            .unwrap()
-                            .to_owned(),
-                    )
-                } else if load_context.path().starts_with("scripts/") {
-                    Some(
-                        PathBuf::from("build/")
-                            .join(load_context.path().strip_prefix("scripts/").unwrap()),
-                    )
-                } else {
-                    None
-                };
-
-            let full_path = &FileAssetReader::get_base_path()
-                .join("assets")
-                .join(load_context.path());
-            bevy::log::info!(
-                "tl check {} : {}",
-                full_path.to_str().unwrap(),
-                scripts_dir.to_str().unwrap()
-            );
-            if let Ok(e) = Command::new("tl")
-                .args(["check", full_path.to_str().unwrap()])
-                .current_dir(scripts_dir)
-                .status()
-            {
-                if !e.success() {
-                    return Box::pin(async move {
-                        Err(Error::msg(format!(
-                            "Teal file `{}` has errors",
-                            load_context.path().to_str().unwrap()
-                        )))
-                    });
-                }
-            } else {
-                fs::remove_file(temp_file_path).expect("Something went wrong running `tl check`");
-                panic!("Something went wrong running `tl check`");
-            }
+///
+/// With the `teal` feature enabled `.tl` files are type checked and compiled to Lua as they're loaded,
+/// using [`TealCompiler::with_default_layout`] for the asset directory unless a compiler is given via [`LuaLoader::with_teal_compiler`].
+/// Files which were precompiled by the [`LuaBytecodeProcessor`] are loaded as is, so processed builds do not need the teal compiler.
+pub struct LuaLoader {
+    #[cfg(feature = "teal")]
+    teal: OnceLock<Result<TealCompiler, String>>,
+    /// the directory scripts are loaded from, used to resolve teal modules and declarations
+    #[cfg(feature = "teal")]
+    asset_dir: PathBuf,
+}
 
-            if let Ok(e) = Command::new("tl")
-                .args([
-                    "gen",
-                    full_path.to_str().unwrap(),
-                    "-o",
-                    temp_file_path.to_str().unwrap(),
-                ])
-                .current_dir(scripts_dir)
-                .status()
-            {
-                if !e.success() {
-                    return Box::pin(async move {
-                        Err(Error::msg(format!(
-                            "Teal file `{}` could not be compiled!",
-                            load_context.path().to_str().unwrap()
-                        )))
-                    });
-                }
-            } else {
-                fs::remove_file(temp_file_path).expect("Something went wrong running `tl gen`");
-                panic!("Something went wrong running `tl gen`")
-            }
+impl Default for LuaLoader {
+    fn default() -> Self {
+        Self::with_asset_dir(bevy::asset::AssetPlugin::default().file_path)
+    }
+}
 
-            if let Some(mut build_dir_path) = build_dir_path {
-                build_dir_path = scripts_dir.join(build_dir_path);
-                let _ = fs::create_dir_all(build_dir_path.parent().unwrap());
-                let _ = fs::copy(temp_file_path, build_dir_path.with_extension("lua"));
-            }
+impl LuaLoader {
+    /// Creates a loader for the asset directory configured on the app's [`bevy::asset::AssetPlugin`],
+    /// or the default one if the plugin was not added yet
+    pub fn from_app(app: &bevy::app::App) -> Self {
+        match app.get_added_plugins::<bevy::asset::AssetPlugin>().first() {
+            Some(plugin) => Self::with_asset_dir(&plugin.file_path),
+            None => Self::default(),
+        }
+    }
 
-            let lua_code =
-                fs::read_to_string(temp_file_path).expect("Could not find output lua file");
-            fs::remove_file(temp_file_path).unwrap();
+    /// Creates a loader for the given asset directory, relative paths are resolved against the base path bevy loads assets from
+    #[cfg_attr(not(feature = "teal"), allow(unused_variables))]
+    pub fn with_asset_dir(dir: impl AsRef<std::path::Path>) -> Self {
+        Self {
+            #[cfg(feature = "teal")]
+            teal: Default::default(),
+            #[cfg(feature = "teal")]
+            asset_dir: FileAssetReader::get_base_path().join(dir),
+        }
+    }
 
-            Box::pin(async move { Ok(lua_code.as_bytes().into()) })
+    /// Creates a loader compiling teal files with the given compiler
+    #[cfg(feature = "teal")]
+    pub fn with_teal_compiler(compiler: TealCompiler) -> Self {
+        Self {
+            teal: OnceLock::from(Ok(compiler)),
+            ..Default::default()
         }
+    }
 
-        _ => Box::pin(async move { Ok(bytes.into()) }),
+    /// The teal compiler used by this loader, initialized on first use
+    #[cfg(feature = "teal")]
+    fn teal(&self) -> Result<&TealCompiler, Error> {
+        self.teal
+            .get_or_init(|| {
+                TealCompiler::with_default_layout(&self.asset_dir).map_err(|e| e.to_string())
+            })
+            .as_ref()
+            .map_err(|e| Error::msg(e.clone()))
     }
 }
+
 impl AssetLoader for LuaLoader {
     type Asset = LuaFile;
-    type Settings = ();
+    type Settings = LuaLoaderSettings;
     type Error = Error;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader, //bytes: &'a [u8],
-        _settings: &'a (),
+        settings: &'a LuaLoaderSettings,
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::asset::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         bevy::prelude::info!("lua loader invoked: {:#}", load_context.asset_path());
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let bytes = old_lua_load(bytes.as_slice(), load_context).await?;
-            Ok(LuaFile { bytes }) //})
+
+            let chunk_name = match &settings.chunk_name {
+                Some(name) => name.clone(),
+                None => load_context.asset_path().to_string(),
+            };
+
+            // precompiled chunks (e.g. processed assets) need no further preparation
+            #[cfg(feature = "teal")]
+            if load_context
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "tl")
+                && !bytes.starts_with(LUA_SIGNATURE)
+            {
+                let source = std::str::from_utf8(&bytes)?;
+                bytes = self.teal()?.compile(source, &chunk_name)?.into_bytes();
+            }
+            if settings.mode == LuaChunkMode::Binary {
+                bytes = compile_lua_chunk(&bytes, &chunk_name, settings.strip_debug)?;
+            }
+
+            Ok(LuaFile { bytes, chunk_name })
         })
     }
 
-    #[cfg(feature = "teal")]
     fn extensions(&self) -> &[&str] {
-        &["lua", "tl"]
+        LuaFile::extensions()
     }
-    #[cfg(not(feature = "teal"))]
-    fn extensions(&self) -> &[&str] {
-        &["lua"]
+}
+
+/// Settings for the [`LuaBytecodeSaver`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LuaBytecodeSaverSettings {
+    /// strips debug information such as line numbers and local variable names from compiled chunks
+    pub strip_debug: bool,
+}
+
+#[derive(Default)]
+/// Asset saver writing Lua scripts as precompiled bytecode
+pub struct LuaBytecodeSaver;
+
+impl AssetSaver for LuaBytecodeSaver {
+    type Asset = LuaFile;
+    type Settings = LuaBytecodeSaverSettings;
+    type OutputLoader = LuaLoader;
+    type Error = Error;
+
+    fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        settings: &'a Self::Settings,
+    ) -> BoxedFuture<'a, Result<LuaLoaderSettings, Self::Error>> {
+        Box::pin(async move {
+            let bytes = compile_lua_chunk(&asset.bytes, &asset.chunk_name, settings.strip_debug)?;
+            writer.write_all(&bytes).await?;
+
+            Ok(LuaLoaderSettings {
+                chunk_name: Some(asset.chunk_name.clone()),
+                mode: LuaChunkMode::Binary,
+                strip_debug: settings.strip_debug,
+            })
+        })
     }
 }
+
+/// Asset processor precompiling Lua scripts to bytecode, used by default for `.lua` files
+/// when assets are processed (see [`bevy::asset::AssetMode::Processed`])
+pub type LuaBytecodeProcessor = LoadAndSave<LuaLoader, LuaBytecodeSaver>;
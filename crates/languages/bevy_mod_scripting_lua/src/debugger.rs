@@ -0,0 +1,849 @@
+//! A [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/) server for Lua scripts.
+//!
+//! Add the [`LuaDebuggerPlugin`] to your app (after registering the [`LuaScriptHost`]) and attach any DAP capable editor
+//! to the configured address, for VSCode this means an `attach` configuration pointing at a generic DAP client.
+//!
+//! Breakpoints are resolved against script names, i.e. a breakpoint set in `/my/game/assets/scripts/player.lua`
+//! will be hit by a script named `scripts/player.lua`.
+//!
+//! While a script is stopped the whole event handling pass is blocked, the rest of the frame
+//! continues once execution is resumed from the editor. Scripts only run with a line hook installed
+//! while breakpoints are set or a pause or step is pending.
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_mod_scripting_core::{prelude::*, world::WorldPointer};
+use parking_lot::Mutex;
+use serde_json::{json, Value as Json};
+use tealr::mlu::mlua::{
+    self, ffi, Debug as LuaDebug, DebugEvent, Function, HookTriggers, Lua, Table, Value,
+};
+
+use crate::{docs::LuaDocFragment, LuaArg, LuaScriptHost};
+
+/// The thread id reported to the client, all Lua states run on the thread handling script events
+const THREAD_ID: i64 = 1;
+
+/// How often a stopped script checks whether the client went away
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Starts a Debug Adapter Protocol server on the given address and installs debug hooks in the Lua scripts
+/// run by the [`LuaScriptHost`] with the given argument type whenever the client needs them.
+///
+/// Must be added after the script host itself.
+pub struct LuaDebuggerPlugin<A: LuaArg> {
+    /// the address the server listens on, should normally be a loopback address
+    pub address: SocketAddr,
+    _ph: std::marker::PhantomData<A>,
+}
+
+impl<A: LuaArg> LuaDebuggerPlugin<A> {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            _ph: Default::default(),
+        }
+    }
+}
+
+impl<A: LuaArg> Default for LuaDebuggerPlugin<A> {
+    fn default() -> Self {
+        Self::new(SocketAddr::from(([127, 0, 0, 1], 4711)))
+    }
+}
+
+impl<A: LuaArg> Plugin for LuaDebuggerPlugin<A> {
+    fn build(&self, app: &mut App) {
+        let debugger = match LuaDebugger::listen(self.address) {
+            Ok(debugger) => debugger,
+            Err(e) => {
+                error!(
+                    "Could not start Lua debug server on {}: {}",
+                    self.address, e
+                );
+                return;
+            }
+        };
+
+        info!("Lua debug server listening on {}", self.address);
+        app.insert_resource(debugger.clone())
+            .add_api_provider::<LuaScriptHost<A>>(Box::new(LuaDebuggerAPIProvider { debugger }));
+    }
+}
+
+/// Handle to a running debug server
+#[derive(Resource, Clone)]
+pub struct LuaDebugger {
+    shared: Arc<Shared>,
+}
+
+impl LuaDebugger {
+    /// Binds the server to the given address and starts accepting clients on a background thread
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let (sender, receiver) = channel();
+        let shared = Arc::new(Shared::new(receiver));
+
+        let server = shared.clone();
+        thread::Builder::new()
+            .name("lua debug server".to_owned())
+            .spawn(move || serve(listener, server, sender))?;
+
+        Ok(Self { shared })
+    }
+
+    /// Whether a client is currently attached
+    pub fn is_connected(&self) -> bool {
+        self.shared.connected.load(Ordering::Acquire)
+    }
+
+    /// Whether a script is currently stopped
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::Acquire)
+    }
+
+    /// Stops at the next executed line of any script, provided a client is attached
+    pub fn pause(&self) {
+        self.shared.pause_requested.store(true, Ordering::Release);
+    }
+}
+
+/// Installs the debug hook in script contexts while the client needs it, i.e. while breakpoints are set
+/// or a pause or step is pending. Otherwise scripts run without any hook overhead.
+pub struct LuaDebuggerAPIProvider {
+    debugger: LuaDebugger,
+}
+
+impl APIProvider for LuaDebuggerAPIProvider {
+    type APITarget = std::sync::Mutex<Lua>;
+    type ScriptContext = std::sync::Mutex<Lua>;
+    type DocTarget = LuaDocFragment;
+
+    fn attach_api(&mut self, _: &mut Self::APITarget) -> Result<(), ScriptError> {
+        Ok(())
+    }
+
+    fn setup_script_runtime(
+        &mut self,
+        _: WorldPointer,
+        _: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+    ) -> Result<(), ScriptError> {
+        let ctx = ctx
+            .get_mut()
+            .expect("Unable to acquire lock on Lua context");
+        if self.debugger.shared.needs_line_hook() {
+            let shared = self.debugger.shared.clone();
+            ctx.set_hook(HookTriggers::EVERY_LINE, move |lua, debug| {
+                shared.on_hook(lua, debug);
+                Ok(())
+            });
+        } else {
+            ctx.remove_hook();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunMode {
+    Continue,
+    StepIn,
+    StepOver { depth: usize },
+    StepOut { depth: usize },
+}
+
+/// State shared between the server thread and the debug hooks
+struct Shared {
+    seq: AtomicI64,
+    client: Mutex<Option<TcpStream>>,
+    connected: AtomicBool,
+    paused: AtomicBool,
+    pause_requested: AtomicBool,
+    /// breakpoint lines for each source path as sent by the client
+    breakpoints: Mutex<HashMap<String, Vec<i64>>>,
+    run_mode: Mutex<RunMode>,
+    /// requests which can only be answered by a stopped script
+    commands: Mutex<Receiver<Json>>,
+}
+
+impl Shared {
+    fn new(commands: Receiver<Json>) -> Self {
+        Self {
+            seq: AtomicI64::new(1),
+            client: Default::default(),
+            connected: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            pause_requested: AtomicBool::new(false),
+            breakpoints: Default::default(),
+            run_mode: Mutex::new(RunMode::Continue),
+            commands: Mutex::new(commands),
+        }
+    }
+
+    /// Whether scripts need to report every executed line, i.e. a client is attached
+    /// and has set breakpoints or is waiting for a pause or step to finish
+    fn needs_line_hook(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+            && (self.pause_requested.load(Ordering::Acquire)
+                || *self.run_mode.lock() != RunMode::Continue
+                || self
+                    .breakpoints
+                    .lock()
+                    .values()
+                    .any(|lines| !lines.is_empty()))
+    }
+
+    fn send(&self, mut message: Json) {
+        message["seq"] = self.seq.fetch_add(1, Ordering::Relaxed).into();
+        if let Some(client) = self.client.lock().as_mut() {
+            if let Err(e) = write_message(client, &message) {
+                warn!("Failed to send message to Lua debugger client: {}", e);
+            }
+        }
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn respond(&self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn respond_error(&self, request: &Json, message: impl ToString) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message.to_string(),
+        }));
+    }
+
+    /// The source path known to the client corresponding to the given chunk name
+    fn client_path(&self, chunk: &str) -> Option<String> {
+        self.breakpoints
+            .lock()
+            .keys()
+            .find(|path| source_matches(path, chunk))
+            .cloned()
+    }
+
+    fn is_breakpoint(&self, chunk: &str, line: i64) -> bool {
+        self.breakpoints
+            .lock()
+            .iter()
+            .any(|(path, lines)| lines.contains(&line) && source_matches(path, chunk))
+    }
+
+    fn on_hook(&self, lua: &Lua, debug: LuaDebug) {
+        if !self.connected.load(Ordering::Acquire) || debug.event() != DebugEvent::Line {
+            return;
+        }
+
+        let line = debug.curr_line() as i64;
+        let chunk = match debug.source().source {
+            Some(source) => source.into_owned(),
+            None => return,
+        };
+
+        let step_finished = match *self.run_mode.lock() {
+            RunMode::Continue => false,
+            RunMode::StepIn => true,
+            RunMode::StepOver { depth } => stack_depth(lua) <= depth,
+            RunMode::StepOut { depth } => stack_depth(lua) < depth,
+        };
+
+        let reason = if self.pause_requested.swap(false, Ordering::AcqRel) {
+            "pause"
+        } else if step_finished {
+            "step"
+        } else if self.is_breakpoint(&chunk, line) {
+            "breakpoint"
+        } else {
+            return;
+        };
+
+        *self.run_mode.lock() = RunMode::Continue;
+        self.stop(lua, reason);
+    }
+
+    /// Blocks the calling script until the client resumes execution, answering any
+    /// requests which need access to the stopped Lua state in the meantime
+    fn stop(&self, lua: &Lua, reason: &str) {
+        let commands = self.commands.lock();
+
+        // anything still queued was meant for a previous stop
+        while let Ok(request) = commands.try_recv() {
+            if request.get("seq").is_some() {
+                self.respond_error(
+                    &request,
+                    "Execution was resumed before the request was handled",
+                );
+            }
+        }
+
+        self.paused.store(true, Ordering::Release);
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+
+        let mut session = StoppedSession {
+            lua,
+            shared: self,
+            handles: Vec::new(),
+        };
+
+        loop {
+            let request = match commands.recv_timeout(POLL_INTERVAL) {
+                Ok(request) => request,
+                Err(RecvTimeoutError::Timeout) if self.connected.load(Ordering::Acquire) => {
+                    continue
+                }
+                Err(_) => break,
+            };
+
+            let resume_with = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Some(RunMode::Continue),
+                "next" => Some(RunMode::StepOver {
+                    depth: stack_depth(lua),
+                }),
+                "stepIn" => Some(RunMode::StepIn),
+                "stepOut" => Some(RunMode::StepOut {
+                    depth: stack_depth(lua),
+                }),
+                "disconnect" => break,
+                _ => {
+                    session.handle(&request);
+                    None
+                }
+            };
+
+            if let Some(mode) = resume_with {
+                *self.run_mode.lock() = mode;
+                self.respond(&request, json!({ "allThreadsContinued": true }));
+                break;
+            }
+        }
+
+        self.paused.store(false, Ordering::Release);
+    }
+
+    fn disconnect(&self) {
+        self.connected.store(false, Ordering::Release);
+        self.client.lock().take();
+        self.breakpoints.lock().clear();
+        self.pause_requested.store(false, Ordering::Release);
+        *self.run_mode.lock() = RunMode::Continue;
+    }
+}
+
+/// A value which can be expanded by the client through a variables reference
+enum VariableHandle<'lua> {
+    Locals(usize),
+    Globals,
+    Table(Table<'lua>),
+}
+
+/// Answers inspection requests while a script is stopped,
+/// variable references are only valid until the script resumes
+struct StoppedSession<'a, 'lua> {
+    lua: &'lua Lua,
+    shared: &'a Shared,
+    handles: Vec<VariableHandle<'lua>>,
+}
+
+impl<'a, 'lua> StoppedSession<'a, 'lua> {
+    fn handle(&mut self, request: &Json) {
+        let arguments = &request["arguments"];
+        let body = match request["command"].as_str().unwrap_or_default() {
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(self.scopes(arguments["frameId"].as_u64().unwrap_or(0) as usize)),
+            "variables" => self.variables(arguments["variablesReference"].as_u64().unwrap_or(0)),
+            "evaluate" => self.evaluate(
+                arguments["expression"].as_str().unwrap_or_default(),
+                arguments["frameId"].as_u64().map(|f| f as usize),
+            ),
+            command => Err(mlua::Error::RuntimeError(format!(
+                "Unsupported request `{command}`"
+            ))),
+        };
+
+        match body {
+            Ok(body) => self.shared.respond(request, body),
+            Err(e) => self.shared.respond_error(request, e),
+        }
+    }
+
+    fn stack_trace(&self) -> Json {
+        let mut frames = Vec::new();
+        let mut level = 0;
+        while let Some(frame) = self.lua.inspect_stack(level) {
+            let source = frame.source();
+            let name = frame
+                .names()
+                .name
+                .map(|n| n.into_owned())
+                .unwrap_or_else(|| match source.what {
+                    "main" => "main chunk".to_owned(),
+                    _ => "?".to_owned(),
+                });
+            let mut stack_frame = json!({
+                "id": level,
+                "name": name,
+                "line": frame.curr_line().max(0),
+                "column": 1,
+            });
+
+            if let (Some(chunk), "Lua" | "main") = (source.source, source.what) {
+                let path = self
+                    .shared
+                    .client_path(&chunk)
+                    .unwrap_or_else(|| chunk_name(&chunk).to_owned());
+                stack_frame["source"] = json!({ "name": chunk_name(&chunk), "path": path });
+            }
+
+            frames.push(stack_frame);
+            level += 1;
+        }
+
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    fn scopes(&mut self, frame: usize) -> Json {
+        let locals = self.handle_for(VariableHandle::Locals(frame));
+        let globals = self.handle_for(VariableHandle::Globals);
+        json!({ "scopes": [
+            { "name": "Locals", "variablesReference": locals, "expensive": false },
+            { "name": "Globals", "variablesReference": globals, "expensive": true },
+        ]})
+    }
+
+    fn variables(&mut self, reference: u64) -> mlua::Result<Json> {
+        let entries = match reference
+            .checked_sub(1)
+            .and_then(|i| self.handles.get(i as usize))
+        {
+            Some(VariableHandle::Locals(level)) => locals(self.lua, *level)?,
+            Some(VariableHandle::Globals) => table_entries(self.lua.globals())?,
+            Some(VariableHandle::Table(table)) => table_entries(table.clone())?,
+            None => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "Invalid variables reference {reference}"
+                )))
+            }
+        };
+
+        let mut variables = Vec::with_capacity(entries.len());
+        for (name, value) in entries {
+            let (value, type_name, reference) = self.describe(value)?;
+            variables.push(json!({
+                "name": name,
+                "value": value,
+                "type": type_name,
+                "variablesReference": reference,
+            }));
+        }
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn evaluate(&mut self, expression: &str, frame: Option<usize>) -> mlua::Result<Json> {
+        let env = self.lua.create_table()?;
+        let meta = self.lua.create_table()?;
+        meta.raw_set("__index", self.lua.globals())?;
+        env.set_metatable(Some(meta));
+        if let Some(level) = frame {
+            for (name, value) in locals(self.lua, level)? {
+                env.raw_set(name, value)?;
+            }
+        }
+
+        // try compiling as an expression first, fall back to a statement if that is not valid syntax.
+        // Only compilation is retried, so the code never runs twice
+        let chunk = match self
+            .lua
+            .load(format!("return {expression}"))
+            .set_name("=evaluate")
+            .set_environment(env.clone())
+            .into_function()
+        {
+            Err(mlua::Error::SyntaxError { .. }) => self
+                .lua
+                .load(expression)
+                .set_name("=evaluate")
+                .set_environment(env)
+                .into_function()?,
+            chunk => chunk?,
+        };
+        let value = chunk.call::<_, Value>(())?;
+
+        let (result, type_name, reference) = self.describe(value)?;
+        Ok(json!({ "result": result, "type": type_name, "variablesReference": reference }))
+    }
+
+    fn handle_for(&mut self, handle: VariableHandle<'lua>) -> usize {
+        self.handles.push(handle);
+        self.handles.len()
+    }
+
+    /// Returns the display string, type and variables reference of the given value
+    fn describe(&mut self, value: Value<'lua>) -> mlua::Result<(String, &'static str, usize)> {
+        let type_name = value.type_name();
+        Ok(match value {
+            Value::String(s) => (format!("{:?}", s.to_string_lossy()), type_name, 0),
+            Value::Table(t) => {
+                let display = display_value(self.lua, Value::Table(t.clone()))?;
+                (
+                    display,
+                    type_name,
+                    self.handle_for(VariableHandle::Table(t)),
+                )
+            }
+            v => (display_value(self.lua, v)?, type_name, 0),
+        })
+    }
+}
+
+fn display_value<'lua>(lua: &'lua Lua, value: Value<'lua>) -> mlua::Result<String> {
+    let type_name = value.type_name();
+    match lua.globals().raw_get::<_, Option<Function>>("tostring")? {
+        Some(tostring) => tostring.call(value),
+        None => Ok(type_name.to_owned()),
+    }
+}
+
+fn table_entries(table: Table<'_>) -> mlua::Result<Vec<(String, Value<'_>)>> {
+    let mut entries = table
+        .pairs::<Value, Value>()
+        .map(|pair| {
+            let (key, value) = pair?;
+            let key = match key {
+                Value::String(s) => s.to_string_lossy().into_owned(),
+                Value::Integer(i) => format!("[{i}]"),
+                Value::Number(n) => format!("[{n}]"),
+                k => format!("[{}]", k.type_name()),
+            };
+            Ok((key, value))
+        })
+        .collect::<mlua::Result<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(entries)
+}
+
+/// Reads the local variables of the function at the given stack level (as seen from the hook)
+fn locals(lua: &Lua, level: usize) -> mlua::Result<Vec<(String, Value<'_>)>> {
+    // safety: the function only reads the stack of the state it is called from and
+    // pushes at most two values, which it reports back to Lua
+    let get_local = unsafe { lua.create_c_function(get_local)? };
+    let mut locals = Vec::new();
+    for n in 1.. {
+        let (name, value) = get_local.call::<_, (Option<String>, Value)>((level, n))?;
+        match name {
+            // temporaries and internal loop state are reported in parentheses
+            Some(name) if name.starts_with('(') => continue,
+            Some(name) => locals.push((name, value)),
+            None => break,
+        }
+    }
+    Ok(locals)
+}
+
+/// `get_local(level, n)` returns the name and value of the n'th local of the function at `level`,
+/// where level 0 is the function which called the caller of `get_local`
+unsafe extern "C-unwind" fn get_local(state: *mut ffi::lua_State) -> c_int {
+    let level = ffi::luaL_checkinteger(state, 1) as c_int;
+    let n = ffi::luaL_checkinteger(state, 2) as c_int;
+    let mut ar: ffi::lua_Debug = std::mem::zeroed();
+    // level 0 is this function itself
+    if ffi::lua_getstack(state, level + 1, &mut ar) == 0 {
+        return 0;
+    }
+    let name = ffi::lua_getlocal(state, &ar, n);
+    if name.is_null() {
+        return 0;
+    }
+    // stack: value -> name, value
+    ffi::lua_pushstring(state, name);
+    ffi::lua_insert(state, -2);
+    2
+}
+
+fn stack_depth(lua: &Lua) -> usize {
+    let mut depth = 0;
+    while lua.inspect_stack(depth).is_some() {
+        depth += 1;
+    }
+    depth
+}
+
+/// Strips the Lua chunk name prefixes denoting file and literal sources
+fn chunk_name(chunk: &str) -> &str {
+    chunk.trim_start_matches(['@', '='])
+}
+
+/// Checks if the path sent by the client refers to the given chunk, i.e. whether
+/// the script name is a suffix of the path
+fn source_matches(path: &str, chunk: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let chunk = chunk_name(chunk).replace('\\', "/");
+    path == chunk || path.ends_with(&format!("/{chunk}"))
+}
+
+fn serve(listener: TcpListener, shared: Arc<Shared>, commands: Sender<Json>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Lua debugger failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        match stream.try_clone() {
+            Ok(writer) => *shared.client.lock() = Some(writer),
+            Err(e) => {
+                warn!("Lua debugger failed to accept connection: {}", e);
+                continue;
+            }
+        }
+        info!("Lua debugger attached from {:?}", stream.peer_addr());
+        shared.connected.store(true, Ordering::Release);
+
+        let mut reader = BufReader::new(stream);
+        loop {
+            match read_message(&mut reader) {
+                Ok(Some(request)) => {
+                    if !handle_request(&shared, &commands, request) {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Lua debugger connection error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        shared.disconnect();
+        // wake up any stopped script
+        let _ = commands.send(json!({ "command": "disconnect" }));
+        info!("Lua debugger detached");
+    }
+}
+
+/// Handles a single request from the client, returns false if the client wishes to disconnect
+fn handle_request(shared: &Shared, commands: &Sender<Json>, request: Json) -> bool {
+    let arguments = &request["arguments"];
+    match request["command"].as_str().unwrap_or_default() {
+        "initialize" => {
+            shared.respond(
+                &request,
+                json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                }),
+            );
+            shared.event("initialized", json!({}));
+        }
+        "setBreakpoints" => {
+            let source = &arguments["source"];
+            let path = source["path"]
+                .as_str()
+                .or_else(|| source["name"].as_str())
+                .unwrap_or_default()
+                .to_owned();
+            let lines = arguments["breakpoints"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|b| b["line"].as_i64())
+                .collect::<Vec<_>>();
+            let breakpoints = lines
+                .iter()
+                .map(|line| json!({ "verified": true, "line": line }))
+                .collect::<Vec<_>>();
+            shared.breakpoints.lock().insert(path, lines);
+            shared.respond(&request, json!({ "breakpoints": breakpoints }));
+        }
+        "threads" => shared.respond(
+            &request,
+            json!({ "threads": [{ "id": THREAD_ID, "name": "Lua" }] }),
+        ),
+        "pause" => {
+            shared.pause_requested.store(true, Ordering::Release);
+            shared.respond(&request, json!({}));
+        }
+        "disconnect" | "terminate" => {
+            shared.respond(&request, json!({}));
+            return false;
+        }
+        "continue" | "next" | "stepIn" | "stepOut" | "stackTrace" | "scopes" | "variables"
+        | "evaluate" => {
+            if shared.paused.load(Ordering::Acquire) {
+                // answered by the stopped script
+                let _ = commands.send(request);
+            } else if request["command"] == "continue" {
+                shared.respond(&request, json!({ "allThreadsContinued": true }));
+            } else {
+                shared.respond_error(&request, "No script is currently stopped");
+            }
+        }
+        // attach, launch, configurationDone, setExceptionBreakpoints etc.
+        _ => shared.respond(&request, json!({})),
+    }
+    true
+}
+
+/// Reads a single `Content-Length` framed message, returns None once the stream is closed
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let content = serde_json::to_vec(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n", content.len())?;
+    writer.write_all(&content)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session_test(f: impl FnOnce(&mut StoppedSession)) {
+        let lua = Lua::new();
+        let shared = Shared::new(channel().1);
+        let mut session = StoppedSession {
+            lua: &lua,
+            shared: &shared,
+            handles: Vec::new(),
+        };
+        f(&mut session);
+    }
+
+    #[test]
+    fn evaluates_expressions_and_statements() {
+        session_test(|session| {
+            let result = session.evaluate("1 + 2", None).unwrap();
+            assert_eq!(result["result"], "3");
+
+            session.lua.load("t = {}").exec().unwrap();
+            session.evaluate("t.x = 5", None).unwrap();
+            let result = session.evaluate("t.x", None).unwrap();
+            assert_eq!(result["result"], "5");
+        });
+    }
+
+    #[test]
+    fn failing_expressions_run_once() {
+        session_test(|session| {
+            session
+                .lua
+                .load("calls = 0; function f() calls = calls + 1; error('failed') end")
+                .exec()
+                .unwrap();
+
+            assert!(session.evaluate("f()", None).is_err());
+            assert!(session.evaluate("y = f()", None).is_err());
+            let calls: i64 = session.lua.globals().get("calls").unwrap();
+            assert_eq!(calls, 2);
+        });
+    }
+
+    #[test]
+    fn line_hook_only_needed_while_debugging() {
+        let shared = Shared::new(channel().1);
+        shared.pause_requested.store(true, Ordering::Release);
+        assert!(!shared.needs_line_hook(), "no client attached");
+
+        shared.connected.store(true, Ordering::Release);
+        assert!(shared.needs_line_hook());
+
+        shared.pause_requested.store(false, Ordering::Release);
+        assert!(!shared.needs_line_hook());
+
+        shared
+            .breakpoints
+            .lock()
+            .insert("scripts/a.lua".to_owned(), Vec::new());
+        assert!(!shared.needs_line_hook());
+
+        shared
+            .breakpoints
+            .lock()
+            .insert("scripts/a.lua".to_owned(), vec![3]);
+        assert!(shared.needs_line_hook());
+
+        shared.disconnect();
+        *shared.run_mode.lock() = RunMode::StepIn;
+        assert!(!shared.needs_line_hook());
+    }
+
+    #[test]
+    fn sources_match_by_suffix() {
+        assert!(source_matches(
+            "/game/assets/scripts/a.lua",
+            "@scripts/a.lua"
+        ));
+        assert!(source_matches("C:\\game\\scripts\\a.lua", "scripts/a.lua"));
+        assert!(source_matches("scripts/a.lua", "=scripts/a.lua"));
+        assert!(!source_matches("/game/assets/scripts/ba.lua", "a.lua"));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let message = json!({ "seq": 1, "type": "request", "command": "threads" });
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &message).unwrap();
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+}
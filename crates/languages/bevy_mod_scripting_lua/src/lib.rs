@@ -1,22 +1,33 @@
 use crate::{
-    assets::{LuaFile, LuaLoader},
+    assets::{LuaBytecodeProcessor, LuaBytecodeSaver, LuaFile, LuaLoader},
     docs::LuaDocFragment,
 };
-use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
-use bevy_mod_scripting_core::{prelude::*, systems::*, world::WorldPointerGuard};
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, utils::Instant};
+use bevy_mod_scripting_core::{
+    prelude::*,
+    systems::*,
+    world::{WorldPointer, WorldPointerGuard},
+};
 
 use std::fmt;
 use std::marker::PhantomData;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tealr::mlu::mlua::{prelude::*, Function};
 
 pub mod assets;
+#[cfg(feature = "debugger")]
+pub mod debugger;
 pub mod docs;
+#[cfg(feature = "teal")]
+pub mod teal;
 pub mod util;
 pub use tealr;
 pub mod prelude {
     pub use crate::{
-        assets::{LuaFile, LuaLoader},
+        assets::{
+            LuaBytecodeProcessor, LuaBytecodeSaver, LuaBytecodeSaverSettings, LuaChunkMode,
+            LuaFile, LuaLoader, LuaLoaderSettings,
+        },
         docs::{LuaDocFragment, TypeWalkerBuilder},
         tealr::{
             self,
@@ -25,8 +36,14 @@ pub mod prelude {
                 TealData,
             },
         },
-        LuaEvent, LuaScriptHost,
+        LuaEvent, LuaReflectConverter, LuaScriptHost,
     };
+
+    #[cfg(feature = "debugger")]
+    pub use crate::debugger::{LuaDebugger, LuaDebuggerPlugin};
+
+    #[cfg(feature = "teal")]
+    pub use crate::teal::TealCompiler;
 }
 
 pub trait LuaArg: for<'lua> IntoLuaMulti<'lua> + Clone + Sync + Send + 'static {}
@@ -57,6 +74,14 @@ impl<A: LuaArg> ScriptEvent for LuaEvent<A> {
     }
 }
 
+/// Converts reflected values into lua values, used to pass the arguments of [`ReflectedScriptEvent`]s to lua scripts.
+///
+/// Inserted by the bevy scripting API, without it lua scripts do not receive reflected events.
+#[derive(Resource, Clone, Copy)]
+pub struct LuaReflectConverter(
+    pub for<'lua> fn(&'lua Lua, WorldPointer, &dyn Reflect) -> LuaResult<LuaValue<'lua>>,
+);
+
 #[derive(Resource)]
 /// Lua script host, enables Lua scripting.
 pub struct LuaScriptHost<A: LuaArg> {
@@ -71,6 +96,94 @@ impl<A: LuaArg> Default for LuaScriptHost<A> {
     }
 }
 
+impl<A: LuaArg> LuaScriptHost<A> {
+    /// Sets up the runtime of every given script and lets `f` call its hooks,
+    /// metrics are recorded and errors reported for each call made through the [`HookCaller`]
+    fn call_hooks<'a>(
+        world: &mut World,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Mutex<Lua>)>,
+        providers: &mut APIProviders<Self>,
+        mut f: impl FnMut(&mut HookCaller),
+    ) {
+        let mut metrics = world.remove_resource::<ScriptMetrics>();
+
+        {
+            // safety:
+            // - we have &mut World access
+            // - we do not use the original reference again anywhere in this block
+            // - the guard is dropped at the end of this block
+            let world = unsafe { WorldPointerGuard::new(world) };
+
+            ctxs.for_each(|(script_data, ctx)| {
+                if let Err(error) = providers.setup_runtime_all(world.clone(), &script_data, ctx) {
+                    report_script_error(&mut world.write(), &script_data, error);
+                    return;
+                }
+
+                f(&mut HookCaller {
+                    world: &world,
+                    metrics: &mut metrics,
+                    script_data: &script_data,
+                    lua: ctx.get_mut().expect("Poison error in context"),
+                });
+            });
+
+            // explictly release the pointer to world.
+            drop(world);
+        }
+
+        if let Some(metrics) = metrics {
+            world.insert_resource(metrics);
+        }
+    }
+}
+
+/// Calls hooks of a single lua script, see [`LuaScriptHost::call_hooks`]
+struct HookCaller<'c> {
+    world: &'c WorldPointer,
+    metrics: &'c mut Option<ScriptMetrics>,
+    script_data: &'c ScriptData<'c>,
+    lua: &'c Lua,
+}
+
+impl<'c> HookCaller<'c> {
+    /// Calls the given hook if the script defines it, with the arguments produced by `args`
+    fn call(
+        &mut self,
+        hook: &str,
+        args: impl FnOnce(&'c Lua, &WorldPointer) -> LuaResult<LuaMultiValue<'c>>,
+    ) {
+        let f: Function = match self.lua.globals().raw_get(hook) {
+            Ok(f) => f,
+            Err(_) => return, // not subscribed to this hook
+        };
+
+        let _span = info_span!(
+            "script_hook",
+            script = self.script_data.name,
+            sid = self.script_data.sid,
+            hook = hook
+        )
+        .entered();
+        let start = Instant::now();
+        let result = args(self.lua, self.world).and_then(|args| {
+            run_script_callback(self.world, self.script_data.sid, || f.call::<_, ()>(args))
+        });
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_call(self.script_data, hook, start.elapsed(), result.is_err());
+        }
+
+        if let Err(error) = result {
+            let error = ScriptError::RuntimeError {
+                script: self.script_data.name.to_owned(),
+                msg: error.to_string(),
+            };
+
+            report_script_error(&mut self.world.write(), self.script_data, error);
+        }
+    }
+}
+
 impl<A: LuaArg> ScriptHost for LuaScriptHost<A> {
     type ScriptContext = Mutex<Lua>;
     type APITarget = Mutex<Lua>;
@@ -79,27 +192,39 @@ impl<A: LuaArg> ScriptHost for LuaScriptHost<A> {
     type DocTarget = LuaDocFragment;
 
     fn register_with_app_in_set(app: &mut App, schedule: impl ScheduleLabel, set: impl SystemSet) {
+        let loader = LuaLoader::from_app(app);
         app.add_priority_event::<Self::ScriptEvent>()
             .init_asset::<LuaFile>()
-            .init_asset_loader::<LuaLoader>()
+            .register_asset_loader(loader)
+            // precompile scripts when assets are processed
+            .register_asset_processor(LuaBytecodeProcessor::from(LuaBytecodeSaver))
+            .set_default_asset_processor::<LuaBytecodeProcessor>("lua")
             .init_resource::<CachedScriptState<Self>>()
             .init_resource::<ScriptContexts<Self::ScriptContext>>()
             .init_resource::<APIProviders<Self>>()
             .register_type::<ScriptCollection<Self::ScriptAsset>>()
             .register_type::<Script<Self::ScriptAsset>>()
+            .register_type::<Vec<Script<Self::ScriptAsset>>>()
             .register_type::<Handle<LuaFile>>()
             // handle script insertions removal first
             // then update their contexts later on script asset changes
             .add_systems(
                 schedule,
                 (
+                    script_component_router::<Self>,
+                    script_scene_resolver::<Self>,
                     script_add_synchronizer::<Self>,
                     script_remove_synchronizer::<Self>,
                     script_hot_reload_handler::<Self>,
+                    script_toggle_handler::<Self>,
                 )
                     .chain()
                     .in_set(set),
             );
+
+        // processed teal files are compiled to bytecode so release builds don't need the teal compiler
+        #[cfg(feature = "teal")]
+        app.set_default_asset_processor::<LuaBytecodeProcessor>("tl");
     }
 
     fn load_script(
@@ -142,6 +267,33 @@ impl<A: LuaArg> ScriptHost for LuaScriptHost<A> {
         providers.setup_all(script_data, ctx)
     }
 
+    fn apply_props(
+        &mut self,
+        script_data: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+        props: &ScriptProps,
+    ) -> Result<(), ScriptError> {
+        let lua = ctx.get_mut().expect("Poison error in context");
+
+        let set_props = || -> LuaResult<()> {
+            let table = lua.create_table()?;
+            for (name, value) in props {
+                match value {
+                    ScriptValue::Bool(v) => table.raw_set(name.as_str(), *v),
+                    ScriptValue::Integer(v) => table.raw_set(name.as_str(), *v),
+                    ScriptValue::Float(v) => table.raw_set(name.as_str(), *v),
+                    ScriptValue::String(v) => table.raw_set(name.as_str(), v.as_str()),
+                }?;
+            }
+            lua.globals().raw_set("props", table)
+        };
+
+        set_props().map_err(|e| ScriptError::FailedToLoad {
+            script: script_data.name.to_owned(),
+            msg: e.to_string(),
+        })
+    }
+
     fn handle_events<'a>(
         &mut self,
         world: &mut World,
@@ -149,49 +301,311 @@ impl<A: LuaArg> ScriptHost for LuaScriptHost<A> {
         ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
         providers: &mut APIProviders<Self>,
     ) {
-        // safety:
-        // - we have &mut World access
-        // - we do not use the original reference again anywhere in this function
-        let world = unsafe { WorldPointerGuard::new(world) };
-
-        ctxs.for_each(|(script_data, ctx)| {
-            providers
-                .setup_runtime_all(world.clone(), &script_data, ctx)
-                .expect("Could not setup script runtime");
-
-            let ctx = ctx.get_mut().expect("Poison error in context");
-
+        Self::call_hooks(world, ctxs, providers, |caller| {
             // event order is preserved, but scripts can't rely on any temporal
             // guarantees when it comes to other scripts callbacks,
             // at least for now.
-            let globals = ctx.globals();
             for event in events {
                 // check if this script should handle this event
-                if !event.recipients().is_recipient(&script_data) {
+                if !event.recipients().is_recipient(caller.script_data) {
                     continue;
                 }
 
-                let f: Function = match globals.raw_get(event.hook_name.clone()) {
-                    Ok(f) => f,
-                    Err(_) => continue, // not subscribed to this event
-                };
-
-                if let Err(error) = f.call::<_, ()>(event.args.clone()) {
-                    let mut world = world.write();
-                    let mut state: CachedScriptState<Self> = world.remove_resource().unwrap();
-
-                    let (_, mut error_wrt, _) = state.event_state.get_mut(&mut world);
+                caller.call(&event.hook_name, |lua, _| {
+                    event.args.clone().into_lua_multi(lua)
+                });
+            }
+        });
+    }
 
-                    let error = ScriptError::RuntimeError {
-                        script: script_data.name.to_owned(),
-                        msg: error.to_string(),
-                    };
+    fn handle_reflected_events<'a>(
+        &mut self,
+        world: &mut World,
+        events: &[ReflectedScriptEvent],
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        providers: &mut APIProviders<Self>,
+    ) {
+        let Some(converter) = world.get_resource::<LuaReflectConverter>().copied() else {
+            warn!("Lua scripts cannot receive reflected script events without a `LuaReflectConverter`, did you forget to add the bevy API provider?");
+            return;
+        };
 
-                    error!("{}", error);
-                    error_wrt.send(ScriptErrorEvent { error });
-                    world.insert_resource(state);
+        Self::call_hooks(world, ctxs, providers, |caller| {
+            for event in events {
+                // check if this script should handle this event
+                if !event.recipients.is_recipient(caller.script_data) {
+                    continue;
                 }
+
+                // every script receives its own copy of the arguments
+                caller.call(&event.hook_name, |lua, world| {
+                    event
+                        .args
+                        .iter()
+                        .map(|arg| (converter.0)(lua, world.clone(), arg.as_ref()))
+                        .collect::<LuaResult<Vec<_>>>()
+                        .map(LuaMultiValue::from_vec)
+                });
             }
         });
     }
+
+    fn call_lifecycle_hook<'a>(
+        &mut self,
+        world: &mut World,
+        hook: &str,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        providers: &mut APIProviders<Self>,
+    ) {
+        Self::call_hooks(world, ctxs, providers, |caller| {
+            caller.call(hook, |_, _| Ok(LuaMultiValue::new()))
+        });
+    }
+
+    fn eval_in_context(
+        &mut self,
+        code: &str,
+        script_data: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+        world: &mut World,
+        providers: &mut APIProviders<Self>,
+    ) -> Result<String, ScriptError> {
+        // safety:
+        // - we have &mut World access
+        // - we do not use the original reference again anywhere in this function
+        // - the guard is dropped at the end of this function
+        let world = unsafe { WorldPointerGuard::new(world) };
+        providers.setup_runtime_all(world.clone(), script_data, ctx)?;
+
+        let lua = ctx.get_mut().map_err(|e| ScriptError::RuntimeError {
+            script: script_data.name.to_owned(),
+            msg: e.to_string(),
+        })?;
+        let to_script_error = |e: LuaError| match e {
+            LuaError::SyntaxError { message, .. } => ScriptError::SyntaxError {
+                script: script_data.name.to_owned(),
+                msg: message,
+            },
+            e => ScriptError::RuntimeError {
+                script: script_data.name.to_owned(),
+                msg: e.to_string(),
+            },
+        };
+
+        // capture anything printed by the snippet so it can be returned alongside the result
+        let output = Arc::new(Mutex::new(Vec::<String>::new()));
+        let captured = output.clone();
+        let capture = lua
+            .create_function(move |lua, args: LuaMultiValue| {
+                let line = format_values(lua, args)?;
+                captured.lock().expect("Poison error in output").push(line);
+                Ok(())
+            })
+            .map_err(to_script_error)?;
+        let globals = lua.globals();
+        let print: LuaValue = globals.raw_get("print").map_err(to_script_error)?;
+        globals.raw_set("print", capture).map_err(to_script_error)?;
+
+        // evaluate as an expression first, fall back to running the snippet as a statement
+        let result = match lua
+            .load(format!("return {code}"))
+            .set_name("=eval")
+            .eval::<LuaMultiValue>()
+        {
+            Err(LuaError::SyntaxError { .. }) => lua.load(code).set_name("=eval").eval(),
+            result => result,
+        }
+        .and_then(|values| format_values(lua, values));
+
+        globals.raw_set("print", print).map_err(to_script_error)?;
+
+        let mut output = std::mem::take(&mut *output.lock().expect("Poison error in output"));
+        output.push(result.map_err(to_script_error)?);
+        output.retain(|line| !line.is_empty());
+        Ok(output.join("\n"))
+    }
+}
+
+/// Formats the given values the same way `print` does
+fn format_values<'lua>(lua: &'lua Lua, values: LuaMultiValue<'lua>) -> LuaResult<String> {
+    let tostring: Function = lua.globals().raw_get("tostring")?;
+    values
+        .into_iter()
+        .map(|v| tostring.call::<_, String>(v))
+        .collect::<LuaResult<Vec<_>>>()
+        .map(|values| values.join("\t"))
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_mod_scripting_core::hosts::eval_in_script;
+
+    use super::*;
+
+    type Host = LuaScriptHost<()>;
+
+    const SID: u32 = 0;
+
+    fn script_data() -> ScriptData<'static> {
+        ScriptData {
+            sid: SID,
+            entity: Entity::from_raw(0),
+            name: "script.lua",
+        }
+    }
+
+    /// Sets up a world with a single loaded script containing the given source
+    fn setup_world(source: &str) -> World {
+        let mut world = World::new();
+        let mut host = Host::default();
+        let mut providers = APIProviders::<Host>::default();
+        let ctx = host
+            .load_script(source.as_bytes(), &script_data(), &mut providers)
+            .unwrap();
+        let mut contexts = ScriptContexts::<Mutex<Lua>>::default();
+        contexts.insert_context(script_data(), Some(ctx));
+
+        world.insert_resource(contexts);
+        world.insert_resource(host);
+        world.insert_resource(providers);
+        world
+    }
+
+    fn eval(world: &mut World, code: &str) -> Result<String, ScriptError> {
+        eval_in_script::<Host>(world, SID, code)
+    }
+
+    /// Returns true if `print` is the same function it was when the script was loaded
+    fn print_restored(world: &mut World) -> bool {
+        let mut contexts = world.resource_mut::<ScriptContexts<Mutex<Lua>>>();
+        let (_, ctx, _) = contexts.context_entities.get_mut(&SID).unwrap();
+        let lua = ctx.as_mut().unwrap().get_mut().unwrap();
+        lua.load("return print == original_print").eval().unwrap()
+    }
+
+    #[test]
+    fn evaluates_expressions_and_statements() {
+        let mut world = setup_world("x = 2");
+
+        assert_eq!(eval(&mut world, "x + 1").unwrap(), "3");
+        assert_eq!(eval(&mut world, "x, 'a'").unwrap(), "2\ta");
+        assert_eq!(eval(&mut world, "x = 5").unwrap(), "");
+        assert_eq!(eval(&mut world, "x").unwrap(), "5");
+    }
+
+    #[test]
+    fn snippets_run_once() {
+        let mut world =
+            setup_world("calls = 0; function f() calls = calls + 1; error('failed') end");
+
+        // a runtime error in an expression is not retried as a statement
+        assert!(matches!(
+            eval(&mut world, "f()"),
+            Err(ScriptError::RuntimeError { .. })
+        ));
+        assert!(eval(&mut world, "y = f()").is_err());
+        assert_eq!(eval(&mut world, "calls").unwrap(), "2");
+
+        assert!(matches!(
+            eval(&mut world, "y = = 2"),
+            Err(ScriptError::SyntaxError { .. })
+        ));
+    }
+
+    #[test]
+    fn print_is_captured_and_restored() {
+        let mut world = setup_world("original_print = print");
+
+        assert_eq!(
+            eval(&mut world, "print('hello', 1); print('world'); return 3").unwrap(),
+            "hello\t1\nworld\n3"
+        );
+        assert!(print_restored(&mut world));
+
+        // print is restored even if the snippet fails
+        assert!(eval(&mut world, "print('x'); error('failed')").is_err());
+        assert!(print_restored(&mut world));
+    }
+
+    #[test]
+    fn unknown_scripts_are_errors() {
+        let mut world = setup_world("");
+
+        assert!(matches!(
+            eval_in_script::<Host>(&mut world, SID + 1, "1"),
+            Err(ScriptError::Other(_))
+        ));
+        // the host is still available afterwards
+        assert_eq!(eval(&mut world, "1").unwrap(), "1");
+    }
+
+    /// Converts the few types used by these tests, failing if reflected events were taken out of the world
+    fn test_converter<'lua>(
+        lua: &'lua Lua,
+        world: WorldPointer,
+        value: &dyn Reflect,
+    ) -> LuaResult<LuaValue<'lua>> {
+        if !world
+            .read()
+            .contains_resource::<Events<ReflectedScriptEvent>>()
+        {
+            return Err(LuaError::RuntimeError(
+                "reflected events were removed".to_owned(),
+            ));
+        }
+        if let Some(v) = value.downcast_ref::<f32>() {
+            v.into_lua(lua)
+        } else if let Some(v) = value.downcast_ref::<String>() {
+            v.as_str().into_lua(lua)
+        } else {
+            Err(LuaError::RuntimeError("unsupported type".to_owned()))
+        }
+    }
+
+    #[test]
+    fn reflected_events_reach_hooks_in_range() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            ScriptingPlugin,
+        ))
+        .add_script_host::<Host>(PostUpdate)
+        .add_script_handler::<Host, 0, 10>(PostUpdate)
+        .insert_resource(LuaReflectConverter(test_converter));
+
+        let ctx = Host::default()
+            .load_script(
+                b"received = '' function on_event(n, s) received = received .. n .. s .. ';' end",
+                &script_data(),
+                &mut APIProviders::default(),
+            )
+            .unwrap();
+        app.world
+            .resource_mut::<ScriptContexts<Mutex<Lua>>>()
+            .insert_context(script_data(), Some(ctx));
+
+        app.world.send_event_batch([
+            ReflectedScriptEvent::new("on_event", 5)
+                .with_arg(1.5f32)
+                .with_arg("a".to_owned()),
+            // outside of the handler's priority range
+            ReflectedScriptEvent::new("on_event", 11)
+                .with_arg(2.5f32)
+                .with_arg("b".to_owned()),
+            // meant for another host
+            ReflectedScriptEvent::new("on_event", 0)
+                .with_arg(3.5f32)
+                .with_arg("c".to_owned())
+                .for_host::<LuaScriptHost<i32>>(),
+            ReflectedScriptEvent::new("on_event", 10)
+                .with_arg(4.5f32)
+                .with_arg("d".to_owned())
+                .for_host::<Host>(),
+        ]);
+        app.update();
+
+        assert!(app.world.resource::<Events<ScriptErrorEvent>>().is_empty());
+        assert_eq!(eval(&mut app.world, "received").unwrap(), "1.5a;4.5d;");
+    }
 }
@@ -0,0 +1,155 @@
+//! Teal compilation running inside an embedded Lua state, used by the [`crate::assets::LuaLoader`] to load `.tl` files
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Error;
+use tealr::mlu::mlua::{Function, Lua, Table};
+
+/// The registry key under which the compile function is stored
+const COMPILE_FN: &str = "bevy_mod_scripting_teal_compile";
+
+/// Lua glue around the teal compiler API, supporting both the `process_string`/`pretty_print_ast` API of older
+/// teal versions and the `check_string`/`generate` API of newer ones
+const COMPILE_GLUE: &str = r#"
+local tl = ...
+return function(input, filename, predefined, gen_target)
+    local env, err
+    if tl.new_env then
+        env, err = tl.new_env({ predefined_modules = predefined, defaults = { gen_target = gen_target } })
+    else
+        env, err = tl.init_env(false, false, gen_target, predefined)
+    end
+    if not env then
+        return nil, err
+    end
+
+    local result
+    if tl.check_string then
+        result = tl.check_string(input, env, filename)
+    else
+        result = tl.process_string(input, false, env, filename)
+    end
+
+    local errors = {}
+    for _, kind in ipairs({ "syntax_errors", "type_errors" }) do
+        for _, e in ipairs(result[kind] or {}) do
+            errors[#errors + 1] = string.format("%s:%d:%d: %s", e.filename or filename, e.y, e.x, e.msg)
+        end
+    end
+    if #errors > 0 then
+        return nil, table.concat(errors, "\n")
+    end
+
+    if tl.generate then
+        return tl.generate(result.ast, gen_target)
+    end
+    return tl.pretty_print_ast(result.ast, gen_target)
+end
+"#;
+
+/// The Lua version teal code is generated for
+#[cfg(feature = "lua54")]
+const GEN_TARGET: &str = "5.4";
+#[cfg(all(feature = "lua53", not(feature = "lua54")))]
+const GEN_TARGET: &str = "5.3";
+#[cfg(not(any(feature = "lua53", feature = "lua54")))]
+const GEN_TARGET: &str = "5.1";
+
+/// Type checks and compiles Teal code to Lua in an embedded Lua state running the teal compiler (`tl.lua`).
+///
+/// Compilation happens entirely in memory so concurrent loads never interfere with each other.
+pub struct TealCompiler {
+    lua: Mutex<Lua>,
+    predefined: Vec<String>,
+}
+
+impl TealCompiler {
+    /// Creates a compiler using the `tl` module found on the Lua package path (e.g. installed via `luarocks install tl`)
+    pub fn new() -> Result<Self, Error> {
+        Self::with_module(|lua| {
+            lua.load("return require('tl')").eval().map_err(|e| {
+                Error::msg(format!(
+                    "Could not find the teal compiler, make sure `tl` is installed and on the Lua package path: {e}"
+                ))
+            })
+        })
+    }
+
+    /// Creates a compiler from the source code of the teal compiler (the contents of `tl.lua`),
+    /// useful for bundling the compiler with your game
+    pub fn from_source(tl_source: &str) -> Result<Self, Error> {
+        Self::with_module(|lua| {
+            let tl: Table = lua.load(tl_source).set_name("tl").call(())?;
+            lua.globals()
+                .get::<_, Table>("package")?
+                .get::<_, Table>("loaded")?
+                .set("tl", tl.clone())?;
+            Ok(tl)
+        })
+    }
+
+    fn with_module(
+        load: impl for<'lua> FnOnce(&'lua Lua) -> Result<Table<'lua>, Error>,
+    ) -> Result<Self, Error> {
+        let lua = Lua::new();
+        {
+            let tl = load(&lua)?;
+            let compile: Function = lua.load(COMPILE_GLUE).set_name("teal_glue").call(tl)?;
+            lua.set_named_registry_value(COMPILE_FN, compile)?;
+        }
+
+        Ok(Self {
+            lua: Mutex::new(lua),
+            predefined: Default::default(),
+        })
+    }
+
+    /// Creates a compiler following the layout generated by the documentation tooling, i.e. resolving modules
+    /// relative to `<asset_dir>/scripts` and loading global definitions from `<asset_dir>/scripts/types/types.d.tl` if present
+    pub fn with_default_layout(asset_dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let scripts_dir = asset_dir.as_ref().join("scripts");
+        let compiler = Self::new()?.with_include_dir(&scripts_dir)?;
+
+        if scripts_dir.join("types").join("types.d.tl").exists() {
+            Ok(compiler.with_global_env_def("types/types"))
+        } else {
+            Ok(compiler)
+        }
+    }
+
+    /// Adds a directory in which required teal modules and declaration files are searched for
+    pub fn with_include_dir(self, dir: impl AsRef<Path>) -> Result<Self, Error> {
+        {
+            let lua = self.lua.lock().expect("Poison error in teal compiler");
+            let package: Table = lua.globals().get("package")?;
+            let path: String = package.get("path")?;
+            let dir: PathBuf = dir.as_ref().to_owned();
+            package.set("path", format!("{}/?.lua;{}", dir.display(), path))?;
+        }
+        Ok(self)
+    }
+
+    /// Adds a module whose global declarations are available to all compiled files, like `global_env_def` in `tlconfig.lua`
+    pub fn with_global_env_def(mut self, module: impl Into<String>) -> Self {
+        self.predefined.push(module.into());
+        self
+    }
+
+    /// Type checks and compiles the given teal source code to Lua source code,
+    /// the chunk name is used in error messages
+    pub fn compile(&self, source: &str, chunk_name: &str) -> Result<String, Error> {
+        let lua = self.lua.lock().expect("Poison error in teal compiler");
+        let compile: Function = lua.named_registry_value(COMPILE_FN)?;
+        let (code, errors): (Option<String>, Option<String>) =
+            compile.call((source, chunk_name, self.predefined.clone(), GEN_TARGET))?;
+
+        code.ok_or_else(|| {
+            Error::msg(format!(
+                "Teal file `{chunk_name}` has errors:\n{}",
+                errors.unwrap_or_default()
+            ))
+        })
+    }
+}
@@ -1,12 +1,13 @@
 /// generates path to the given script depending on build configuration.
-/// (optimized builds don't have the teal compiler available)
+/// (teal files are compiled as they're loaded, or precompiled when assets are processed)
 ///
-/// Current configuration will provide "scripts/*.tl" paths
+/// Current configuration will provide "scripts/*.tl" paths, in release builds as well.
+/// Unless assets are processed this means the teal compiler (`tl.lua`) has to be available at runtime
 /// ```rust
 /// use bevy_mod_scripting_lua::lua_path;
 /// assert_eq!("scripts/my_script.tl",lua_path!("my_script"))
 /// ```
-#[cfg(all(feature = "teal", debug_assertions))]
+#[cfg(feature = "teal")]
 #[macro_export]
 macro_rules! lua_path {
     ($v:literal) => {
@@ -15,23 +16,7 @@ macro_rules! lua_path {
 }
 
 /// generates path to the given script depending on build configuration.
-/// (optimized builds don't have the teal compiler available)
-///
-/// Current configuration will provide "scripts/build/*.lua" paths
-/// ```rust
-/// use bevy_mod_scripting::lua_path;
-/// assert_eq!("scripts/build/my_script.lua",lua_path!("my_script"))
-/// ```
-#[cfg(all(not(debug_assertions), feature = "teal"))]
-#[macro_export]
-macro_rules! lua_path {
-    ($v:literal) => {
-        concat!("scripts/build/", $v, ".lua")
-    };
-}
-
-/// generates path to the given script depending on build configuration.
-/// (optimized builds don't have the teal compiler available)
+/// (teal files are compiled as they're loaded, or precompiled when assets are processed)
 ///
 /// Current configuration will provide "/scripts/*.lua" paths
 /// ```rust
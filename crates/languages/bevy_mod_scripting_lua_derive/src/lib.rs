@@ -484,6 +484,18 @@ pub fn impl_lua_proxy(input: TokenStream) -> TokenStream {
                 #(#type_level_document_calls)*
                 #(#methods)*
                 #(#composites)*
+
+                methods.document("Returns the value at the given reflection path relative to this value, e.g. `translation.x` or `items[2].name`.");
+                methods.add_method("get_path", |ctx, s, path: String| {
+                    let world_ptr = <#tealr::mlua::Lua as bevy_script_api::common::bevy::GetWorld>::get_world(ctx)?;
+                    Ok(s.reflect_ref(world_ptr).sub_path(&path)?)
+                });
+
+                methods.document("Sets the value at the given reflection path relative to this value.");
+                methods.add_method_mut("set_path", |ctx, s, (path, new_val): (String, #tealr::mlua::Value)| {
+                    let world_ptr = <#tealr::mlua::Lua as bevy_script_api::common::bevy::GetWorld>::get_world(ctx)?;
+                    bevy_script_api::lua::ApplyLua::apply_lua(&mut s.reflect_ref(world_ptr).sub_path(&path)?, ctx, new_val)
+                });
             }
 
             fn add_fields<'lua, T: #tealr::TealDataFields<'lua, Self>>(fields: &mut T) {
@@ -15,6 +15,10 @@ impl CodeAsset for RhaiFile {
     fn bytes(&self) -> &[u8] {
         self.bytes.as_slice()
     }
+
+    fn extensions() -> &'static [&'static str] {
+        &["rhai"]
+    }
 }
 
 #[derive(Default)]
@@ -39,6 +43,6 @@ impl AssetLoader for RhaiLoader {
     }
 
     fn extensions(&self) -> &[&str] {
-        &["rhai"]
+        RhaiFile::extensions()
     }
 }
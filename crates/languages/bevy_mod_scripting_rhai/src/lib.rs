@@ -2,10 +2,17 @@ use crate::{
     assets::{RhaiFile, RhaiLoader},
     docs::RhaiDocFragment,
 };
-use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
-use bevy_mod_scripting_core::{prelude::*, systems::*, world::WorldPointerGuard};
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, utils::Instant};
+use bevy_mod_scripting_core::{
+    prelude::*,
+    systems::*,
+    world::{WorldPointer, WorldPointerGuard},
+};
 use rhai::*;
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 
 pub mod assets;
 pub mod docs;
@@ -14,7 +21,7 @@ pub mod prelude {
     pub use crate::{
         assets::{RhaiFile, RhaiLoader},
         docs::RhaiDocFragment,
-        RhaiContext, RhaiEvent, RhaiScriptHost,
+        RhaiContext, RhaiEvent, RhaiReflectConverter, RhaiScriptHost,
     };
     pub use rhai;
     pub use rhai::{Engine, FuncArgs};
@@ -22,7 +29,12 @@ pub mod prelude {
 
 #[derive(Resource)]
 pub struct RhaiScriptHost<A: FuncArgs + Send> {
+    /// the engine running all scripts.
+    ///
+    /// Replacing its `on_print` callback prevents `print` output from being captured by [`ScriptHost::eval_in_context`]
     pub engine: Engine,
+    /// collects printed lines while code is evaluated in the context of a script, None otherwise
+    print_capture: Arc<Mutex<Option<Vec<String>>>>,
     _ph: PhantomData<A>,
 }
 
@@ -35,13 +47,121 @@ impl<A: FuncArgs + Send> Default for RhaiScriptHost<A> {
             Ok(info.name() != "state" && info.name() != "world" && info.name() != "entity")
         });
 
+        let print_capture = Arc::new(Mutex::new(None::<Vec<String>>));
+        let captured = print_capture.clone();
+        e.on_print(
+            move |line| match captured.lock().expect("Poison error in output").as_mut() {
+                Some(output) => output.push(line.to_owned()),
+                None => println!("{line}"),
+            },
+        );
+
         Self {
             engine: e,
+            print_capture,
             _ph: Default::default(),
         }
     }
 }
 
+impl<A: FuncArgs + Send + Clone + Sync + 'static> RhaiScriptHost<A> {
+    /// Sets up the runtime of every given script and lets `f` call its hooks,
+    /// metrics are recorded and errors reported for each call made through the [`HookCaller`]
+    fn call_hooks<'a>(
+        &self,
+        world: &mut World,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut RhaiContext)>,
+        providers: &mut APIProviders<Self>,
+        mut f: impl FnMut(&mut HookCaller),
+    ) {
+        let mut metrics = world.remove_resource::<ScriptMetrics>();
+
+        {
+            // safety:
+            // - we have &mut World access
+            // - we do not use the original reference again anywhere in this block
+            // - the guard is dropped at the end of this block
+            let world = unsafe { WorldPointerGuard::new(world) };
+
+            ctxs.for_each(|(fd, ctx)| {
+                if let Err(error) = providers.setup_runtime_all(world.clone(), &fd, ctx) {
+                    report_script_error(&mut world.write(), &fd, error);
+                    return;
+                }
+
+                f(&mut HookCaller {
+                    engine: &self.engine,
+                    world: &world,
+                    metrics: &mut metrics,
+                    script_data: &fd,
+                    ctx,
+                });
+            });
+
+            // explictly release the pointer to world.
+            drop(world);
+        }
+
+        if let Some(metrics) = metrics {
+            world.insert_resource(metrics);
+        }
+    }
+}
+
+/// Calls hooks of a single rhai script, see [`RhaiScriptHost::call_hooks`]
+struct HookCaller<'c> {
+    engine: &'c Engine,
+    world: &'c WorldPointer,
+    metrics: &'c mut Option<ScriptMetrics>,
+    script_data: &'c ScriptData<'c>,
+    ctx: &'c mut RhaiContext,
+}
+
+impl HookCaller<'_> {
+    /// Calls the given hook if the script defines it, with the arguments produced by `args`
+    fn call<Args: FuncArgs>(
+        &mut self,
+        hook: &str,
+        args: impl FnOnce(&WorldPointer) -> Result<Args, Box<EvalAltResult>>,
+    ) {
+        let fd = self.script_data;
+        let _span =
+            info_span!("script_hook", script = fd.name, sid = fd.sid, hook = hook).entered();
+        let start = Instant::now();
+        let mut called = false;
+        let result = args(self.world).and_then(|args| {
+            called = true;
+            run_script_callback(self.world, fd.sid, || {
+                self.engine
+                    .call_fn::<()>(&mut self.ctx.scope, &self.ctx.ast, hook, args)
+            })
+        });
+
+        if called {
+            // global statements are evaluated by the first call and must only ever run once,
+            // all this method call does is set a variable on the AST to NONE so should not affect performance
+            self.ctx.ast.clear_statements();
+        }
+
+        if matches!(&result, Err(e) if matches!(**e, EvalAltResult::ErrorFunctionNotFound(..))) {
+            return; // not subscribed to this hook
+        }
+
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_call(fd, hook, start.elapsed(), result.is_err());
+        }
+
+        if let Err(e) = result {
+            let error = ScriptError::RuntimeError {
+                script: fd.name.to_string(),
+                msg: e.to_string(),
+            };
+
+            report_script_error(&mut self.world.write(), fd, error);
+        }
+    }
+}
+
 pub struct RhaiContext {
     pub ast: AST,
     pub scope: Scope<'static>,
@@ -62,6 +182,14 @@ impl<A: FuncArgs + Clone + Send + Sync + 'static> ScriptEvent for RhaiEvent<A> {
     }
 }
 
+/// Converts reflected values into rhai values, used to pass the arguments of [`ReflectedScriptEvent`]s to rhai scripts.
+///
+/// Inserted by the bevy scripting API, without it rhai scripts do not receive reflected events.
+#[derive(Resource, Clone, Copy)]
+pub struct RhaiReflectConverter(
+    pub fn(WorldPointer, &dyn Reflect) -> Result<Dynamic, Box<EvalAltResult>>,
+);
+
 impl<A: FuncArgs + Send + Clone + Sync + 'static> ScriptHost for RhaiScriptHost<A> {
     type ScriptContext = RhaiContext;
     type ScriptEvent = RhaiEvent<A>;
@@ -82,13 +210,17 @@ impl<A: FuncArgs + Send + Clone + Sync + 'static> ScriptHost for RhaiScriptHost<
             .init_resource::<APIProviders<Self>>()
             .register_type::<ScriptCollection<Self::ScriptAsset>>()
             .register_type::<Script<Self::ScriptAsset>>()
+            .register_type::<Vec<Script<Self::ScriptAsset>>>()
             .register_type::<Handle<RhaiFile>>()
             .add_systems(
                 schedule,
                 (
+                    script_component_router::<Self>,
+                    script_scene_resolver::<Self>,
                     script_add_synchronizer::<Self>,
                     script_remove_synchronizer::<Self>,
                     script_hot_reload_handler::<Self>,
+                    script_toggle_handler::<Self>,
                 )
                     .chain()
                     .in_set(set),
@@ -113,6 +245,29 @@ impl<A: FuncArgs + Send + Clone + Sync + 'static> ScriptHost for RhaiScriptHost<
         providers.setup_all(script_data, ctx)
     }
 
+    fn apply_props(
+        &mut self,
+        _: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+        props: &ScriptProps,
+    ) -> Result<(), ScriptError> {
+        let props = props
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    ScriptValue::Bool(v) => Dynamic::from(*v),
+                    ScriptValue::Integer(v) => Dynamic::from(*v as INT),
+                    ScriptValue::Float(v) => Dynamic::from(*v as FLOAT),
+                    ScriptValue::String(v) => Dynamic::from(v.clone()),
+                };
+                (name.into(), value)
+            })
+            .collect::<Map>();
+
+        ctx.scope.set_value("props", props);
+        Ok(())
+    }
+
     fn load_script(
         &mut self,
         script: &[u8],
@@ -148,55 +303,337 @@ impl<A: FuncArgs + Send + Clone + Sync + 'static> ScriptHost for RhaiScriptHost<
         ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
         providers: &mut APIProviders<Self>,
     ) {
-        // safety:
-        // - we have &mut World access
-        // - we do not use the original reference again anywhere in this function
-        let world = unsafe { WorldPointerGuard::new(world) };
+        self.call_hooks(world, ctxs, providers, |caller| {
+            for event in events.iter() {
+                // check if this script should handle this event
+                if !event.recipients().is_recipient(caller.script_data) {
+                    continue;
+                };
 
-        ctxs.for_each(|(fd, ctx)| {
-            providers
-                .setup_runtime_all(world.clone(), &fd, ctx)
-                .expect("Failed to setup script runtime");
+                caller.call(&event.hook_name, |_| Ok(event.args.clone()));
+            }
+        });
+    }
+
+    fn handle_reflected_events<'a>(
+        &mut self,
+        world: &mut World,
+        events: &[ReflectedScriptEvent],
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        providers: &mut APIProviders<Self>,
+    ) {
+        let Some(converter) = world.get_resource::<RhaiReflectConverter>().copied() else {
+            warn!("Rhai scripts cannot receive reflected script events without a `RhaiReflectConverter`, did you forget to add the bevy API provider?");
+            return;
+        };
 
+        self.call_hooks(world, ctxs, providers, |caller| {
             for event in events.iter() {
                 // check if this script should handle this event
-                if !event.recipients().is_recipient(&fd) {
+                if !event.recipients.is_recipient(caller.script_data) {
                     continue;
                 };
 
-                match self.engine.call_fn(
-                    &mut ctx.scope,
-                    &ctx.ast,
-                    &event.hook_name,
-                    event.args.clone(),
-                ) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        let mut world = world.write();
-                        let mut state: CachedScriptState<Self> = world.remove_resource().unwrap();
-
-                        match *e {
-                            EvalAltResult::ErrorFunctionNotFound(..) => {}
-                            _ => {
-                                let (_, mut error_wrt, _) = state.event_state.get_mut(&mut world);
-
-                                let error = ScriptError::RuntimeError {
-                                    script: fd.name.to_string(),
-                                    msg: e.to_string(),
-                                };
-                                error!("{}", error);
-                                error_wrt.send(ScriptErrorEvent { error });
-                            }
-                        }
-
-                        world.insert_resource(state);
-                    }
-                };
+                // every script receives its own copy of the arguments
+                caller.call(&event.hook_name, |world| {
+                    event
+                        .args
+                        .iter()
+                        .map(|arg| (converter.0)(world.clone(), arg.as_ref()))
+                        .collect::<Result<Vec<_>, _>>()
+                });
             }
+        });
+    }
 
-            // executing this at the end here means we execute global statements exactly once
-            // all this method call does is set a variable on the AST to NONE so should not affect performance
-            ctx.ast.clear_statements();
+    fn call_lifecycle_hook<'a>(
+        &mut self,
+        world: &mut World,
+        hook: &str,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        providers: &mut APIProviders<Self>,
+    ) {
+        self.call_hooks(world, ctxs, providers, |caller| {
+            caller.call(hook, |_| Ok(()))
+        });
+    }
+
+    fn eval_in_context(
+        &mut self,
+        code: &str,
+        script_data: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+        world: &mut World,
+        providers: &mut APIProviders<Self>,
+    ) -> Result<String, ScriptError> {
+        // safety:
+        // - we have &mut World access
+        // - we do not use the original reference again anywhere in this function
+        // - the guard is dropped at the end of this function
+        let world = unsafe { WorldPointerGuard::new(world) };
+        providers.setup_runtime_all(world.clone(), script_data, ctx)?;
+
+        let snippet = self
+            .engine
+            .compile_with_scope(&ctx.scope, code)
+            .map_err(|e| ScriptError::SyntaxError {
+                script: script_data.name.to_owned(),
+                msg: e.to_string(),
+            })?;
+
+        // the snippet can call the script's functions, but the script's global statements are not re-run
+        let ast = ctx.ast.clone_functions_only().merge(&snippet);
+
+        // capture anything printed by the snippet so it can be returned alongside the result
+        *self.print_capture.lock().expect("Poison error in output") = Some(Vec::new());
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut ctx.scope, &ast);
+        let mut output = self
+            .print_capture
+            .lock()
+            .expect("Poison error in output")
+            .take()
+            .unwrap_or_default();
+
+        let result = result.map_err(|e| ScriptError::RuntimeError {
+            script: script_data.name.to_owned(),
+            msg: e.to_string(),
+        })?;
+        if !result.is_unit() {
+            output.push(result.to_string());
+        }
+        Ok(output.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_mod_scripting_core::hosts::eval_in_script;
+
+    use super::*;
+
+    type Host = RhaiScriptHost<()>;
+
+    const SID: u32 = 0;
+
+    fn script_data() -> ScriptData<'static> {
+        ScriptData {
+            sid: SID,
+            entity: Entity::from_raw(0),
+            name: "script.rhai",
+        }
+    }
+
+    /// Sets up a world with a single loaded script containing the given source
+    fn setup_world(source: &str) -> World {
+        let mut world = World::new();
+        let mut host = Host::default();
+        let mut providers = APIProviders::<Host>::default();
+        let ctx = host
+            .load_script(source.as_bytes(), &script_data(), &mut providers)
+            .unwrap();
+        let mut contexts = ScriptContexts::<RhaiContext>::default();
+        contexts.insert_context(script_data(), Some(ctx));
+
+        world.insert_resource(contexts);
+        world.insert_resource(host);
+        world.insert_resource(providers);
+        world
+    }
+
+    fn eval(world: &mut World, code: &str) -> Result<String, ScriptError> {
+        eval_in_script::<Host>(world, SID, code)
+    }
+
+    #[test]
+    fn snippets_can_call_script_functions() {
+        let mut world = setup_world("fn double(x) { x * 2 }");
+
+        assert_eq!(eval(&mut world, "double(2)").unwrap(), "4");
+        assert_eq!(eval(&mut world, "let y = 1;").unwrap(), "");
+    }
+
+    #[test]
+    fn global_statements_are_not_rerun() {
+        let mut world = setup_world("print(\"loaded\"); fn double(x) { x * 2 }");
+
+        assert_eq!(eval(&mut world, "double(2)").unwrap(), "4");
+        assert_eq!(eval(&mut world, "1").unwrap(), "1");
+    }
+
+    #[test]
+    fn print_is_captured() {
+        let mut world = setup_world("");
+
+        assert_eq!(
+            eval(&mut world, "print(\"hello\"); print(1); 3").unwrap(),
+            "hello\n1\n3"
+        );
+        assert!(eval(&mut world, "print(\"x\"); throw \"failed\"").is_err());
+        // capturing stops once the snippet is done
+        assert!(world
+            .resource::<Host>()
+            .print_capture
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let mut world = setup_world("");
+
+        assert!(matches!(
+            eval(&mut world, "let = 2"),
+            Err(ScriptError::SyntaxError { .. })
+        ));
+        assert!(matches!(
+            eval(&mut world, "throw \"failed\""),
+            Err(ScriptError::RuntimeError { .. })
+        ));
+        assert!(matches!(
+            eval_in_script::<Host>(&mut world, SID + 1, "1"),
+            Err(ScriptError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn script_collections_round_trip_through_scenes() {
+        use bevy::{
+            asset::ron,
+            ecs::entity::EntityHashMap,
+            scene::{serde::SceneDeserializer, DynamicScene, DynamicSceneBuilder},
+        };
+
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            ScriptingPlugin,
+        ))
+        .add_script_host::<Host>(PostUpdate);
+
+        let handle = app
+            .world
+            .resource::<AssetServer>()
+            .load::<RhaiFile>("scripts/test.rhai");
+        let original = Script::new("test.rhai".to_owned(), handle);
+        let original_id = original.id();
+        let entity = app
+            .world
+            .spawn(ScriptCollection::<RhaiFile> {
+                scripts: vec![original],
+            })
+            .id();
+
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        let serialized = DynamicSceneBuilder::from_world(&app.world)
+            .extract_entity(entity)
+            .build()
+            .serialize_ron(&registry)
+            .unwrap();
+        let scene: DynamicScene = ron::Options::default()
+            .from_str_seed(
+                &serialized,
+                SceneDeserializer {
+                    type_registry: &registry.read(),
+                },
+            )
+            .unwrap();
+
+        let mut entity_map = EntityHashMap::default();
+        scene
+            .write_to_world(&mut app.world, &mut entity_map)
+            .unwrap();
+        app.update();
+
+        let spawned = entity_map[&entity];
+        assert_ne!(spawned, entity);
+        let scripts = &app
+            .world
+            .get::<ScriptCollection<RhaiFile>>(spawned)
+            .unwrap()
+            .scripts;
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].name(), "test.rhai");
+        assert_ne!(scripts[0].id(), original_id);
+        assert_eq!(
+            scripts[0].handle().path().map(ToString::to_string),
+            Some("scripts/test.rhai".to_owned())
+        );
+    }
+
+    /// Converts the few types used by these tests, failing if reflected events were taken out of the world
+    fn test_converter(
+        world: WorldPointer,
+        value: &dyn Reflect,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if !world
+            .read()
+            .contains_resource::<Events<ReflectedScriptEvent>>()
+        {
+            return Err("reflected events were removed".into());
+        }
+        if let Some(v) = value.downcast_ref::<f32>() {
+            Ok(Dynamic::from_float(*v as FLOAT))
+        } else if let Some(v) = value.downcast_ref::<String>() {
+            Ok(v.clone().into())
+        } else {
+            Err("unsupported type".into())
+        }
+    }
+
+    #[test]
+    fn reflected_events_reach_hooks_in_range() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            ScriptingPlugin,
+        ))
+        .add_script_host::<Host>(PostUpdate)
+        .add_script_handler::<Host, 0, 10>(PostUpdate)
+        .insert_resource(RhaiReflectConverter(test_converter));
+
+        let received = Arc::new(Mutex::new(Vec::<String>::new()));
+        let recorded = received.clone();
+        let mut host = app.world.resource_mut::<Host>();
+        host.engine.register_fn("record", move |s: &str| {
+            recorded.lock().unwrap().push(s.to_owned())
         });
+        let ctx = host
+            .load_script(
+                b"fn on_event(n, s) { record(`${n}${s}`) }",
+                &script_data(),
+                &mut APIProviders::default(),
+            )
+            .unwrap();
+        app.world
+            .resource_mut::<ScriptContexts<RhaiContext>>()
+            .insert_context(script_data(), Some(ctx));
+
+        app.world.send_event_batch([
+            ReflectedScriptEvent::new("on_event", 5)
+                .with_arg(1.5f32)
+                .with_arg("a".to_owned()),
+            // outside of the handler's priority range
+            ReflectedScriptEvent::new("on_event", 11)
+                .with_arg(2.5f32)
+                .with_arg("b".to_owned()),
+            // meant for another host
+            ReflectedScriptEvent::new("on_event", 0)
+                .with_arg(3.5f32)
+                .with_arg("c".to_owned())
+                .for_host::<RhaiScriptHost<(i32,)>>(),
+            ReflectedScriptEvent::new("on_event", 10)
+                .with_arg(4.5f32)
+                .with_arg("d".to_owned())
+                .for_host::<Host>(),
+        ]);
+        app.update();
+
+        assert!(app.world.resource::<Events<ScriptErrorEvent>>().is_empty());
+        assert_eq!(*received.lock().unwrap(), ["1.5a", "4.5d"]);
     }
 }
@@ -16,6 +16,10 @@ impl CodeAsset for RuneFile {
     fn bytes(&self) -> &[u8] {
         self.bytes.as_slice()
     }
+
+    fn extensions() -> &'static [&'static str] {
+        &["rune", "rn"]
+    }
 }
 
 #[derive(Default)]
@@ -41,6 +45,6 @@ impl AssetLoader for RuneLoader {
     }
 
     fn extensions(&self) -> &[&str] {
-        &["rune", "rn"]
+        RuneFile::extensions()
     }
 }
@@ -1,6 +1,6 @@
 use std::{marker::PhantomData, sync::Arc};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::Instant};
 use bevy_mod_scripting_core::{
     prelude::*,
     systems::{self, CachedScriptState},
@@ -65,6 +65,10 @@ impl Default for RuneVm {
 pub struct RuneScriptContext {
     pub unit: Arc<Unit>,
     pub runtime_context: Arc<RuntimeContext>,
+    /// the compilation context, kept around to compile code evaluated in the context of this script
+    pub context: Arc<Context>,
+    /// the source code of the script
+    pub source: String,
 }
 
 #[derive(Resource)]
@@ -82,24 +86,150 @@ impl<A: RuneArgs> Default for RuneScriptHost<A> {
 }
 
 impl<A: RuneArgs> RuneScriptHost<A> {
+    /// Compiles the given source into a unit, reporting any diagnostics as a syntax error.
+    fn compile(
+        script_data: &ScriptData<'_>,
+        source: &str,
+        context: &Context,
+    ) -> Result<Unit, ScriptError> {
+        let mut diagnostics = Diagnostics::new();
+
+        let mut sources = Sources::new();
+        sources
+            .insert(Source::new(script_data.name, source).map_err(|msg| {
+                ScriptError::FailedToLoad {
+                    script: script_data.name.into(),
+                    msg: msg.to_string(),
+                }
+            })?)
+            .map_err(|msg| ScriptError::FailedToLoad {
+                script: script_data.name.into(),
+                msg: msg.to_string(),
+            })?;
+
+        let result = rune::prepare(&mut sources)
+            .with_context(context)
+            .with_diagnostics(&mut diagnostics)
+            .build();
+
+        if !diagnostics.is_empty() {
+            let mut writer = rune::termcolor::Buffer::no_color();
+
+            diagnostics
+                .emit(&mut writer, &sources)
+                .expect("Failed to write diagnostics to buffer");
+
+            return Err(ScriptError::SyntaxError {
+                script: script_data.name.into(),
+                msg: std::str::from_utf8(writer.as_slice())
+                    .expect("Slice was not UTF-8")
+                    .to_owned(),
+            });
+        }
+
+        Ok(result.expect("Failed to build Rune unit."))
+    }
+
+    /// Sets up the runtime of every given script and lets `f` call its hooks,
+    /// metrics are recorded and errors reported for each call made through the [`HookCaller`]
+    fn call_hooks<'a>(
+        world: &mut World,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut RuneScriptContext)>,
+        providers: &mut APIProviders<Self>,
+        mut f: impl FnMut(&mut HookCaller),
+    ) {
+        // Grab the cached Vm.
+        let RuneVm(mut vm) = world.remove_non_send_resource::<RuneVm>().unwrap(/* invariant */);
+        let mut metrics = world.remove_resource::<ScriptMetrics>();
+
+        {
+            // Safety:
+            // - we have &mut World access
+            // - we do not use the original reference again anywhere in this block.
+            // - the guard is dropped at the end of this block.
+            let world = unsafe { WorldPointerGuard::new(world) };
+
+            ctxs.for_each(|(script_data, ctx)| {
+                if let Err(error) = providers.setup_runtime_all(world.clone(), &script_data, ctx) {
+                    report_script_error(&mut world.write(), &script_data, error);
+                    return;
+                }
+
+                // Swap out the old context and old unit with the new ones.
+                *vm.context_mut() = Arc::clone(&ctx.runtime_context);
+                *vm.unit_mut() = Arc::clone(&ctx.unit);
+
+                f(&mut HookCaller {
+                    vm: &mut vm,
+                    world: &world,
+                    metrics: &mut metrics,
+                    script_data: &script_data,
+                });
+            });
+
+            // explictly release the pointer to world.
+            drop(world);
+        }
+
+        world.insert_non_send_resource(RuneVm(vm));
+        if let Some(metrics) = metrics {
+            world.insert_resource(metrics);
+        }
+    }
+}
+
+/// Calls hooks of a single rune script, see [`RuneScriptHost::call_hooks`]
+struct HookCaller<'c> {
+    vm: &'c mut Vm,
+    world: &'c WorldPointer,
+    metrics: &'c mut Option<ScriptMetrics>,
+    script_data: &'c ScriptData<'c>,
+}
+
+impl HookCaller<'_> {
+    /// Calls the given hook with the given arguments if the script defines it
+    fn call(&mut self, hook: &str, args: impl Args) {
+        if self.vm.lookup_function([hook]).is_err() {
+            return; // not subscribed to this hook
+        }
+
+        let _span = info_span!(
+            "script_hook",
+            script = self.script_data.name,
+            sid = self.script_data.sid,
+            hook = hook
+        )
+        .entered();
+        let start = Instant::now();
+        let result = run_script_callback(self.world, self.script_data.sid, || {
+            match self.vm.execute([hook], args) {
+                Ok(mut exec) => match exec.complete() {
+                    VmResult::Ok(_) => Ok(()),
+                    VmResult::Err(error) => Err(error),
+                },
+                Err(error) => Err(error),
+            }
+        });
+
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_call(self.script_data, hook, start.elapsed(), result.is_err());
+        }
+
+        if let Err(error) = result {
+            self.handle_rune_error(error);
+        }
+    }
+
     /// Helper function to handle errors from a Rune virtual machine.
     ///
     #[cold]
-    fn handle_rune_error(world: WorldPointer, error: VmError, script_data: &ScriptData<'_>) {
-        let mut world = world.write();
-        let mut state: CachedScriptState<Self> = world.remove_resource().unwrap();
-
-        let (_, mut error_wrt, _) = state.event_state.get_mut(&mut world);
-
+    fn handle_rune_error(&self, error: VmError) {
         let error = ScriptError::RuntimeError {
-            script: script_data.name.to_owned(),
+            script: self.script_data.name.to_owned(),
             msg: error.to_string(),
         };
 
-        error!("{}", error);
-
-        error_wrt.send(ScriptErrorEvent { error });
-        world.insert_resource(state);
+        report_script_error(&mut self.world.write(), self.script_data, error);
     }
 }
 
@@ -127,6 +257,7 @@ impl<A: RuneArgs> ScriptHost for RuneScriptHost<A> {
             .init_resource::<APIProviders<Self>>()
             .register_type::<ScriptCollection<Self::ScriptAsset>>()
             .register_type::<Script<Self::ScriptAsset>>()
+            .register_type::<Vec<Script<Self::ScriptAsset>>>()
             .register_type::<Handle<RuneFile>>()
             // Add a cached Vm as a non-send resource.
             .insert_non_send_resource(RuneVm::default())
@@ -135,9 +266,12 @@ impl<A: RuneArgs> ScriptHost for RuneScriptHost<A> {
             .add_systems(
                 schedule,
                 (
+                    systems::script_component_router::<Self>,
+                    systems::script_scene_resolver::<Self>,
                     systems::script_add_synchronizer::<Self>,
                     systems::script_remove_synchronizer::<Self>,
                     systems::script_hot_reload_handler::<Self>,
+                    systems::script_toggle_handler::<Self>,
                 )
                     .chain()
                     .in_set(set),
@@ -154,48 +288,16 @@ impl<A: RuneArgs> ScriptHost for RuneScriptHost<A> {
 
         // Rune requires that we tell it what modules and types we'll be using before
         // it compiles a file.
-        providers.attach_all(&mut context).unwrap();
-
-        let mut diagnostics = Diagnostics::new();
+        providers.attach_all(&mut context)?;
 
-        let mut sources = Sources::new();
-        sources
-            .insert(
-                Source::new(
-                    script_data.name,
-                    std::str::from_utf8(script).expect("Slice is not UTF-8"),
-                )
-                .map_err(|msg| ScriptError::FailedToLoad {
-                    script: script_data.name.into(),
-                    msg: msg.to_string(),
-                })?,
-            )
-            .map_err(|msg| ScriptError::FailedToLoad {
-                script: script_data.name.into(),
-                msg: msg.to_string(),
-            })?;
-
-        let result = rune::prepare(&mut sources)
-            .with_context(&context)
-            .with_diagnostics(&mut diagnostics)
-            .build();
-
-        if !diagnostics.is_empty() {
-            let mut writer = rune::termcolor::Buffer::no_color();
-
-            diagnostics
-                .emit(&mut writer, &sources)
-                .expect("Failed to write diagnostics to buffer");
-
-            return Err(ScriptError::SyntaxError {
+        let source = std::str::from_utf8(script)
+            .map_err(|e| ScriptError::FailedToLoad {
                 script: script_data.name.into(),
-                msg: std::str::from_utf8(writer.as_slice())
-                    .expect("Slice was not UTF-8")
-                    .to_owned(),
-            });
-        }
+                msg: e.to_string(),
+            })?
+            .to_owned();
 
-        let unit = result.expect("Failed to build Rune unit.");
+        let unit = Self::compile(script_data, &source, &context)?;
 
         let runtime_ctx = context
             .runtime()
@@ -204,6 +306,8 @@ impl<A: RuneArgs> ScriptHost for RuneScriptHost<A> {
         Ok(RuneScriptContext {
             unit: Arc::new(unit),
             runtime_context: Arc::new(runtime_ctx),
+            context: Arc::new(context),
+            source,
         })
     }
 
@@ -223,49 +327,146 @@ impl<A: RuneArgs> ScriptHost for RuneScriptHost<A> {
         ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
         providers: &mut APIProviders<Self>,
     ) {
-        // Grab the cached Vm.
-        let RuneVm(mut vm) = world.remove_non_send_resource::<RuneVm>().unwrap(/* invariant */);
+        Self::call_hooks(world, ctxs, providers, |caller| {
+            for event in events {
+                if !event.recipients().is_recipient(caller.script_data) {
+                    continue;
+                }
 
-        {
-            // Safety:
-            // - we have &mut World access
-            // - we do not use the original reference again anywhere in this block.
-            // - the guard is dropped at the end of this block.
-            let world = unsafe { WorldPointerGuard::new(world) };
+                caller.call(&event.hook_name, event.args.clone());
+            }
+        });
+    }
 
-            ctxs.for_each(|(script_data, ctx)| {
-                providers
-                    .setup_runtime_all(world.clone(), &script_data, ctx)
-                    .expect("Could not setup script runtime");
-
-                for event in events {
-                    if !event.recipients().is_recipient(&script_data) {
-                        continue;
-                    }
-
-                    // Swap out the old context and old unit with the new ones.
-                    *vm.context_mut() = Arc::clone(&ctx.runtime_context);
-                    *vm.unit_mut() = Arc::clone(&ctx.unit);
-
-                    let mut exec = match vm.execute([event.hook_name.as_str()], event.args.clone())
-                    {
-                        Ok(exec) => exec,
-                        Err(error) => {
-                            Self::handle_rune_error(world.clone(), error, &script_data);
-                            continue;
-                        }
-                    };
-
-                    if let VmResult::Err(error) = exec.complete() {
-                        Self::handle_rune_error(world.clone(), error, &script_data);
-                    }
-                }
-            });
+    fn call_lifecycle_hook<'a>(
+        &mut self,
+        world: &mut World,
+        hook: &str,
+        ctxs: impl Iterator<Item = (ScriptData<'a>, &'a mut Self::ScriptContext)>,
+        providers: &mut APIProviders<Self>,
+    ) {
+        Self::call_hooks(world, ctxs, providers, |caller| caller.call(hook, ()));
+    }
 
-            // explictly release the pointer to world.
-            drop(world);
+    /// Rune units are immutable and hold no state between calls, so there is no live context to evaluate in.
+    /// Instead the snippet is compiled together with the script's source into a fresh unit and run in a new `Vm`,
+    /// which gives it access to everything the script declares. Unlike the Lua and Rhai hosts, output printed
+    /// by the snippet is not captured and goes to stdout as usual.
+    fn eval_in_context(
+        &mut self,
+        code: &str,
+        script_data: &ScriptData,
+        ctx: &mut Self::ScriptContext,
+        world: &mut World,
+        providers: &mut APIProviders<Self>,
+    ) -> Result<String, ScriptError> {
+        // Safety:
+        // - we have &mut World access
+        // - we do not use the original reference again anywhere in this function.
+        // - the guard is dropped at the end of this function.
+        let world = unsafe { WorldPointerGuard::new(world) };
+        providers.setup_runtime_all(world.clone(), script_data, ctx)?;
+
+        // Rune units are immutable, so the snippet is compiled as a function alongside the script's
+        // source, giving it access to everything the script declares.
+        let source = format!(
+            "{}\n\npub fn {EVAL_FN}() {{\n    let value = {{\n{code}\n    }};\n    format!(\"{{:?}}\", value)\n}}\n",
+            ctx.source
+        );
+        let unit = Self::compile(script_data, &source, &ctx.context)?;
+
+        let mut vm = Vm::new(ctx.runtime_context.clone(), Arc::new(unit));
+        let result = vm
+            .call([EVAL_FN], ())
+            .and_then(rune::from_value::<String>)
+            .map_err(|e| ScriptError::RuntimeError {
+                script: script_data.name.to_owned(),
+                msg: e.to_string(),
+            })?;
+
+        Ok(if result == "()" {
+            String::new()
+        } else {
+            result
+        })
+    }
+}
+
+/// The name of the function wrapping code evaluated with [`ScriptHost::eval_in_context`]
+const EVAL_FN: &str = "__bms_eval";
+
+#[cfg(test)]
+mod test {
+    use bevy_mod_scripting_core::hosts::eval_in_script;
+
+    use super::*;
+
+    type Host = RuneScriptHost<()>;
+
+    const SID: u32 = 0;
+
+    fn script_data() -> ScriptData<'static> {
+        ScriptData {
+            sid: SID,
+            entity: Entity::from_raw(0),
+            name: "script.rune",
         }
+    }
 
-        world.insert_non_send_resource(RuneVm(vm));
+    /// Sets up a world with a single loaded script containing the given source
+    fn setup_world(source: &str) -> World {
+        let mut world = World::new();
+        let mut host = Host::default();
+        let mut providers = APIProviders::<Host>::default();
+        let ctx = host
+            .load_script(source.as_bytes(), &script_data(), &mut providers)
+            .unwrap();
+        let mut contexts = ScriptContexts::<RuneScriptContext>::default();
+        contexts.insert_context(script_data(), Some(ctx));
+
+        world.insert_resource(contexts);
+        world.insert_resource(host);
+        world.insert_resource(providers);
+        world
+    }
+
+    fn eval(world: &mut World, code: &str) -> Result<String, ScriptError> {
+        eval_in_script::<Host>(world, SID, code)
+    }
+
+    #[test]
+    fn snippets_can_use_script_items() {
+        let mut world = setup_world("pub fn base() { 1 }\n\npub fn double(x) { x * 2 }");
+
+        assert_eq!(eval(&mut world, "double(2) + base()").unwrap(), "5");
+        assert_eq!(eval(&mut world, "\"text\"").unwrap(), "\"text\"");
+    }
+
+    #[test]
+    fn statements_evaluate_to_nothing() {
+        let mut world = setup_world("");
+
+        assert_eq!(eval(&mut world, "let x = 1;").unwrap(), "");
+        assert_eq!(eval(&mut world, "let x = 1; x + 1").unwrap(), "2");
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let mut world = setup_world("pub fn double(x) { x * 2 }");
+
+        assert!(matches!(
+            eval(&mut world, "let = 2"),
+            Err(ScriptError::SyntaxError { .. })
+        ));
+        assert!(matches!(
+            eval(&mut world, "double(\"a\")"),
+            Err(ScriptError::RuntimeError { .. })
+        ));
+        assert!(matches!(
+            eval_in_script::<Host>(&mut world, SID + 1, "1"),
+            Err(ScriptError::Other(_))
+        ));
+        // the script itself is unaffected by failed snippets
+        assert_eq!(eval(&mut world, "double(1)").unwrap(), "2");
     }
 }
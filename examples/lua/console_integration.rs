@@ -103,7 +103,9 @@ pub fn run_script_cmd(
             Some(e) => {
                 if let Ok(mut scripts) = existing_scripts.get_mut(Entity::from_raw(e)) {
                     info!("Creating script: scripts/{} {:?}", &path, e);
-                    scripts.scripts.push(Script::<LuaFile>::new(path, handle));
+                    let script = Script::<LuaFile>::new(path, handle);
+                    log.reply_ok(format!("Created script with id: {}", script.id()));
+                    scripts.scripts.push(script);
                 } else {
                     log.reply_failed("Something went wrong".to_string());
                 };
@@ -111,8 +113,10 @@ pub fn run_script_cmd(
             None => {
                 info!("Creating script: scripts/{}", &path);
 
+                let script = Script::<LuaFile>::new(path, handle);
+                log.reply_ok(format!("Created script with id: {}", script.id()));
                 commands.spawn(()).insert(ScriptCollection::<LuaFile> {
-                    scripts: vec![Script::<LuaFile>::new(path, handle)],
+                    scripts: vec![script],
                 });
             }
         };
@@ -156,6 +160,39 @@ pub struct DeleteScriptCmd {
     pub entity_id: u32,
 }
 
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "eval")]
+///Evaluates Lua code inside the context of a running script
+pub struct EvalCmd {
+    /// the id of the script, as printed by `run_script`
+    pub script_id: u32,
+
+    /// the code to evaluate, e.g.: `"print(world)"`
+    pub code: String,
+}
+
+/// code waiting to be evaluated, console commands cannot access the world exclusively
+/// so the evaluation happens in a separate exclusive system
+#[derive(Resource, Default)]
+pub struct PendingEvals(Vec<EvalCmd>);
+
+pub fn eval_cmd(mut log: ConsoleCommand<EvalCmd>, mut pending: ResMut<PendingEvals>) {
+    if let Some(Ok(cmd)) = log.take() {
+        pending.0.push(cmd);
+    }
+}
+
+pub fn run_pending_evals(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<PendingEvals>().0);
+    for EvalCmd { script_id, code } in pending {
+        let line = match eval_in_script::<LuaScriptHost<()>>(world, script_id, &code) {
+            Ok(output) => output,
+            Err(e) => format!("ERROR:{}", e),
+        };
+        world.send_event(PrintConsoleLine { line: line.into() });
+    }
+}
+
 fn main() -> std::io::Result<()> {
     let mut app = App::new();
     app.add_plugins(DefaultPlugins)
@@ -164,6 +201,8 @@ fn main() -> std::io::Result<()> {
         // register bevy_console commands
         .add_console_command::<RunScriptCmd, _>(run_script_cmd)
         .add_console_command::<DeleteScriptCmd, _>(delete_script_cmd)
+        .add_console_command::<EvalCmd, _>(eval_cmd)
+        .init_resource::<PendingEvals>()
         // choose and register the script hosts you want to use
         .add_script_host::<LuaScriptHost<()>>(PostUpdate)
         .add_api_provider::<LuaScriptHost<()>>(Box::new(LuaAPIProvider))
@@ -171,9 +210,11 @@ fn main() -> std::io::Result<()> {
         .add_script_handler::<LuaScriptHost<()>, 0, 0>(PostUpdate)
         // add your systems
         .add_systems(Update, trigger_on_update_lua)
-        .add_systems(Update, forward_script_err_to_console);
+        .add_systems(Update, forward_script_err_to_console)
+        .add_systems(Update, run_pending_evals.after(eval_cmd));
 
     info!("press '~' to open the console. Type in `run_script \"console_integration.lua\"` to run example script!");
+    info!("Type in `eval <script id> \"<code>\"` to evaluate code inside a running script!");
     app.run();
 
     Ok(())